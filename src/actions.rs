@@ -1,48 +1,322 @@
+use std::borrow::Cow;
+
 use xkbcommon::xkb::Keysym;
 
 use crate::{
-    keyboard::{MODS_ALT, MODS_CTRL, MODS_SHIFT},
+    events::MouseButton,
+    keyboard::{MODS_CTRL, MODS_MOD, MODS_SHIFT},
+    layout::Direction,
     tiling::Layout,
 };
 
 #[derive(Debug, Clone)]
 pub enum ActionType {
     Quit,
+    /// replaces the window manager with a fresh instance of itself, keeping the windows
+    Restart,
     CycleLayout,
     CloseFocusedWindow,
+    /// disconnects the program of the focused window from the X server, for programs that
+    /// don't react to being closed
+    KillFocused,
     SwitchToLayout(Layout),
-    Launch(&'static str),
+    ToggleFloating,
+    /// makes every window of the current workspace float
+    FloatAll,
+    /// puts every floating window of the current workspace back into the layout
+    TileAll,
+    ToggleSticky,
+    /// keeps the focused window above the other windows of its workspace
+    ToggleAbove,
+    ToggleSmartGaps,
+    /// turns the gaps of the current workspace off, or back on
+    ToggleGaps,
+    /// lets the tiled windows use the space reserved by panels and the bar, or gives it back
+    ToggleStruts,
+    /// makes the focused window cover its workspace for a while, without changing the layout
+    ToggleMonocleForFocused,
+    ToggleScratchpad,
+    /// shows the scratchpad without hiding it if it's already shown
+    ShowScratchpad,
+    /// puts the focused window in the scratchpad, or takes the scratchpad out of it
+    MoveToScratchpad,
+    FocusUrgent,
+    /// walks through the windows in most-recently-used order until the modifiers are released
+    CycleWindowMru,
+    /// grows the master column by this many pixels, or shrinks it if negative
+    ResizeMaster(i16),
+    /// resets the master column of the current workspace to its default width
+    BalanceLayout,
+    /// grows the space between tiled windows by this many pixels, or shrinks it if negative
+    InnerGap(i16),
+    /// grows the space to the edge of the screen by this many pixels, or shrinks it if negative
+    OuterGap(i16),
+    /// moves the focused window `delta` workspaces further, wrapping around at either end. with
+    /// `follow`, the destination is switched to as well
+    MoveToWorkspaceRelative {
+        delta: i32,
+        follow: bool,
+    },
+    /// switches to the workspace after the current one, wrapping around at the end
+    NextWorkspace,
+    /// switches to the workspace before the current one, wrapping around at the start
+    PrevWorkspace,
+    /// switches back to the workspace that was shown before the current one
+    LastWorkspace,
+    /// exchanges the windows of the two workspaces, counted from 0
+    SwapWorkspaces(u8, u8),
+    /// renames the current workspace
+    RenameWorkspace(Cow<'static, str>),
+    Minimize,
+    RestoreLast,
+    /// swaps the focused window with the master, or the master with the top of the stack
+    SwapFocusedWithMasterOrStack,
+    FocusDir(Direction),
+    MoveDir(Direction),
+    /// moves every tiled window one place towards the master (up or left) or away from it (down
+    /// or right). the window at the end comes around to the other end
+    RotateStack(Direction),
+    /// runs a program. the arguments are separated from it by whitespace, there is no quoting
+    Launch(Cow<'static, str>),
 }
 
 #[derive(Debug, Clone)]
 pub struct Action {
     pub key: Keysym,
     pub mods: u8,
+    /// the keys that have to be pressed after `key` to trigger the action
+    pub sequence: Cow<'static, [(Keysym, u8)]>,
     pub action: ActionType,
+    /// runs when `key` is released again. only for single key bindings
+    pub release: Option<ActionType>,
 }
 
 impl Action {
     pub const fn new(key: Keysym, mods: u8, action: ActionType) -> Self {
-        Self { key, mods, action }
+        Self {
+            key,
+            mods,
+            sequence: Cow::Borrowed(&[]),
+            action,
+            release: None,
+        }
+    }
+
+    /// an action that runs `action` when the key is pressed and `release` when it is released
+    pub const fn with_release(
+        key: Keysym,
+        mods: u8,
+        action: ActionType,
+        release: ActionType,
+    ) -> Self {
+        Self {
+            key,
+            mods,
+            sequence: Cow::Borrowed(&[]),
+            action,
+            release: Some(release),
+        }
+    }
+
+    /// an action that is triggered by pressing the keys in `sequence` after `key`
+    pub const fn chord(
+        key: Keysym,
+        mods: u8,
+        sequence: &'static [(Keysym, u8)],
+        action: ActionType,
+    ) -> Self {
+        Self {
+            key,
+            mods,
+            sequence: Cow::Borrowed(sequence),
+            action,
+            release: None,
+        }
+    }
+}
+
+/// an action that is triggered by clicking a mouse button anywhere on the screen
+#[derive(Debug, Clone)]
+pub struct MouseAction {
+    pub button: MouseButton,
+    pub mods: u8,
+    pub action: ActionType,
+}
+
+impl MouseAction {
+    pub const fn new(button: MouseButton, mods: u8, action: ActionType) -> Self {
+        Self {
+            button,
+            mods,
+            action,
+        }
     }
 }
 
+pub static MOUSE_ACTIONS: &[MouseAction] = &[
+    MouseAction::new(MouseButton::Back, MODS_MOD, ActionType::PrevWorkspace),
+    MouseAction::new(MouseButton::Forward, MODS_MOD, ActionType::NextWorkspace),
+];
+
 pub static ACTIONS: &[Action] = &[
-    Action::new(Keysym::q, MODS_CTRL | MODS_ALT, ActionType::Quit),
+    Action::new(Keysym::q, MODS_CTRL | MODS_MOD, ActionType::Quit),
+    Action::new(Keysym::r, MODS_CTRL | MODS_MOD, ActionType::Restart),
     Action::new(
         Keysym::q,
-        MODS_SHIFT | MODS_ALT,
+        MODS_SHIFT | MODS_MOD,
         ActionType::CloseFocusedWindow,
     ),
-    Action::new(Keysym::l, MODS_ALT, ActionType::CycleLayout),
+    Action::new(
+        Keysym::q,
+        MODS_CTRL | MODS_SHIFT | MODS_MOD,
+        ActionType::KillFocused,
+    ),
+    Action::new(Keysym::l, MODS_MOD, ActionType::CycleLayout),
+    Action::new(
+        Keysym::space,
+        MODS_SHIFT | MODS_MOD,
+        ActionType::ToggleFloating,
+    ),
+    Action::new(Keysym::space, MODS_CTRL | MODS_MOD, ActionType::FloatAll),
+    Action::new(
+        Keysym::space,
+        MODS_CTRL | MODS_SHIFT | MODS_MOD,
+        ActionType::TileAll,
+    ),
+    Action::new(Keysym::s, MODS_SHIFT | MODS_MOD, ActionType::ToggleSticky),
+    Action::new(Keysym::a, MODS_SHIFT | MODS_MOD, ActionType::ToggleAbove),
+    Action::new(Keysym::g, MODS_MOD, ActionType::ToggleSmartGaps),
+    Action::new(Keysym::g, MODS_SHIFT | MODS_MOD, ActionType::ToggleGaps),
+    Action::new(Keysym::b, MODS_MOD, ActionType::ToggleStruts),
+    Action::new(Keysym::f, MODS_MOD, ActionType::ToggleMonocleForFocused),
+    Action::new(Keysym::grave, MODS_MOD, ActionType::ToggleScratchpad),
+    Action::new(
+        Keysym::grave,
+        MODS_CTRL | MODS_MOD,
+        ActionType::ShowScratchpad,
+    ),
+    Action::new(
+        Keysym::grave,
+        MODS_SHIFT | MODS_MOD,
+        ActionType::MoveToScratchpad,
+    ),
+    Action::new(Keysym::u, MODS_MOD, ActionType::FocusUrgent),
+    Action::new(Keysym::Tab, MODS_MOD, ActionType::CycleWindowMru),
+    Action::new(Keysym::minus, MODS_MOD, ActionType::ResizeMaster(-50)),
+    Action::new(Keysym::equal, MODS_MOD, ActionType::ResizeMaster(50)),
+    Action::new(
+        Keysym::equal,
+        MODS_SHIFT | MODS_MOD,
+        ActionType::BalanceLayout,
+    ),
+    Action::new(Keysym::bracketleft, MODS_MOD, ActionType::InnerGap(-2)),
+    Action::new(Keysym::bracketright, MODS_MOD, ActionType::InnerGap(2)),
+    Action::new(
+        Keysym::bracketleft,
+        MODS_SHIFT | MODS_MOD,
+        ActionType::OuterGap(-2),
+    ),
+    Action::new(
+        Keysym::bracketright,
+        MODS_SHIFT | MODS_MOD,
+        ActionType::OuterGap(2),
+    ),
+    Action::new(
+        Keysym::period,
+        MODS_SHIFT | MODS_MOD,
+        ActionType::MoveToWorkspaceRelative {
+            delta: 1,
+            follow: false,
+        },
+    ),
+    Action::new(
+        Keysym::comma,
+        MODS_SHIFT | MODS_MOD,
+        ActionType::MoveToWorkspaceRelative {
+            delta: -1,
+            follow: false,
+        },
+    ),
+    Action::new(
+        Keysym::period,
+        MODS_CTRL | MODS_MOD,
+        ActionType::MoveToWorkspaceRelative {
+            delta: 1,
+            follow: true,
+        },
+    ),
+    Action::new(
+        Keysym::comma,
+        MODS_CTRL | MODS_MOD,
+        ActionType::MoveToWorkspaceRelative {
+            delta: -1,
+            follow: true,
+        },
+    ),
+    Action::new(Keysym::period, MODS_MOD, ActionType::NextWorkspace),
+    Action::new(Keysym::comma, MODS_MOD, ActionType::PrevWorkspace),
+    Action::new(Keysym::BackSpace, MODS_MOD, ActionType::LastWorkspace),
+    Action::new(Keysym::m, MODS_MOD, ActionType::Minimize),
+    Action::new(Keysym::m, MODS_SHIFT | MODS_MOD, ActionType::RestoreLast),
+    Action::new(
+        Keysym::Left,
+        MODS_MOD,
+        ActionType::FocusDir(Direction::Left),
+    ),
+    Action::new(
+        Keysym::Right,
+        MODS_MOD,
+        ActionType::FocusDir(Direction::Right),
+    ),
+    Action::new(Keysym::Up, MODS_MOD, ActionType::FocusDir(Direction::Up)),
+    Action::new(
+        Keysym::Down,
+        MODS_MOD,
+        ActionType::FocusDir(Direction::Down),
+    ),
+    Action::new(
+        Keysym::Left,
+        MODS_SHIFT | MODS_MOD,
+        ActionType::MoveDir(Direction::Left),
+    ),
+    Action::new(
+        Keysym::Right,
+        MODS_SHIFT | MODS_MOD,
+        ActionType::MoveDir(Direction::Right),
+    ),
+    Action::new(
+        Keysym::Up,
+        MODS_SHIFT | MODS_MOD,
+        ActionType::MoveDir(Direction::Up),
+    ),
+    Action::new(
+        Keysym::Down,
+        MODS_SHIFT | MODS_MOD,
+        ActionType::MoveDir(Direction::Down),
+    ),
+    Action::new(
+        Keysym::Return,
+        MODS_SHIFT | MODS_MOD,
+        ActionType::SwapFocusedWithMasterOrStack,
+    ),
+    Action::new(
+        Keysym::Up,
+        MODS_CTRL | MODS_MOD,
+        ActionType::RotateStack(Direction::Up),
+    ),
+    Action::new(
+        Keysym::Down,
+        MODS_CTRL | MODS_MOD,
+        ActionType::RotateStack(Direction::Down),
+    ),
     Action::new(
         Keysym::p,
-        MODS_ALT,
-        ActionType::Launch("/usr/bin/dmenu_run"),
+        MODS_MOD,
+        ActionType::Launch(Cow::Borrowed("/usr/bin/dmenu_run")),
     ),
     Action::new(
         Keysym::Return,
-        MODS_ALT,
-        ActionType::Launch("/usr/bin/alacritty"),
+        MODS_MOD,
+        ActionType::Launch(Cow::Borrowed("/usr/bin/alacritty")),
     ),
 ];
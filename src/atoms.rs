@@ -30,6 +30,14 @@ macro_rules! atoms {
 
         impl Atoms {
             pub fn get(conn: &Connection) -> Self {
+                // every field is named after its atom, which makes a misaligned entry easy to spot
+                $(debug_assert!(
+                    $x_name.strip_prefix(b"_").unwrap_or($x_name)
+                        .eq_ignore_ascii_case(stringify!($name).as_bytes()),
+                    "atom field `{}` does not match {:?}",
+                    stringify!($name),
+                    String::from_utf8_lossy($x_name),
+                );)*
                 $(let $name = get_internal_atom(conn, $x_name);)*
 
                 return Self {
@@ -51,13 +59,28 @@ atoms! {
     pub struct Atoms {
         wm_protocols = b"WM_PROTOCOLS",
         wm_delete_window = b"WM_DELETE_WINDOW",
+        wm_take_focus = b"WM_TAKE_FOCUS",
+        wm_state = b"WM_STATE",
+        utf8_string = b"UTF8_STRING",
+        compound_text = b"COMPOUND_TEXT",
         net_wm_name = b"_NET_WM_NAME",
         net_wm_state = b"_NET_WM_STATE",
+        net_frame_extents = b"_NET_FRAME_EXTENTS",
         net_wm_state_focused = b"_NET_WM_STATE_FOCUSED",
+        net_wm_state_fullscreen = b"_NET_WM_STATE_FULLSCREEN",
+        net_wm_state_sticky = b"_NET_WM_STATE_STICKY",
+        net_wm_state_above = b"_NET_WM_STATE_ABOVE",
+        net_wm_state_demands_attention = b"_NET_WM_STATE_DEMANDS_ATTENTION",
         net_wm_window_type = b"_NET_WM_WINDOW_TYPE",
+        net_wm_window_type_dialog = b"_NET_WM_WINDOW_TYPE_DIALOG",
+        net_wm_window_type_utility = b"_NET_WM_WINDOW_TYPE_UTILITY",
+        net_wm_window_type_splash = b"_NET_WM_WINDOW_TYPE_SPLASH",
+        net_wm_window_type_toolbar = b"_NET_WM_WINDOW_TYPE_TOOLBAR",
+        net_wm_window_type_dock = b"_NET_WM_WINDOW_TYPE_DOCK",
         net_current_desktop = b"_NET_CURRENT_DESKTOP",
         net_number_of_desktops = b"_NET_NUMBER_OF_DESKTOPS",
         net_wm_desktop = b"_NET_WM_DESKTOP",
+        net_wm_pid = b"_NET_WM_PID",
         net_supported = b"_NET_SUPPORTED",
         net_wm_strut_partial = b"_NET_WM_STRUT_PARTIAL",
         net_wm_strut = b"_NET_WM_STRUT",
@@ -0,0 +1,241 @@
+use std::{ffi::CString, sync::Arc};
+
+use xcb::{
+    x::{
+        ConfigWindow, ConfigureWindow, CreateWindow, Cw, DestroyWindow, EventMask, MapWindow,
+        Window, COPY_FROM_PARENT,
+    },
+    Connection, ProtocolError,
+};
+
+use crate::{
+    config,
+    drawing::{ArgbVisual, DrawContext, ARGB_DEPTH},
+    layout::Position,
+    tiling::Layout,
+};
+
+const FONT: &str = "fixed";
+const PADDING: u16 = 6;
+
+/// everything the bar displays
+pub struct BarContent<'a> {
+    /// the ids of all workspaces, whether they have windows and whether one of them is urgent
+    pub workspaces: &'a [(u32, bool, bool)],
+    pub current_workspace: usize,
+    pub layout: Layout,
+    /// the amount of windows on the current workspace
+    pub window_count: usize,
+    pub title: &'a str,
+}
+
+/// the status bar at the top of the screen
+pub struct Bar {
+    window: Window,
+    width: u16,
+    draw: DrawContext,
+    conn: Arc<Connection>,
+    /// the width of the widest clock drawn so far, so a shorter one covers it up completely
+    clock_width: u16,
+    /// where each workspace label starts and ends, in the order of the workspaces
+    workspace_labels: Vec<(u16, u16)>,
+}
+
+impl Bar {
+    /// creates the bar with the depth of the root window, or as a 32-bit window if `argb` is given
+    pub fn new(
+        root_window: Window,
+        width: u16,
+        conn: Arc<Connection>,
+        depth: u8,
+        argb: Option<ArgbVisual>,
+    ) -> Result<Self, ProtocolError> {
+        let window = conn.generate_id();
+        let (depth, visual, colormap) = match argb {
+            Some(argb) => (ARGB_DEPTH, argb.visual, Some(argb.colormap)),
+            None => (depth, COPY_FROM_PARENT, None),
+        };
+        let mut value_list = vec![
+            Cw::BackPixel(config::BAR_COLOR),
+            // a window of a different depth than its parent can't copy the parent's border
+            Cw::BorderPixel(0),
+            Cw::OverrideRedirect(true),
+            Cw::EventMask(EventMask::EXPOSURE | EventMask::BUTTON_PRESS),
+        ];
+        value_list.extend(colormap.map(Cw::Colormap));
+        conn.send_and_check_request(&CreateWindow {
+            depth,
+            wid: window,
+            parent: root_window,
+            x: 0,
+            y: 0,
+            width,
+            height: config::BAR_HEIGHT,
+            border_width: 0,
+            class: xcb::x::WindowClass::InputOutput,
+            visual,
+            value_list: &value_list,
+        })?;
+
+        let mut draw = match DrawContext::new(
+            window,
+            Position::new(0, 0, width, config::BAR_HEIGHT),
+            conn.clone(),
+            depth,
+        ) {
+            Ok(draw) => draw,
+            Err(e) => {
+                _ = conn.send_and_check_request(&DestroyWindow { window });
+                return Err(e);
+            }
+        };
+        draw.open_font(FONT)?;
+        conn.send_and_check_request(&MapWindow { window })?;
+
+        Ok(Self {
+            window,
+            width,
+            draw,
+            conn,
+            clock_width: 0,
+            workspace_labels: vec![],
+        })
+    }
+
+    pub fn window(&self) -> Window {
+        self.window
+    }
+
+    /// makes the bar as wide as the screen again
+    pub fn resize(&mut self, width: u16) -> Result<(), ProtocolError> {
+        self.conn.send_and_check_request(&ConfigureWindow {
+            window: self.window,
+            value_list: &[ConfigWindow::Width(width as u32)],
+        })?;
+        self.draw
+            .resize(Position::new(0, 0, width, config::BAR_HEIGHT))?;
+        self.width = width;
+        Ok(())
+    }
+
+    /// the index of the workspace whose label was drawn at `x`
+    pub fn workspace_at(&self, x: u16) -> Option<usize> {
+        self.workspace_labels
+            .iter()
+            .position(|&(start, end)| (start..end).contains(&x))
+    }
+
+    pub fn draw(&mut self, content: &BarContent) -> anyhow::Result<()> {
+        self.draw.draw_rect(
+            Position::new(0, 0, self.width, config::BAR_HEIGHT),
+            config::BAR_COLOR,
+            config::BAR_COLOR,
+        )?;
+
+        let mut x = 0;
+        self.workspace_labels.clear();
+        for (i, &(id, has_windows, urgent)) in content.workspaces.iter().enumerate() {
+            let label = id.to_string();
+            let label_width = self.draw.text_extents(&label)?.0 + PADDING * 2;
+            let (fg, bg) = if i == content.current_workspace {
+                (config::TITLE_COLOR, config::BORDER_COLOR_ACTIVE)
+            } else if urgent {
+                (config::TITLE_COLOR, config::BORDER_COLOR_URGENT)
+            } else if has_windows {
+                (config::TITLE_COLOR, config::BAR_COLOR)
+            } else {
+                (config::BAR_INACTIVE_COLOR, config::BAR_COLOR)
+            };
+
+            let area = Position::new(x, 0, label_width, config::BAR_HEIGHT);
+            self.draw.draw_rect(area, bg, bg)?;
+            let (text_x, text_y) = self.draw.centered_text(&label, area)?;
+            self.draw.draw_string(text_x, text_y, &label, fg, bg)?;
+            self.workspace_labels.push((x, x + label_width));
+            x += label_width;
+        }
+
+        x += PADDING;
+        let layout = format!("{} {}", content.layout, content.window_count);
+        let (layout_width, ascent, descent) = self.draw.text_extents(&layout)?;
+        let text_y = (config::BAR_HEIGHT as i16 + ascent - descent) / 2;
+        self.draw.draw_string(
+            x as i16,
+            text_y,
+            &layout,
+            config::TITLE_COLOR,
+            config::BAR_COLOR,
+        )?;
+        x += layout_width + PADDING * 2;
+
+        self.draw.draw_string(
+            x as i16,
+            text_y,
+            content.title,
+            config::TITLE_COLOR,
+            config::BAR_COLOR,
+        )?;
+        self.draw_clock()?;
+
+        self.draw.finalise()?;
+        Ok(())
+    }
+
+    /// redraws only the clock
+    pub fn update_clock(&mut self) -> anyhow::Result<()> {
+        self.draw_clock()?;
+        self.draw.finalise()?;
+        Ok(())
+    }
+
+    fn draw_clock(&mut self) -> anyhow::Result<()> {
+        let clock = clock_text(config::CLOCK_FORMAT);
+        let (clock_width, ascent, descent) = self.draw.text_extents(&clock)?;
+        self.clock_width = self.clock_width.max(clock_width + PADDING * 2);
+        let x = self.width.saturating_sub(self.clock_width);
+        self.draw.draw_rect(
+            Position::new(x, 0, self.clock_width, config::BAR_HEIGHT),
+            config::BAR_COLOR,
+            config::BAR_COLOR,
+        )?;
+        let text_y = (config::BAR_HEIGHT as i16 + ascent - descent) / 2;
+        self.draw.draw_string(
+            self.width.saturating_sub(clock_width + PADDING) as i16,
+            text_y,
+            &clock,
+            config::TITLE_COLOR,
+            config::BAR_COLOR,
+        )?;
+        Ok(())
+    }
+}
+
+/// formats the current local time with strftime(3)
+fn clock_text(format: &str) -> String {
+    let Ok(format) = CString::new(format) else {
+        return String::new();
+    };
+    let mut buf = [0u8; 128];
+    let len = unsafe {
+        let now = libc::time(std::ptr::null_mut());
+        let mut tm = std::mem::zeroed::<libc::tm>();
+        if libc::localtime_r(&now, &mut tm).is_null() {
+            return String::new();
+        }
+        libc::strftime(
+            buf.as_mut_ptr() as *mut libc::c_char,
+            buf.len(),
+            format.as_ptr(),
+            &tm,
+        )
+    };
+    String::from_utf8_lossy(&buf[..len]).into_owned()
+}
+
+impl Drop for Bar {
+    fn drop(&mut self) {
+        _ = self.conn.send_and_check_request(&DestroyWindow {
+            window: self.window,
+        });
+    }
+}
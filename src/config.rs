@@ -1,10 +1,27 @@
-use std::path::PathBuf;
+use std::{borrow::Cow, path::PathBuf, str::FromStr, time::Duration};
+
+use anyhow::Context;
+use tracing::{error, info, Level};
+use xkbcommon::xkb::{keysym_from_name, Keysym, KEYSYM_CASE_INSENSITIVE, KEYSYM_NO_FLAGS};
+
+use crate::{
+    actions::{self, Action, ActionType},
+    keyboard::{MODS_ALT, MODS_CTRL, MODS_HYPER, MODS_META, MODS_MOD, MODS_SHIFT, MODS_SUPER},
+    layout::Direction,
+    screen::Client,
+    tiling::Layout,
+};
 
 static APP_NAME: &str = "wm";
 
 static XDG_HOME: &str = "HOME";
 static XDG_CONFIG_HOME: &str = "XDG_CONFIG_HOME";
 static XDG_DATA_DIR: &str = "XDG_DATA_HOME";
+static XDG_RUNTIME_DIR: &str = "XDG_RUNTIME_DIR";
+/// overrides `LOG_LEVEL`
+static LOG_LEVEL_VAR: &str = "WM_LOG";
+/// the X screen to manage, overriding the one $DISPLAY names
+static SCREEN_VAR: &str = "WM_SCREEN";
 
 fn get_data_dir() -> anyhow::Result<PathBuf> {
     match std::env::var(XDG_DATA_DIR).map(PathBuf::from) {
@@ -37,8 +54,752 @@ pub fn get_log_file() -> anyhow::Result<(PathBuf, String)> {
     Ok((get_data_dir()?, format!("{}.log", APP_NAME)))
 }
 
-pub const GAP_SIZE: u16 = 2;
+/// the most verbose level that ends up in the log file, read from $WM_LOG and `LOG_LEVEL`
+/// otherwise. an invalid value falls back to `LOG_LEVEL`, the error is returned so that it can be
+/// logged once logging is set up
+pub fn get_log_level() -> (Level, Option<anyhow::Error>) {
+    let Ok(value) = std::env::var(LOG_LEVEL_VAR) else {
+        return (LOG_LEVEL, None);
+    };
+    match parse_log_level(&value) {
+        Ok(level) => (level, None),
+        Err(e) => (
+            LOG_LEVEL,
+            Some(e.context(format!("invalid ${LOG_LEVEL_VAR}"))),
+        ),
+    }
+}
+
+/// the index of the X screen to manage from $WM_SCREEN, or None if it isn't set. useful for
+/// nested servers and setups with separate X screens
+pub fn get_screen_index() -> anyhow::Result<Option<usize>> {
+    let Ok(value) = std::env::var(SCREEN_VAR) else {
+        return Ok(None);
+    };
+    let index = value
+        .trim()
+        .parse()
+        .with_context(|| format!("invalid ${SCREEN_VAR} {value:?}"))?;
+    Ok(Some(index))
+}
+
+fn parse_log_level(name: &str) -> anyhow::Result<Level> {
+    Ok(match name.trim().to_ascii_lowercase().as_str() {
+        "trace" => Level::TRACE,
+        "debug" => Level::DEBUG,
+        "info" => Level::INFO,
+        "warn" | "warning" => Level::WARN,
+        "error" => Level::ERROR,
+        _ => anyhow::bail!("unknown log level {name:?}"),
+    })
+}
+
+/// the file the arrangement of the windows is saved in
+pub fn get_session_file() -> anyhow::Result<PathBuf> {
+    Ok(get_data_dir()?.join("session"))
+}
+
+/// the path of the control socket. it lives in $XDG_RUNTIME_DIR, or the data directory if that
+/// isn't set
+pub fn get_socket_path() -> anyhow::Result<PathBuf> {
+    let mut path = match std::env::var(XDG_RUNTIME_DIR).map(PathBuf::from) {
+        Ok(path) => path,
+        Err(_) => get_data_dir()?,
+    };
+    path.push(format!("{}.sock", APP_NAME));
+    Ok(path)
+}
+
+fn get_config_dir() -> anyhow::Result<PathBuf> {
+    if let Ok(mut path) = std::env::var(XDG_CONFIG_HOME).map(PathBuf::from) {
+        path.push(APP_NAME);
+        return Ok(path);
+    }
+
+    if let Ok(mut path) = std::env::var(XDG_HOME).map(PathBuf::from) {
+        path.push(".config");
+        path.push(APP_NAME);
+        return Ok(path);
+    }
+
+    anyhow::bail!("failed to get the $HOME variable");
+}
+
+pub fn get_config_file() -> anyhow::Result<PathBuf> {
+    let mut path = get_config_dir()?;
+    path.push("config");
+    Ok(path)
+}
+
+/// reads the keybindings from the config file, falling back to the built-in ones if there is no
+/// config file or it is invalid
+pub fn load_actions() -> Vec<Action> {
+    match read_actions() {
+        Ok(Some(actions)) => actions,
+        Ok(None) => {
+            info!("No config file found, using the default keybindings");
+            actions::ACTIONS.to_vec()
+        }
+        Err(e) => {
+            error!("Failed to load the config, using the default keybindings: {e:?}");
+            actions::ACTIONS.to_vec()
+        }
+    }
+}
+
+/// parses the config file without starting the window manager, for `--check-config`. every
+/// invalid line is printed to stderr, and an error is returned if there was one
+pub fn check_config() -> anyhow::Result<()> {
+    let path = get_config_file()?;
+    if !path.exists() {
+        eprintln!(
+            "{}: no config file, the default keybindings are used",
+            path.display()
+        );
+        return Ok(());
+    }
+    let source = std::fs::read_to_string(&path)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+
+    let (actions, errors) = parse_actions_lenient(&source);
+    for e in &errors {
+        eprintln!("{}: {e:#}", path.display());
+    }
+    if !errors.is_empty() {
+        anyhow::bail!("{} invalid line(s) in {}", errors.len(), path.display());
+    }
+    eprintln!("{}: {} keybindings, ok", path.display(), actions.len());
+    Ok(())
+}
+
+/// applies the `set` lines of the config file. invalid ones are skipped
+pub fn load_settings(config: &mut Config) {
+    let source = match read_config_file() {
+        Ok(Some(source)) => source,
+        Ok(None) => return,
+        Err(e) => {
+            error!("Failed to load the settings: {e:?}");
+            return;
+        }
+    };
+
+    for (i, line) in source.lines().enumerate() {
+        let Some(setting) = line.trim().strip_prefix("set ") else {
+            continue;
+        };
+        match parse_setting(setting) {
+            Ok(setting) => setting.apply(config),
+            Err(e) => error!("Skipping line {} of the config: {e:#}", i + 1),
+        }
+    }
+}
+
+fn read_actions() -> anyhow::Result<Option<Vec<Action>>> {
+    read_config_file()?
+        .map(|source| parse_actions(&source))
+        .transpose()
+}
+
+/// the contents of the config file, or None if there is none
+fn read_config_file() -> anyhow::Result<Option<String>> {
+    let path = get_config_file()?;
+    if !path.exists() {
+        return Ok(None);
+    }
+    std::fs::read_to_string(&path)
+        .with_context(|| format!("failed to read {}", path.display()))
+        .map(Some)
+}
+
+/// parses lines like `Super+Shift+q = close`. empty lines and lines starting with `#` are ignored.
+/// a line like `release Super+x = toggle-scratchpad` sets what happens when the key of the
+/// binding above it with the same key is released. `set` lines are only checked, they are applied
+/// by `load_settings`
+fn parse_actions(source: &str) -> anyhow::Result<Vec<Action>> {
+    let (actions, errors) = parse_actions_lenient(source);
+    match errors.into_iter().next() {
+        Some(e) => Err(e),
+        None => Ok(actions),
+    }
+}
+
+/// like `parse_actions`, but skips invalid lines and returns their errors
+fn parse_actions_lenient(source: &str) -> (Vec<Action>, Vec<anyhow::Error>) {
+    let mut actions: Vec<Action> = vec![];
+    let mut errors = vec![];
+
+    for (i, line) in source.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let result = if let Some(release) = line.strip_prefix("release ") {
+            parse_release(release, &mut actions)
+        } else if let Some(setting) = line.strip_prefix("set ") {
+            parse_setting(setting).map(|_| ())
+        } else {
+            parse_action(line).map(|action| actions.push(action))
+        };
+        if let Err(e) = result {
+            errors.push(e.context(format!("line {}: {line}", i + 1)));
+        }
+    }
+
+    (actions, errors)
+}
+
+/// sets the release action of the last binding with the same key
+fn parse_release(line: &str, actions: &mut [Action]) -> anyhow::Result<()> {
+    let release = parse_action(line)?;
+    let action = actions
+        .iter_mut()
+        .rev()
+        .find(|action| action.key == release.key && action.mods == release.mods)
+        .context("there is no binding for the key before this")?;
+    if !action.sequence.is_empty() || !release.sequence.is_empty() {
+        anyhow::bail!("key sequences can't have a release action");
+    }
+    action.release = Some(release.action);
+    Ok(())
+}
+
+/// an option of the config file, set with a line like `set new-window-position = stack-bottom`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Setting {
+    NewWindowPosition(NewWindowPosition),
+}
+
+impl Setting {
+    pub fn apply(self, config: &mut Config) {
+        match self {
+            Self::NewWindowPosition(position) => config.new_window_position = position,
+        }
+    }
+}
+
+fn parse_setting(line: &str) -> anyhow::Result<Setting> {
+    let (name, value) = line
+        .split_once('=')
+        .context("expected `set <option> = <value>`")?;
+    let (name, value) = (name.trim(), value.trim());
+    Ok(match name {
+        "new-window-position" => Setting::NewWindowPosition(value.parse()?),
+        _ => anyhow::bail!("unknown option {name:?}"),
+    })
+}
+
+/// parses a binding. a key sequence is written as keys separated by whitespace, like
+/// `Super+w c = close`
+fn parse_action(line: &str) -> anyhow::Result<Action> {
+    let (keys, action) = line
+        .split_once('=')
+        .context("expected `<keys> = <action>`")?;
+
+    // join the parts of a key that were spaced out like `Super + w`
+    let mut steps: Vec<String> = vec![];
+    for part in keys.split_whitespace() {
+        match steps.last_mut() {
+            Some(last) if last.ends_with('+') || part.starts_with('+') => last.push_str(part),
+            _ => steps.push(part.to_string()),
+        }
+    }
+    let mut steps = steps.iter().map(|step| parse_key(step));
+    let (key, mods) = steps.next().context("missing a key")??;
+    let sequence = steps.collect::<anyhow::Result<Vec<_>>>()?;
+
+    let mut action = Action::new(key, mods, parse_action_type(action.trim())?);
+    action.sequence = Cow::Owned(sequence);
+    Ok(action)
+}
+
+/// parses a single key with its modifiers, like `Super+Shift+q`
+fn parse_key(keys: &str) -> anyhow::Result<(Keysym, u8)> {
+    let mut mods = 0;
+    let mut key = None;
+    for part in keys.split('+').map(str::trim) {
+        if key.is_some() {
+            anyhow::bail!("the key has to come after the modifiers");
+        }
+        match part.to_ascii_lowercase().as_str() {
+            "ctrl" | "control" => mods |= MODS_CTRL,
+            "shift" => mods |= MODS_SHIFT,
+            "alt" | "mod1" => mods |= MODS_ALT,
+            "super" | "mod4" | "win" => mods |= MODS_SUPER,
+            "meta" => mods |= MODS_META,
+            "hyper" => mods |= MODS_HYPER,
+            "mod" => mods |= MODS_MOD,
+            _ => key = Some(parse_keysym(part)?),
+        }
+    }
+    let key = key.context("missing a key")?;
+
+    Ok((key, mods))
+}
+
+fn parse_keysym(name: &str) -> anyhow::Result<Keysym> {
+    if name.is_empty() || name.contains('\0') {
+        anyhow::bail!("invalid key name {name:?}");
+    }
+
+    let mut keysym = keysym_from_name(name, KEYSYM_NO_FLAGS);
+    if keysym == Keysym::NoSymbol {
+        keysym = keysym_from_name(name, KEYSYM_CASE_INSENSITIVE);
+    }
+    if keysym == Keysym::NoSymbol {
+        anyhow::bail!("unknown key {name:?}");
+    }
+
+    Ok(keysym)
+}
+
+pub fn parse_layout(name: &str) -> anyhow::Result<Layout> {
+    Ok(name.parse()?)
+}
+
+fn parse_direction(name: &str) -> anyhow::Result<Direction> {
+    Ok(match name {
+        "left" => Direction::Left,
+        "right" => Direction::Right,
+        "up" => Direction::Up,
+        "down" => Direction::Down,
+        _ => anyhow::bail!("unknown direction {name:?}"),
+    })
+}
+
+fn parse_pixels(amount: &str) -> anyhow::Result<i16> {
+    amount
+        .parse()
+        .map_err(|_| anyhow::anyhow!("invalid amount of pixels {amount:?}"))
+}
+
+/// parses the number of a workspace as shown in the bar, starting at 1, into its index
+fn parse_workspace(number: &str) -> anyhow::Result<u8> {
+    match number.parse::<u8>() {
+        Ok(number) if number > 0 => Ok(number - 1),
+        _ => anyhow::bail!("invalid workspace {number:?}"),
+    }
+}
+
+fn parse_action_type(action: &str) -> anyhow::Result<ActionType> {
+    let (name, argument) = match action.split_once(char::is_whitespace) {
+        Some((name, argument)) => (name, Some(argument.trim())),
+        None => (action, None),
+    };
+
+    Ok(match (name, argument) {
+        ("quit", None) => ActionType::Quit,
+        ("restart", None) => ActionType::Restart,
+        ("close", None) => ActionType::CloseFocusedWindow,
+        ("kill", None) => ActionType::KillFocused,
+        ("cycle-layout", None) => ActionType::CycleLayout,
+        ("toggle-floating", None) => ActionType::ToggleFloating,
+        ("float-all", None) => ActionType::FloatAll,
+        ("tile-all", None) => ActionType::TileAll,
+        ("toggle-sticky", None) => ActionType::ToggleSticky,
+        ("toggle-above", None) => ActionType::ToggleAbove,
+        ("toggle-smart-gaps", None) => ActionType::ToggleSmartGaps,
+        ("toggle-gaps", None) => ActionType::ToggleGaps,
+        ("toggle-struts", None) => ActionType::ToggleStruts,
+        ("toggle-expand", None) => ActionType::ToggleMonocleForFocused,
+        ("toggle-scratchpad", None) => ActionType::ToggleScratchpad,
+        ("show-scratchpad", None) => ActionType::ShowScratchpad,
+        ("move-to-scratchpad", None) => ActionType::MoveToScratchpad,
+        ("focus-urgent", None) => ActionType::FocusUrgent,
+        ("cycle-windows", None) => ActionType::CycleWindowMru,
+        ("move-to-next-workspace", None) => ActionType::MoveToWorkspaceRelative {
+            delta: 1,
+            follow: false,
+        },
+        ("move-to-prev-workspace", None) => ActionType::MoveToWorkspaceRelative {
+            delta: -1,
+            follow: false,
+        },
+        ("follow-to-next-workspace", None) => ActionType::MoveToWorkspaceRelative {
+            delta: 1,
+            follow: true,
+        },
+        ("follow-to-prev-workspace", None) => ActionType::MoveToWorkspaceRelative {
+            delta: -1,
+            follow: true,
+        },
+        ("next-workspace", None) => ActionType::NextWorkspace,
+        ("prev-workspace", None) => ActionType::PrevWorkspace,
+        ("last-workspace", None) => ActionType::LastWorkspace,
+        ("minimize", None) => ActionType::Minimize,
+        ("restore", None) => ActionType::RestoreLast,
+        ("swap-master", None) => ActionType::SwapFocusedWithMasterOrStack,
+        ("balance", None) => ActionType::BalanceLayout,
+        ("focus", Some(direction)) => ActionType::FocusDir(parse_direction(direction)?),
+        ("move", Some(direction)) => ActionType::MoveDir(parse_direction(direction)?),
+        ("rotate", Some(direction)) => ActionType::RotateStack(parse_direction(direction)?),
+        ("layout", Some(layout)) => ActionType::SwitchToLayout(parse_layout(layout)?),
+        ("resize-master", Some(delta)) => ActionType::ResizeMaster(parse_pixels(delta)?),
+        ("inner-gap", Some(delta)) => ActionType::InnerGap(parse_pixels(delta)?),
+        ("outer-gap", Some(delta)) => ActionType::OuterGap(parse_pixels(delta)?),
+        ("launch", Some(command)) => ActionType::Launch(Cow::Owned(command.to_string())),
+        ("rename-workspace", Some(name)) => {
+            ActionType::RenameWorkspace(Cow::Owned(name.to_string()))
+        }
+        ("swap-workspaces", Some(workspaces)) => {
+            let (a, b) = workspaces
+                .split_once(char::is_whitespace)
+                .context("expected two workspace numbers")?;
+            ActionType::SwapWorkspaces(parse_workspace(a)?, parse_workspace(b.trim())?)
+        }
+        (
+            "layout" | "launch" | "focus" | "move" | "rotate" | "resize-master" | "inner-gap"
+            | "outer-gap" | "swap-workspaces" | "rename-workspace",
+            None,
+        ) => {
+            anyhow::bail!("`{name}` is missing an argument")
+        }
+        (
+            "quit"
+            | "restart"
+            | "close"
+            | "kill"
+            | "cycle-layout"
+            | "toggle-floating"
+            | "float-all"
+            | "tile-all"
+            | "toggle-sticky"
+            | "toggle-above"
+            | "toggle-smart-gaps"
+            | "toggle-gaps"
+            | "toggle-struts"
+            | "toggle-expand"
+            | "toggle-scratchpad"
+            | "show-scratchpad"
+            | "move-to-scratchpad"
+            | "focus-urgent"
+            | "cycle-windows"
+            | "move-to-next-workspace"
+            | "move-to-prev-workspace"
+            | "follow-to-next-workspace"
+            | "follow-to-prev-workspace"
+            | "next-workspace"
+            | "prev-workspace"
+            | "last-workspace"
+            | "minimize"
+            | "restore"
+            | "swap-master"
+            | "balance",
+            Some(_),
+        ) => anyhow::bail!("`{name}` doesn't take an argument"),
+        _ => anyhow::bail!("unknown action {name:?}"),
+    })
+}
+
+/// the most verbose level that is logged, unless $WM_LOG says otherwise
+pub const LOG_LEVEL: Level = Level::INFO;
+
+/// the space between tiled windows
+pub const INNER_GAP: u16 = 2;
+/// the space between the tiled windows and the edge of the screen
+pub const OUTER_GAP: u16 = 1;
+/// a window that is alone on its workspace fills it without gaps or a border
+pub const SMART_GAPS: bool = false;
 
 pub const BORDER_SIZE: u16 = 2;
 pub const BORDER_COLOR: u32 = 0xff252525;
 pub const BORDER_COLOR_ACTIVE: u32 = 0xff2D4F67;
+/// the border of windows that want attention
+pub const BORDER_COLOR_URGENT: u32 = 0xffa03030;
+
+pub const WORKSPACES: usize = 10;
+/// the names of the first workspaces. the others are called `Desktop N`
+pub const WORKSPACE_NAMES: &[&str] = &[];
+/// the layouts the first workspaces start with. the others start with `DEFAULT_LAYOUT`
+pub const WORKSPACE_LAYOUTS: &[Layout] = &[];
+pub const DEFAULT_LAYOUT: Layout = Layout::Grid;
+
+/// the appearance settings that can change at runtime. the defaults are the constants above
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Config {
+    pub border_size: u16,
+    pub border_color: u32,
+    pub border_color_active: u32,
+    pub border_color_urgent: u32,
+    pub inner_gap: u16,
+    pub outer_gap: u16,
+    pub smart_gaps: bool,
+    pub new_window_position: NewWindowPosition,
+    /// the amount of workspaces, between 1 and 255
+    pub workspaces: usize,
+    pub workspace_names: &'static [&'static str],
+    pub workspace_layouts: &'static [Layout],
+}
+
+impl Config {
+    /// the name of the workspace with the given id, starting at 1
+    pub fn workspace_name(&self, id: u32) -> String {
+        match self.workspace_names.get(id as usize - 1) {
+            Some(name) => name.to_string(),
+            None => format!("Desktop {id}"),
+        }
+    }
+
+    /// the layout the workspace with the given id, starting at 1, starts with
+    pub fn workspace_layout(&self, id: u32) -> Layout {
+        self.workspace_layouts
+            .get(id as usize - 1)
+            .copied()
+            .unwrap_or(DEFAULT_LAYOUT)
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            border_size: BORDER_SIZE,
+            border_color: BORDER_COLOR,
+            border_color_active: BORDER_COLOR_ACTIVE,
+            border_color_urgent: BORDER_COLOR_URGENT,
+            inner_gap: INNER_GAP,
+            outer_gap: OUTER_GAP,
+            smart_gaps: SMART_GAPS,
+            new_window_position: NEW_WINDOW_POSITION,
+            workspaces: WORKSPACES,
+            workspace_names: WORKSPACE_NAMES,
+            workspace_layouts: WORKSPACE_LAYOUTS,
+        }
+    }
+}
+
+pub const TITLE_COLOR: u32 = 0xffffffff;
+/// the color of the glyph in the title bar that marks the master, floating and fullscreen windows
+pub const TITLE_ROLE_COLOR: u32 = 0xffe0a040;
+/// draw a line at the top of the title bar of the focused window, which is easier to spot than
+/// the border color alone
+pub const ACTIVE_INDICATOR: bool = false;
+pub const ACTIVE_INDICATOR_COLOR: u32 = 0xff7fb4ca;
+pub const ACTIVE_INDICATOR_HEIGHT: u16 = 2;
+
+pub const BAR_HEIGHT: u16 = 25;
+pub const BAR_COLOR: u32 = 0xff151515;
+pub const BAR_INACTIVE_COLOR: u32 = 0xff808080;
+/// draw the bar with an alpha channel, so that a compositor can make it semi-transparent. the
+/// transparency comes from the alpha byte of the bar colors
+pub const BAR_ARGB: bool = false;
+
+/// the strftime(3) format of the clock on the right side of the bar
+pub const CLOCK_FORMAT: &str = "%a %d %b %H:%M";
+
+/// warp the pointer onto the focused window after rearranging windows, so that
+/// focus-follows-mouse doesn't hand focus to whatever window slid under the cursor
+pub const WARP_POINTER: bool = true;
+
+/// move the pointer to the middle of the workspace that is switched to. the server sends the
+/// window that ends up under it an EnterNotify, so focus-follows-mouse picks it up
+pub const WARP_POINTER_ON_SWITCH: bool = false;
+
+/// focus windows by hovering over them. when disabled, windows are focused by clicking on them
+pub const FOCUS_FOLLOWS_MOUSE: bool = true;
+
+/// focus windows when they are opened. dialogs and transient windows are focused either way
+pub const FOCUS_NEW_WINDOWS: bool = true;
+
+/// switching to the next or previous workspace leaves out the ones without windows
+pub const SKIP_EMPTY_WORKSPACES: bool = true;
+
+/// where new tiled windows are put in the layout
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NewWindowPosition {
+    /// in front of all other windows, which makes it the master in the master layouts
+    Master,
+    /// right next to the focused window. if that is floating or there is none, it becomes the
+    /// master
+    NextToFocused,
+    /// behind all other windows, at the bottom of the stack
+    StackBottom,
+}
+
+impl FromStr for NewWindowPosition {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "master" => Self::Master,
+            "next-to-focused" => Self::NextToFocused,
+            "stack-bottom" => Self::StackBottom,
+            _ => anyhow::bail!(
+                "invalid window position {s:?}, expected master, next-to-focused or stack-bottom"
+            ),
+        })
+    }
+}
+
+/// where new tiled windows go, unless the config file sets `new-window-position`
+pub const NEW_WINDOW_POSITION: NewWindowPosition = NewWindowPosition::Master;
+
+/// the modifier `Mod` in key bindings stands for, `MODS_ALT` or `MODS_SUPER`
+pub const MOD_KEY: u8 = MODS_ALT;
+
+/// the modifier that has to be held to drag floating windows around with the mouse
+pub const DRAG_MODS: u8 = MODS_ALT;
+
+/// how long to wait for the next key of a key sequence before giving up on it
+pub const CHORD_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// how far floating windows are moved by the keyboard
+pub const MOVE_STEP: u16 = 20;
+/// how far each window is moved from the previous one when all windows are floated at once, or
+/// when new floating windows are opened without a position
+pub const CASCADE_STEP: u16 = 30;
+
+/// the smallest size (excluding the borders) a floating window can be resized to
+pub const MIN_WINDOW_SIZE: u16 = 40;
+
+/// the smallest width the master and the stack column of the master layouts can be resized to
+pub const MIN_COLUMN_WIDTH: u16 = 100;
+/// how close to the boundary between the master and the stack column a click has to be to drag
+/// the boundary
+pub const MASTER_EDGE_GRAB_DISTANCE: u16 = 8;
+
+/// hide a terminal while a window that was started from it is open, and put that window in its
+/// place
+pub const SWALLOW: bool = false;
+/// the WM_CLASS of the windows that can be swallowed
+pub const SWALLOW_CLASSES: &[&str] = &["Alacritty", "st-256color", "XTerm"];
+
+/// the programs that are started with their arguments when the window manager starts, like
+/// `&["/usr/bin/picom", "-b"]`. they aren't started again on a restart
+pub const AUTOSTART: &[&[&str]] = &[];
+
+/// the program that is started the first time the scratchpad is toggled, with its arguments
+pub const SCRATCHPAD_COMMAND: &[&str] = &["/usr/bin/alacritty", "--class", "scratchpad"];
+/// the WM_CLASS the scratchpad window is recognized by
+pub const SCRATCHPAD_CLASS: &str = "scratchpad";
+
+/// decides where new windows go, based on their WM_CLASS
+pub struct WindowRule {
+    /// matched against the instance and class name of the window
+    pub class: &'static str,
+    /// the id of the workspace the window should be put on, starting at 1
+    pub workspace: Option<u32>,
+    pub floating: bool,
+    pub fullscreen: bool,
+}
+
+impl WindowRule {
+    pub fn matches(&self, client: &Client) -> bool {
+        client.instance.contains(self.class) || client.class.contains(self.class)
+    }
+}
+
+/// the first matching rule is applied. for example, to put firefox on the second workspace:
+///
+/// ```ignore
+/// WindowRule {
+///     class: "firefox",
+///     workspace: Some(2),
+///     floating: false,
+///     fullscreen: false,
+/// }
+/// ```
+pub static WINDOW_RULES: &[WindowRule] = &[];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_come_after_their_modifiers() {
+        assert_eq!(
+            parse_key("Super+Shift+q").unwrap(),
+            (Keysym::q, MODS_SUPER | MODS_SHIFT)
+        );
+        assert_eq!(
+            parse_key("ctrl+mod+Return").unwrap(),
+            (Keysym::Return, MODS_CTRL | MODS_MOD)
+        );
+        assert_eq!(parse_key("Meta+Hyper+a").unwrap().1, MODS_META | MODS_HYPER);
+        assert!(parse_key("q+Shift").is_err());
+        assert!(parse_key("Shift").is_err());
+        assert!(parse_key("Super+NoSuchKey").is_err());
+    }
+
+    #[test]
+    fn bindings_can_be_key_sequences() {
+        let action = parse_action("Super + w  c = close").unwrap();
+        assert_eq!((action.key, action.mods), (Keysym::w, MODS_SUPER));
+        assert_eq!(action.sequence.as_ref(), [(Keysym::c, 0)]);
+        assert!(matches!(action.action, ActionType::CloseFocusedWindow));
+
+        assert!(parse_action("Super+w close").is_err());
+        assert!(parse_action(" = close").is_err());
+    }
+
+    #[test]
+    fn launch_keeps_the_arguments() {
+        let action = parse_action("Super+Return = launch alacritty -e htop").unwrap();
+        assert!(
+            matches!(&action.action, ActionType::Launch(command) if command == "alacritty -e htop")
+        );
+    }
+
+    #[test]
+    fn release_lines_belong_to_the_binding_above() {
+        let actions = parse_actions(
+            "# the scratchpad is shown while the key is held\n\
+             Super+x = show-scratchpad\n\
+             \n\
+             release Super+x = toggle-scratchpad\n",
+        )
+        .unwrap();
+        assert_eq!(actions.len(), 1);
+        assert!(matches!(
+            actions[0].release,
+            Some(ActionType::ToggleScratchpad)
+        ));
+
+        assert!(parse_actions("release Super+x = close").is_err());
+        assert!(parse_actions("Super+x y = close\nrelease Super+x = close").is_err());
+    }
+
+    #[test]
+    fn arguments_are_checked() {
+        let error = |action| parse_action_type(action).unwrap_err().to_string();
+        assert_eq!(error("layout"), "`layout` is missing an argument");
+        assert_eq!(error("close now"), "`close` doesn't take an argument");
+        assert_eq!(error("frobnicate"), "unknown action \"frobnicate\"");
+        assert!(parse_action_type("focus sideways").is_err());
+        assert!(parse_action_type("swap-workspaces 1").is_err());
+        assert!(matches!(
+            parse_action_type("swap-workspaces 1 3").unwrap(),
+            ActionType::SwapWorkspaces(0, 2)
+        ));
+    }
+
+    #[test]
+    fn settings_are_checked() {
+        assert_eq!(
+            parse_setting("new-window-position = stack-bottom").unwrap(),
+            Setting::NewWindowPosition(NewWindowPosition::StackBottom)
+        );
+        assert!(parse_setting("new-window-position = middle").is_err());
+        assert!(parse_setting("new-window-position").is_err());
+        assert!(parse_setting("no-such-option = 1").is_err());
+    }
+
+    #[test]
+    fn every_invalid_line_is_reported() {
+        let (actions, errors) = parse_actions_lenient(
+            "Super+q = close\n\
+             Super+w = frobnicate\n\
+             set new-window-position = master\n\
+             set new-window-position = middle\n\
+             Super+e = kill\n",
+        );
+        assert_eq!(actions.len(), 2);
+        let errors = errors.iter().map(|e| e.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            errors,
+            [
+                "line 2: Super+w = frobnicate",
+                "line 4: set new-window-position = middle"
+            ]
+        );
+    }
+}
@@ -1,15 +1,60 @@
-use std::{cell::Cell, sync::Arc};
+use std::{cell::Cell, fmt::Debug, sync::Arc};
 
 use xcb::{
     x::{
-        ChangeGc, CloseFont, CopyArea, CreateGc, CreatePixmap, Font, FreeGc, FreePixmap, Gc,
-        Gcontext, ImageText8, OpenFont, Pixmap, PolyFillRectangle, Window,
+        ChangeGc, Char2b, CloseFont, Colormap, ColormapAlloc, CopyArea, CreateColormap, CreateGc,
+        CreatePixmap, Font, Fontable, FreeGc, FreePixmap, Gc, Gcontext, ImageText8, OpenFont,
+        Pixmap, PolyFillRectangle, QueryTextExtents, VisualClass, Visualid, Window,
     },
     Connection, ProtocolError,
 };
 
 use crate::layout::Position;
 
+/// the depth of drawables with an alpha channel
+pub const ARGB_DEPTH: u8 = 32;
+
+/// a 32-bit TrueColor visual, for windows that a compositor can draw semi-transparent
+#[derive(Debug, Clone, Copy)]
+pub struct ArgbVisual {
+    pub visual: Visualid,
+    pub colormap: Colormap,
+}
+
+impl ArgbVisual {
+    /// finds a 32-bit visual on the screen of `root_window` and creates a colormap for it. returns
+    /// None if the X server doesn't have one
+    pub fn find(root_window: Window, conn: &Connection) -> Result<Option<Self>, ProtocolError> {
+        let setup = conn.get_setup();
+        let Some(screen) = setup.roots().find(|screen| screen.root() == root_window) else {
+            return Ok(None);
+        };
+        let Some(visual) = screen
+            .allowed_depths()
+            .filter(|depth| depth.depth() == ARGB_DEPTH)
+            .flat_map(|depth| depth.visuals())
+            .find(|visual| visual.class() == VisualClass::TrueColor)
+            .map(|visual| visual.visual_id())
+        else {
+            return Ok(None);
+        };
+
+        let colormap = conn.generate_id();
+        conn.send_and_check_request(&CreateColormap {
+            alloc: ColormapAlloc::None,
+            mid: colormap,
+            window: root_window,
+            visual,
+        })?;
+        Ok(Some(Self { visual, colormap }))
+    }
+}
+
+/// draws into a pixmap that gets copied onto the window by `finalise`.
+///
+/// colors are 0xAARRGGBB. on a drawable with a depth of [`ARGB_DEPTH`] the alpha is honored, and
+/// the color channels are premultiplied with it as compositors expect. on any other depth the
+/// alpha is ignored.
 pub struct DrawContext {
     window: Window,
     pos: Position,
@@ -21,6 +66,15 @@ pub struct DrawContext {
     font: Option<Font>,
 }
 
+impl Debug for DrawContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DrawContext")
+            .field("window", &self.window)
+            .field("pos", &self.pos)
+            .finish_non_exhaustive()
+    }
+}
+
 impl DrawContext {
     pub fn new(
         window: Window,
@@ -86,6 +140,68 @@ impl DrawContext {
         Ok(())
     }
 
+    /// measures `string` in the opened font. returns the width, and the ascent and descent of the
+    /// font
+    pub fn text_extents(&self, string: &str) -> anyhow::Result<(u16, i16, i16)> {
+        let Some(font) = self.font else {
+            anyhow::bail!("no font is open");
+        };
+        // draw_string sends the string as 8-bit characters
+        let string = string
+            .bytes()
+            .map(|byte| Char2b {
+                byte1: 0,
+                byte2: byte,
+            })
+            .collect::<Vec<_>>();
+
+        let reply = self
+            .conn
+            .wait_for_reply(self.conn.send_request(&QueryTextExtents {
+                font: Fontable::Font(font),
+                string: &string,
+            }))?;
+        Ok((
+            reply.overall_width().clamp(0, u16::MAX as i32) as u16,
+            reply.font_ascent(),
+            reply.font_descent(),
+        ))
+    }
+
+    /// returns where `string` has to be drawn for it to be centered in `area`
+    pub fn centered_text(&self, string: &str, area: Position) -> anyhow::Result<(i16, i16)> {
+        let (width, ascent, descent) = self.text_extents(string)?;
+        let x = area.x as i32 + (area.width as i32 - width as i32) / 2;
+        let y = area.y as i32 + (area.height as i32 + ascent as i32 - descent as i32) / 2;
+        // text that doesn't fit starts at the left edge and gets cut off on the right
+        Ok((x.max(area.x as i32) as i16, y as i16))
+    }
+
+    /// converts a color into the pixel value of the drawable
+    fn pixel(&self, color: u32) -> u32 {
+        if self.depth != ARGB_DEPTH {
+            return color;
+        }
+        let alpha = color >> 24;
+        let premultiply = |shift: u32| ((color >> shift & 0xff) * alpha / 0xff) << shift;
+        alpha << 24 | premultiply(16) | premultiply(8) | premultiply(0)
+    }
+
+    /// sets the foreground and background of the graphics context, unless they already are
+    fn set_colors(&self, fg: u32, bg: u32) -> Result<(), ProtocolError> {
+        if self.last_color.get() != (fg, bg) {
+            self.conn.send_and_check_request(&ChangeGc {
+                gc: self.graphic_context,
+                value_list: &[
+                    Gc::Foreground(self.pixel(fg)),
+                    Gc::Background(self.pixel(bg)),
+                ],
+            })?;
+            self.last_color.set((fg, bg));
+        }
+        Ok(())
+    }
+
     pub fn draw_rect(&self, mut pos: Position, fg: u32, bg: u32) -> anyhow::Result<()> {
         if pos.x >= self.pos.width || pos.y >= self.pos.height {
             anyhow::bail!("Tried drawing outside of the rectt");
@@ -97,13 +213,7 @@ impl DrawContext {
             pos.height = self.pos.height - pos.y;
         }
 
-        if self.last_color.get() != (fg, bg) {
-            self.conn.send_and_check_request(&ChangeGc {
-                gc: self.graphic_context,
-                value_list: &[Gc::Foreground(fg), Gc::Background(bg)],
-            })?;
-            self.last_color.set((fg, bg));
-        }
+        self.set_colors(fg, bg)?;
 
         self.conn.send_and_check_request(&PolyFillRectangle {
             drawable: xcb::x::Drawable::Pixmap(self.pixmap),
@@ -121,13 +231,7 @@ impl DrawContext {
         fg: u32,
         bg: u32,
     ) -> Result<(), ProtocolError> {
-        if self.last_color.get() != (fg, bg) {
-            self.conn.send_and_check_request(&ChangeGc {
-                gc: self.graphic_context,
-                value_list: &[Gc::Foreground(fg), Gc::Background(bg)],
-            })?;
-            self.last_color.set((fg, bg));
-        }
+        self.set_colors(fg, bg)?;
 
         self.conn.send_and_check_request(&ImageText8 {
             drawable: xcb::x::Drawable::Pixmap(self.pixmap),
@@ -152,44 +256,54 @@ impl DrawContext {
         })
     }
 
-    pub fn resize(mut self, new_pos: Position) -> Result<Self, ProtocolError> {
+    /// moves the drawing area to `new_pos`, replacing the pixmap and the graphics context with
+    /// ones of the new size. the font and the colors are kept. if the new ones can't be created,
+    /// the old ones are kept and the context still draws at the old size
+    pub fn resize(&mut self, new_pos: Position) -> Result<(), ProtocolError> {
         let new_pixmap = self.conn.generate_id();
         let new_graphic_context = self.conn.generate_id();
 
-        let destroy_pixmap_cookie = self.conn.send_request_checked(&FreePixmap {
-            pixmap: self.pixmap,
-        });
-        let destroy_gc_cookie = self.conn.send_request_checked(&FreeGc {
-            gc: self.graphic_context,
-        });
-
-        let create_pixmap_cookie = self.conn.send_request_checked(&CreatePixmap {
+        self.conn.send_and_check_request(&CreatePixmap {
             depth: self.depth,
             drawable: xcb::x::Drawable::Window(self.window),
             width: new_pos.width,
             height: new_pos.height,
             pid: new_pixmap,
-        });
-        self.conn.check_request(destroy_pixmap_cookie)?;
-        self.conn.check_request(destroy_gc_cookie)?;
-        self.conn.check_request(create_pixmap_cookie)?;
-        self.conn.send_and_check_request(&CreateGc {
+        })?;
+        let mut value_list = vec![
+            Gc::Foreground(self.pixel(self.last_color.get().0)),
+            Gc::Background(self.pixel(self.last_color.get().1)),
+            Gc::LineStyle(xcb::x::LineStyle::Solid),
+            Gc::CapStyle(xcb::x::CapStyle::Butt),
+            Gc::JoinStyle(xcb::x::JoinStyle::Miter),
+        ];
+        // the font belongs to the old graphics context
+        if let Some(font) = self.font {
+            value_list.push(Gc::Font(font));
+        }
+        if let Err(e) = self.conn.send_and_check_request(&CreateGc {
             drawable: xcb::x::Drawable::Pixmap(new_pixmap),
             cid: new_graphic_context,
-            value_list: &[
-                Gc::Foreground(self.last_color.get().0),
-                Gc::Background(self.last_color.get().1),
-                Gc::LineStyle(xcb::x::LineStyle::Solid),
-                Gc::CapStyle(xcb::x::CapStyle::Butt),
-                Gc::JoinStyle(xcb::x::JoinStyle::Miter),
-            ],
-        })?;
+            value_list: &value_list,
+        }) {
+            _ = self
+                .conn
+                .send_and_check_request(&FreePixmap { pixmap: new_pixmap });
+            return Err(e);
+        }
 
-        self.pixmap = new_pixmap;
-        self.graphic_context = new_graphic_context;
+        let old_pixmap = std::mem::replace(&mut self.pixmap, new_pixmap);
+        let old_graphic_context = std::mem::replace(&mut self.graphic_context, new_graphic_context);
         self.pos = new_pos;
 
-        Ok(self)
+        let destroy_pixmap_cookie = self
+            .conn
+            .send_request_checked(&FreePixmap { pixmap: old_pixmap });
+        let destroy_gc_cookie = self.conn.send_request_checked(&FreeGc {
+            gc: old_graphic_context,
+        });
+        self.conn.check_request(destroy_pixmap_cookie)?;
+        self.conn.check_request(destroy_gc_cookie)
     }
 }
 
@@ -1,12 +1,17 @@
-use xcb::x::Window;
+use std::sync::mpsc::Sender;
 
-use crate::keyboard::KeyboardEvent;
+use xcb::x::{Atom, KeyButMask, StackMode, Window};
 
-#[derive(Debug, Clone, Copy)]
+use crate::{ipc::Command, keyboard::KeyboardEvent};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum MouseButton {
     Left = 1,
     Middle = 2,
     Right = 3,
+    /// the thumb buttons
+    Back = 8,
+    Forward = 9,
 }
 impl TryFrom<u8> for MouseButton {
     type Error = ();
@@ -16,17 +21,46 @@ impl TryFrom<u8> for MouseButton {
             1 => Ok(Self::Left),
             2 => Ok(Self::Middle),
             3 => Ok(Self::Right),
+            8 => Ok(Self::Back),
+            9 => Ok(Self::Forward),
             _ => Err(()),
         }
     }
 }
 
+/// the values a client asked to change in a ConfigureRequest. values the client didn't set are `None`
+#[derive(Debug, Clone, Copy)]
+pub struct ConfigureRequest {
+    pub window: Window,
+    pub x: Option<i16>,
+    pub y: Option<i16>,
+    pub width: Option<u16>,
+    pub height: Option<u16>,
+    pub border_width: Option<u16>,
+    pub sibling: Option<Window>,
+    pub stack_mode: Option<StackMode>,
+}
+
 #[derive(Debug, Clone)]
 pub enum Event {
     KeyPress(KeyboardEvent),
     KeyRelease(KeyboardEvent),
     MouseScroll(i32),
-    ButtonPress(MouseButton),
+    ButtonPress {
+        button: MouseButton,
+        /// the top-level window (most likely a frame) that the pointer is on, if any
+        window: Option<Window>,
+        absolute_x: i16,
+        absolute_y: i16,
+        mods: KeyButMask,
+    },
+    /// a click inside a frame, only reported when focus doesn't follow the mouse
+    ClientClick(Window),
+    /// a click on the status bar. `x` is relative to the bar
+    BarClick {
+        button: u8,
+        x: i16,
+    },
     ButtonRelease(MouseButton),
     MouseMove {
         window_x: i16,
@@ -36,7 +70,40 @@ pub enum Event {
     },
 
     MapRequest(Window),
-    EnterNotify(Window),
+    /// the pointer entered a window. `sequence` is the last request the server had processed
+    EnterNotify {
+        window: Window,
+        sequence: u16,
+    },
     UnmapNotify(Window),
     DestroyNotify(Window),
+    Expose(Window),
+    ConfigureRequest(ConfigureRequest),
+    PropertyNotify {
+        window: Window,
+        atom: Atom,
+    },
+    ClientMessage {
+        window: Window,
+        atom: Atom,
+        data: [u32; 5],
+    },
+
+    /// the resolution of the screen changed
+    ScreenChange {
+        width: u16,
+        height: u16,
+    },
+
+    /// the config file should be read again
+    ReloadConfig,
+    /// SIGCHLD arrived, so a program that was started has exited
+    ChildExited,
+    /// the keyboard layout changed, so the keycodes of the keybindings have to be looked up again
+    KeymapChanged,
+    /// a command from the control socket. the reply is sent back to the client
+    Command {
+        command: Command,
+        reply: Sender<String>,
+    },
 }
@@ -3,7 +3,7 @@ use std::iter;
 use xcb::{
     x::{
         Atom, ClientMessageData, ClientMessageEvent, DestroyWindow, EventMask, GetProperty,
-        PropMode, SendEvent, Window, ATOM_ATOM, ATOM_CARDINAL, ATOM_STRING, ATOM_WINDOW,
+        PropMode, SendEvent, Window, ATOM_ATOM, ATOM_CARDINAL, ATOM_WINDOW,
     },
     Connection, Xid,
 };
@@ -23,6 +23,69 @@ macro_rules! change_property {
     };
 }
 
+/// advertises the hints we implement. keep this in sync with the setters in this file
+pub fn set_supported(root: Window, atoms: &Atoms, conn: &Connection) -> EwmhResult {
+    change_property!(
+        conn,
+        root,
+        PropMode::Replace,
+        ATOM_ATOM,
+        atoms.net_supported,
+        &[
+            atoms.net_supported,
+            atoms.net_supporting_wm_check,
+            atoms.net_number_of_desktops,
+            atoms.net_current_desktop,
+            atoms.net_desktop_names,
+            atoms.net_desktop_viewport,
+            atoms.net_wm_desktop,
+            atoms.net_client_list,
+            atoms.net_client_list_stacking,
+            atoms.net_active_window,
+            atoms.net_showing_desktop,
+            atoms.net_wm_state,
+            atoms.net_wm_state_fullscreen,
+            atoms.net_wm_state_sticky,
+            atoms.net_wm_state_above,
+            atoms.net_wm_state_demands_attention,
+            atoms.net_frame_extents,
+            // hints set by clients that we honor
+            atoms.net_wm_name,
+            atoms.net_wm_strut,
+            atoms.net_wm_strut_partial,
+        ]
+    )
+}
+
+/// points `window`'s _NET_SUPPORTING_WM_CHECK at `check_window`. has to be set on the root and on
+/// the check window itself
+pub fn set_supporting_wm_check(
+    window: Window,
+    check_window: Window,
+    atoms: &Atoms,
+    conn: &Connection,
+) -> EwmhResult {
+    change_property!(
+        conn,
+        window,
+        PropMode::Replace,
+        ATOM_WINDOW,
+        atoms.net_supporting_wm_check,
+        &[check_window]
+    )
+}
+
+pub fn set_wm_name(window: Window, name: &str, atoms: &Atoms, conn: &Connection) -> EwmhResult {
+    change_property!(
+        conn,
+        window,
+        PropMode::Replace,
+        atoms.utf8_string,
+        atoms.net_wm_name,
+        name.as_bytes()
+    )
+}
+
 pub fn set_number_of_desktops(
     new_amount: u32,
     root: Window,
@@ -65,7 +128,8 @@ pub fn set_desktop_names(
         conn,
         root,
         PropMode::Replace,
-        ATOM_STRING,
+        // STRING is latin-1, the names can contain any character
+        atoms.utf8_string,
         atoms.net_desktop_names,
         &workspaces
             .iter()
@@ -99,23 +163,78 @@ pub fn set_desktop_viewport(
 }
 
 /// updates _NET_WM_DESKTOP for all clients on all workspaces for the
-/// current screen
+/// current screen. desktops are the 0-based index of the workspace, like _NET_CURRENT_DESKTOP
 pub fn set_wm_desktop(workspaces: &[Workspace], ctx: &Context) -> EwmhResult {
-    for workspace in workspaces.iter() {
+    for (desktop, workspace) in workspaces.iter().enumerate() {
         for client in workspace.windows() {
+            let client = &ctx.windows[client];
             change_property!(
                 ctx.connection,
-                ctx.windows[client].window,
+                client.window,
                 PropMode::Replace,
                 ATOM_CARDINAL,
                 ctx.atoms.net_wm_desktop,
-                &[workspace.id()]
+                // sticky windows are on all desktops
+                &[if client.sticky {
+                    0xFFFFFFFF
+                } else {
+                    desktop as u32
+                }]
             )?;
         }
     }
     Ok(())
 }
 
+/// reads _NET_WM_DESKTOP, the index of the desktop the window is on or wants to be on
+pub fn get_wm_desktop(window: Window, atoms: &Atoms, conn: &Connection) -> Option<u32> {
+    let reply = conn
+        .wait_for_reply(conn.send_request(&GetProperty {
+            delete: false,
+            long_offset: 0,
+            long_length: 1,
+            property: atoms.net_wm_desktop,
+            r#type: ATOM_CARDINAL,
+            window,
+        }))
+        .ok()?;
+
+    reply.value::<u32>().first().copied()
+}
+
+/// sets the focused client, or none if `window` is `Window::none()`
+pub fn set_active_window(
+    window: Window,
+    root: Window,
+    atoms: &Atoms,
+    conn: &Connection,
+) -> EwmhResult {
+    change_property!(
+        conn,
+        root,
+        PropMode::Replace,
+        ATOM_WINDOW,
+        atoms.net_active_window,
+        &[window]
+    )
+}
+
+/// reads _NET_WM_PID, the process id of the client
+pub fn get_wm_pid(window: Window, atoms: &Atoms, conn: &Connection) -> Option<u32> {
+    let reply = conn
+        .wait_for_reply(conn.send_request(&GetProperty {
+            delete: false,
+            long_offset: 0,
+            long_length: 1,
+            property: atoms.net_wm_pid,
+            r#type: ATOM_CARDINAL,
+            window,
+        }))
+        .ok()?;
+
+    reply.value::<u32>().first().copied()
+}
+
 /// list all the clients currently managed by the window manager
 /// by order of insertion
 pub fn set_client_list<'a>(
@@ -135,8 +254,7 @@ pub fn set_client_list<'a>(
 }
 
 /// list all the clients currently managed by the window manager
-/// by stacking order, since we dont stack windows, this is the same
-/// as the other list
+/// the managed windows from bottom to top
 pub fn set_client_list_stacking<'a>(
     clients: &[Window],
     root: Window,
@@ -172,6 +290,56 @@ pub fn set_showing_desktop(
     )
 }
 
+/// the window types of the window, in order of preference
+pub fn get_window_type(window: Window, atoms: &Atoms, conn: &Connection) -> Vec<Atom> {
+    let Ok(reply) = conn.wait_for_reply(conn.send_request(&GetProperty {
+        delete: false,
+        long_offset: 0,
+        long_length: 32,
+        property: atoms.net_wm_window_type,
+        r#type: ATOM_ATOM,
+        window,
+    })) else {
+        return vec![];
+    };
+
+    reply.value::<Atom>().to_vec()
+}
+
+/// tells the client how much space the frame adds around its window
+pub fn set_frame_extents(
+    window: Window,
+    [left, right, top, bottom]: [u32; 4],
+    atom: Atom,
+    conn: &Connection,
+) -> EwmhResult {
+    change_property!(
+        conn,
+        window,
+        PropMode::Replace,
+        ATOM_CARDINAL,
+        atom,
+        &[left, right, top, bottom]
+    )
+}
+
+/// sets the states (_NET_WM_STATE_*) the window is in
+pub fn set_wm_state(
+    window: Window,
+    states: &[Atom],
+    atoms: &Atoms,
+    conn: &Connection,
+) -> EwmhResult {
+    change_property!(
+        conn,
+        window,
+        PropMode::Replace,
+        ATOM_ATOM,
+        atoms.net_wm_state,
+        states
+    )
+}
+
 pub fn window_supports(
     requested_atom: Atom,
     window: Window,
@@ -225,3 +393,24 @@ pub fn delete_window(window: Window, atoms: &Atoms, conn: &Connection) -> bool {
         true
     }
 }
+
+/// asks the window to take the input focus itself (ICCCM 4.1.7)
+pub fn take_focus(window: Window, atoms: &Atoms, conn: &Connection) -> EwmhResult {
+    let event = ClientMessageEvent::new(
+        window,
+        atoms.wm_protocols,
+        ClientMessageData::Data32([
+            atoms.wm_take_focus.resource_id(),
+            xcb::x::CURRENT_TIME,
+            0,
+            0,
+            0,
+        ]),
+    );
+    conn.send_and_check_request(&SendEvent {
+        destination: xcb::x::SendEventDest::Window(window),
+        event: &event,
+        propagate: false,
+        event_mask: EventMask::NO_EVENT,
+    })
+}
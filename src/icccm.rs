@@ -0,0 +1,332 @@
+use xcb::{
+    x::{
+        ChangeProperty, GetProperty, PropMode, Window, ATOM_STRING, ATOM_WINDOW, ATOM_WM_CLASS,
+        ATOM_WM_HINTS, ATOM_WM_NORMAL_HINTS, ATOM_WM_SIZE_HINTS, ATOM_WM_TRANSIENT_FOR,
+    },
+    Connection, Xid,
+};
+
+use crate::atoms::Atoms;
+
+// WM_SIZE_HINTS.flags: https://tronche.com/gui/x/icccm/sec-4.html#s-4.1.2.3
+const US_POSITION: u32 = 1;
+const US_SIZE: u32 = 1 << 1;
+const P_POSITION: u32 = 1 << 2;
+const P_SIZE: u32 = 1 << 3;
+const P_MIN_SIZE: u32 = 1 << 4;
+const P_MAX_SIZE: u32 = 1 << 5;
+const P_RESIZE_INC: u32 = 1 << 6;
+const P_ASPECT: u32 = 1 << 7;
+const P_BASE_SIZE: u32 = 1 << 8;
+
+/// the size constraints a client set in WM_NORMAL_HINTS. sizes are of the client window, without
+/// the frame
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SizeHints {
+    pub min: Option<(u16, u16)>,
+    pub max: Option<(u16, u16)>,
+    pub base: Option<(u16, u16)>,
+    pub increment: Option<(u16, u16)>,
+    /// the smallest and the largest width to height ratio, as numerator and denominator
+    pub min_aspect: Option<(u16, u16)>,
+    pub max_aspect: Option<(u16, u16)>,
+    /// the user or the program asked for the position the window was created with
+    pub position: bool,
+    /// the user or the program asked for the size the window was created with
+    pub size: bool,
+}
+
+fn clamp_u16(value: u32) -> u16 {
+    value.min(u16::MAX as u32) as u16
+}
+
+impl SizeHints {
+    pub fn get(window: Window, conn: &Connection) -> Self {
+        let Ok(reply) = conn.wait_for_reply(conn.send_request(&GetProperty {
+            delete: false,
+            window,
+            property: ATOM_WM_NORMAL_HINTS,
+            r#type: ATOM_WM_SIZE_HINTS,
+            long_offset: 0,
+            long_length: 18,
+        })) else {
+            return Self::default();
+        };
+        let Some(values) = reply.value::<u32>().get(0..18) else {
+            return Self::default();
+        };
+        let flags = values[0];
+        let pair = |flag: u32, idx: usize| {
+            (flags & flag > 0).then(|| (clamp_u16(values[idx]), clamp_u16(values[idx + 1])))
+        };
+
+        Self {
+            min: pair(P_MIN_SIZE, 5),
+            max: pair(P_MAX_SIZE, 7),
+            increment: pair(P_RESIZE_INC, 9),
+            min_aspect: pair(P_ASPECT, 11),
+            max_aspect: pair(P_ASPECT, 13),
+            base: pair(P_BASE_SIZE, 15),
+            position: flags & (US_POSITION | P_POSITION) > 0,
+            size: flags & (US_SIZE | P_SIZE) > 0,
+        }
+    }
+
+    /// adjusts the size of the client window to satisfy the hints. tiled windows only honor the
+    /// minimum size, as they have to fit into the space the layout gives them.
+    pub fn constrain(&self, mut width: u16, mut height: u16, floating: bool) -> (u16, u16) {
+        // per ICCCM, the base size defaults to the minimum size and vice versa
+        let min = self.min.or(self.base);
+        let base = self.base.or(self.min).unwrap_or((0, 0));
+
+        if floating {
+            (width, height) = self.constrain_aspect(width, height);
+            if let Some((inc_width, inc_height)) = self.increment {
+                if inc_width > 0 && width > base.0 {
+                    width -= (width - base.0) % inc_width;
+                }
+                if inc_height > 0 && height > base.1 {
+                    height -= (height - base.1) % inc_height;
+                }
+            }
+            if let Some((max_width, max_height)) = self.max {
+                if max_width > 0 {
+                    width = width.min(max_width);
+                }
+                if max_height > 0 {
+                    height = height.min(max_height);
+                }
+            }
+        }
+        if let Some((min_width, min_height)) = min {
+            width = width.max(min_width);
+            height = height.max(min_height);
+        }
+
+        (width, height)
+    }
+
+    /// shrinks the width or the height until the ratio between them is within the aspect hints
+    fn constrain_aspect(&self, width: u16, height: u16) -> (u16, u16) {
+        let (w, h) = (width as u32, height as u32);
+        let ratio = |aspect: Option<(u16, u16)>| {
+            aspect
+                .filter(|&(num, den)| num > 0 && den > 0)
+                .map(|(num, den)| (num as u32, den as u32))
+        };
+        if let Some((num, den)) = ratio(self.max_aspect) {
+            // too wide
+            if w * den > h * num {
+                return (clamp_u16(h * num / den), height);
+            }
+        }
+        if let Some((num, den)) = ratio(self.min_aspect) {
+            // too tall
+            if w * den < h * num {
+                return (width, clamp_u16(w * den / num));
+            }
+        }
+        (width, height)
+    }
+}
+
+// WM_HINTS.flags: https://tronche.com/gui/x/icccm/sec-4.html#s-4.1.2.4
+const INPUT_HINT: u32 = 1;
+const URGENCY_HINT: u32 = 1 << 8;
+
+/// the parts of WM_HINTS we care about
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WmHints {
+    /// whether the window wants us to give it the input focus. windows without the hint are
+    /// assumed to want it
+    pub input: bool,
+    /// the window wants the user's attention
+    pub urgent: bool,
+}
+
+impl Default for WmHints {
+    fn default() -> Self {
+        Self {
+            input: true,
+            urgent: false,
+        }
+    }
+}
+
+impl WmHints {
+    pub fn get(window: Window, conn: &Connection) -> Self {
+        let Ok(reply) = conn.wait_for_reply(conn.send_request(&GetProperty {
+            delete: false,
+            window,
+            property: ATOM_WM_HINTS,
+            r#type: ATOM_WM_HINTS,
+            long_offset: 0,
+            long_length: 9,
+        })) else {
+            return Self::default();
+        };
+        Self::from_values(reply.value())
+    }
+
+    fn from_values(values: &[u32]) -> Self {
+        let Some(&flags) = values.first() else {
+            return Self::default();
+        };
+
+        Self {
+            input: flags & INPUT_HINT == 0 || values.get(1).is_none_or(|&input| input != 0),
+            urgent: flags & URGENCY_HINT > 0,
+        }
+    }
+}
+
+/// reads WM_CLASS, returning the instance and the class name
+pub fn get_wm_class(window: Window, conn: &Connection) -> (String, String) {
+    let Ok(reply) = conn.wait_for_reply(conn.send_request(&GetProperty {
+        delete: false,
+        window,
+        property: ATOM_WM_CLASS,
+        r#type: ATOM_STRING,
+        long_offset: 0,
+        long_length: 128,
+    })) else {
+        return Default::default();
+    };
+
+    let mut parts = reply
+        .value::<u8>()
+        .split(|&c| c == 0)
+        .map(|part| String::from_utf8_lossy(part).into_owned());
+    let instance = parts.next().unwrap_or_default();
+    let class = parts.next().unwrap_or_default();
+    (instance, class)
+}
+
+/// reads WM_TRANSIENT_FOR, the window a dialog belongs to
+pub fn get_transient_for(window: Window, conn: &Connection) -> Option<Window> {
+    let reply = conn
+        .wait_for_reply(conn.send_request(&GetProperty {
+            delete: false,
+            window,
+            property: ATOM_WM_TRANSIENT_FOR,
+            r#type: ATOM_WINDOW,
+            long_offset: 0,
+            long_length: 1,
+        }))
+        .ok()?;
+
+    let parent = *reply.value::<Window>().first()?;
+    (!parent.is_none() && parent != window).then_some(parent)
+}
+
+/// WM_STATE.state: https://tronche.com/gui/x/icccm/sec-4.html#s-4.1.3.1
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WmState {
+    Withdrawn = 0,
+    Normal = 1,
+    Iconic = 3,
+}
+
+/// sets WM_STATE. we never use icon windows, so the icon field is always None
+pub fn set_wm_state(
+    window: Window,
+    state: WmState,
+    atoms: &Atoms,
+    conn: &Connection,
+) -> Result<(), xcb::ProtocolError> {
+    conn.send_and_check_request(&ChangeProperty {
+        mode: PropMode::Replace,
+        window,
+        property: atoms.wm_state,
+        r#type: atoms.wm_state,
+        data: &[state as u32, Window::none().resource_id()],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn windows_want_input_unless_they_say_otherwise() {
+        assert!(WmHints::from_values(&[]).input);
+        // the flag isn't set, so the field doesn't count
+        assert!(WmHints::from_values(&[0, 0]).input);
+        assert!(WmHints::from_values(&[INPUT_HINT, 1]).input);
+        assert!(!WmHints::from_values(&[INPUT_HINT, 0]).input);
+        // the flag without the field
+        assert!(WmHints::from_values(&[INPUT_HINT]).input);
+    }
+
+    #[test]
+    fn urgency_is_read_from_the_flags() {
+        assert!(!WmHints::from_values(&[INPUT_HINT, 1]).urgent);
+        let hints = WmHints::from_values(&[URGENCY_HINT | INPUT_HINT, 0]);
+        assert!(hints.urgent);
+        assert!(!hints.input);
+    }
+
+    #[test]
+    fn tiled_windows_only_honor_the_minimum() {
+        let hints = SizeHints {
+            min: Some((100, 50)),
+            max: Some((200, 200)),
+            min_aspect: Some((1, 1)),
+            max_aspect: Some((1, 1)),
+            ..Default::default()
+        };
+        assert_eq!(hints.constrain(80, 300, false), (100, 300));
+        assert_eq!(hints.constrain(80, 300, true), (100, 80));
+    }
+
+    #[test]
+    fn floating_windows_are_clamped_to_the_maximum() {
+        let hints = SizeHints {
+            min: Some((50, 50)),
+            max: Some((200, 0)),
+            ..Default::default()
+        };
+        // a maximum of 0 means there is none
+        assert_eq!(hints.constrain(300, 300, true), (200, 300));
+        assert_eq!(hints.constrain(10, 10, true), (50, 50));
+    }
+
+    #[test]
+    fn floating_windows_snap_to_the_increments_above_the_base() {
+        let hints = SizeHints {
+            base: Some((10, 10)),
+            increment: Some((20, 15)),
+            ..Default::default()
+        };
+        assert_eq!(hints.constrain(75, 60, true), (70, 55));
+        assert_eq!(hints.constrain(70, 55, true), (70, 55));
+
+        // without a base size, the minimum size is the base
+        let hints = SizeHints {
+            min: Some((10, 10)),
+            increment: Some((20, 15)),
+            ..Default::default()
+        };
+        assert_eq!(hints.constrain(75, 60, true), (70, 55));
+        assert_eq!(hints.constrain(5, 5, true), (10, 10));
+    }
+
+    #[test]
+    fn floating_windows_keep_their_aspect_ratio() {
+        // between square and twice as wide as tall
+        let hints = SizeHints {
+            min_aspect: Some((1, 1)),
+            max_aspect: Some((2, 1)),
+            ..Default::default()
+        };
+        assert_eq!(hints.constrain(500, 100, true), (200, 100));
+        assert_eq!(hints.constrain(100, 300, true), (100, 100));
+        assert_eq!(hints.constrain(150, 100, true), (150, 100));
+        // a ratio with a zero in it is ignored
+        let hints = SizeHints {
+            max_aspect: Some((0, 1)),
+            ..Default::default()
+        };
+        assert_eq!(hints.constrain(500, 100, true), (500, 100));
+    }
+}
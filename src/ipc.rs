@@ -0,0 +1,265 @@
+use std::{
+    io::{BufRead, BufReader, ErrorKind, Write},
+    os::unix::net::{UnixListener, UnixStream},
+    path::Path,
+    time::Duration,
+};
+
+use serde::Serialize;
+use tracing::{error, info};
+
+use crate::{config, screen::ReservedSpace, tiling::Layout};
+
+/// a command sent to the control socket
+#[derive(Debug, Clone)]
+pub enum Command {
+    /// switches to the workspace with this id, starting at 1
+    SwitchWorkspace(u32),
+    Close,
+    CycleLayout,
+    SetLayout(Layout),
+    /// changes the border of all windows to this many pixels
+    SetBorderSize(u16),
+    /// replies with the state of the window manager as json
+    GetState,
+}
+
+/// how long a client may take to send the next command or to read the reply. connections are
+/// handled one at a time, so an idle client would keep all others waiting
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// the version of the document `get-state` replies with. bump it when the schema changes
+pub const STATE_VERSION: u32 = 2;
+
+/// the document `get-state` replies with. windows are identified by their X window id
+#[derive(Debug, Serialize)]
+pub struct State<'a> {
+    pub version: u32,
+    /// the id of the shown workspace
+    pub current_workspace: u32,
+    pub focused: Option<u32>,
+    pub focused_name: Option<&'a str>,
+    pub workspaces: Vec<WorkspaceState<'a>>,
+    pub clients: Vec<ClientState<'a>>,
+    pub reserved: ReservedSpace,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WorkspaceState<'a> {
+    pub id: u32,
+    pub name: &'a str,
+    /// the symbol of the layout, as shown in the bar
+    pub layout: String,
+    pub focused: Option<u32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ClientState<'a> {
+    pub window: u32,
+    pub class: &'a str,
+    pub name: &'a str,
+    /// the id of the workspace
+    pub workspace: u32,
+    pub floating: bool,
+    pub fullscreen: bool,
+    pub x: u16,
+    pub y: u16,
+    pub width: u16,
+    pub height: u16,
+}
+
+impl State<'_> {
+    /// the reply is a single line
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("the state only contains strings and numbers")
+    }
+}
+
+pub fn parse_command(line: &str) -> anyhow::Result<Command> {
+    let line = line.trim();
+    let (name, argument) = match line.split_once(char::is_whitespace) {
+        Some((name, argument)) => (name, Some(argument.trim())),
+        None => (line, None),
+    };
+
+    Ok(match (name, argument) {
+        ("switch-workspace", Some(id)) => Command::SwitchWorkspace(
+            id.parse()
+                .map_err(|_| anyhow::anyhow!("invalid workspace {id:?}"))?,
+        ),
+        ("close", None) => Command::Close,
+        ("cycle-layout", None) => Command::CycleLayout,
+        ("get-state", None) => Command::GetState,
+        ("set-layout", Some(layout)) => Command::SetLayout(config::parse_layout(layout)?),
+        ("set-border-size", Some(size)) => Command::SetBorderSize(
+            size.parse()
+                .map_err(|_| anyhow::anyhow!("invalid border size {size:?}"))?,
+        ),
+        ("switch-workspace" | "set-layout" | "set-border-size", None) => {
+            anyhow::bail!("`{name}` is missing an argument")
+        }
+        ("close" | "cycle-layout" | "get-state", Some(_)) => {
+            anyhow::bail!("`{name}` doesn't take an argument")
+        }
+        _ => anyhow::bail!("unknown command {name:?}"),
+    })
+}
+
+/// binds the control socket and calls `callback` with every command that is sent to it. the
+/// string the callback returns is sent back as the reply. connections are handled one after
+/// another on a separate thread, and closed when they are idle for `CLIENT_TIMEOUT`.
+pub fn listen(
+    path: &Path,
+    mut callback: impl FnMut(Command) -> String + Send + 'static,
+) -> std::io::Result<()> {
+    // a previous instance that didn't exit cleanly may have left its socket behind. one that
+    // still accepts connections belongs to a window manager that is running
+    if path.exists() {
+        match UnixStream::connect(path) {
+            Ok(_) => {
+                return Err(std::io::Error::new(
+                    ErrorKind::AddrInUse,
+                    format!("another instance is listening on {path:?}"),
+                ))
+            }
+            Err(e) if e.kind() == ErrorKind::ConnectionRefused => std::fs::remove_file(path)?,
+            Err(e) => return Err(e),
+        }
+    }
+    let listener = UnixListener::bind(path)?;
+    info!("listening for commands on {path:?}");
+
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    if let Err(e) = handle_client(stream, &mut callback) {
+                        error!("failed to handle an ipc client: {e:?}");
+                    }
+                }
+                Err(e) => error!("failed to accept an ipc client: {e:?}"),
+            }
+        }
+    });
+
+    Ok(())
+}
+
+fn handle_client(
+    stream: UnixStream,
+    callback: &mut impl FnMut(Command) -> String,
+) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+    stream.set_write_timeout(Some(CLIENT_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = stream;
+    let mut line = String::new();
+
+    loop {
+        match reader.read_line(&mut line) {
+            Ok(0) => break,
+            Ok(_) => {}
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                info!("closing an idle ipc connection");
+                break;
+            }
+            Err(e) => return Err(e),
+        }
+        let reply = match parse_command(&line) {
+            Ok(command) => callback(command),
+            Err(e) => format!("error: {e}"),
+        };
+        writeln!(writer, "{reply}")?;
+        line.clear();
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    fn state<'a>(name: &'a str, class: &'a str) -> State<'a> {
+        State {
+            version: STATE_VERSION,
+            current_workspace: 1,
+            focused: Some(0x400001),
+            focused_name: Some(name),
+            workspaces: vec![WorkspaceState {
+                id: 1,
+                name: "1",
+                layout: Layout::MasterLeft.to_string(),
+                focused: Some(0x400001),
+            }],
+            clients: vec![ClientState {
+                window: 0x400001,
+                class,
+                name,
+                workspace: 1,
+                floating: false,
+                fullscreen: false,
+                x: 0,
+                y: 20,
+                width: 640,
+                height: 460,
+            }],
+            reserved: ReservedSpace {
+                top: 20,
+                ..Default::default()
+            },
+        }
+    }
+
+    #[test]
+    fn state_has_the_documented_shape() {
+        let json = state("term", "st").to_json();
+        assert!(!json.contains('\n'));
+        let value: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            value,
+            json!({
+                "version": STATE_VERSION,
+                "current_workspace": 1,
+                "focused": 0x400001,
+                "focused_name": "term",
+                "workspaces": [{"id": 1, "name": "1", "layout": "[]=", "focused": 0x400001}],
+                "clients": [{
+                    "window": 0x400001,
+                    "class": "st",
+                    "name": "term",
+                    "workspace": 1,
+                    "floating": false,
+                    "fullscreen": false,
+                    "x": 0,
+                    "y": 20,
+                    "width": 640,
+                    "height": 460,
+                }],
+                "reserved": {"top": 20, "bottom": 0, "left": 0, "right": 0},
+            })
+        );
+    }
+
+    #[test]
+    fn titles_are_escaped() {
+        let name = "\"quoted\" \\ tab\there\nnewline \u{1}";
+        let json = state(name, "st").to_json();
+        assert!(!json.contains('\n'));
+        let value: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["focused_name"], name);
+        assert_eq!(value["clients"][0]["name"], name);
+    }
+
+    #[test]
+    fn nothing_focused_is_null() {
+        let mut state = state("", "");
+        state.focused = None;
+        state.focused_name = None;
+        let value: Value = serde_json::from_str(&state.to_json()).unwrap();
+        assert_eq!(value["focused"], Value::Null);
+        assert_eq!(value["focused_name"], Value::Null);
+    }
+}
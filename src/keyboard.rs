@@ -1,22 +1,157 @@
 use std::{cell::RefCell, collections::HashMap};
 
-use tracing::error;
+use tracing::{debug, error};
 use xcb::{
     x::{GrabKey, KeyPressEvent, ModMask as XModMask, UngrabKey, Window},
     xkb::{EventType, MapPart, SelectEvents, StateNotifyEvent, UseExtension},
     Connection,
 };
 use xkbcommon::xkb::{
-    x11::{get_core_keyboard_device_id, keymap_new_from_device, state_new_from_device}, Context, Keycode, Keymap, Keysym, LayoutIndex, ModMask, State, CONTEXT_NO_FLAGS, KEYMAP_COMPILE_NO_FLAGS
+    x11::{get_core_keyboard_device_id, keymap_new_from_device, state_new_from_device},
+    Context, Keycode, Keymap, Keysym, LayoutIndex, ModMask, State, CONTEXT_NO_FLAGS,
+    KEYMAP_COMPILE_NO_FLAGS, MOD_INVALID, STATE_MODS_EFFECTIVE,
 };
 
-use crate::{actions::Action, events::Event};
+use crate::{actions::Action, config, events::Event};
 
 pub const MODS_CTRL: u8 = 0x01 << 0;
 pub const MODS_SHIFT: u8 = 0x01 << 1;
 pub const MODS_ALT: u8 = 0x01 << 2;
 pub const MODS_SUPER: u8 = 0x01 << 3;
-pub const MODS_MASK: u8 = MODS_CTRL | MODS_SHIFT | MODS_ALT | MODS_SUPER;
+/// stands for the modifier chosen with `config::MOD_KEY`. it is replaced when the bindings are
+/// grabbed, so bindings that use `MODS_ALT` or `MODS_SUPER` directly keep working as they are
+pub const MODS_MOD: u8 = 0x01 << 4;
+pub const MODS_META: u8 = 0x01 << 5;
+pub const MODS_HYPER: u8 = 0x01 << 6;
+pub const MODS_MASK: u8 =
+    MODS_CTRL | MODS_SHIFT | MODS_ALT | MODS_SUPER | MODS_MOD | MODS_META | MODS_HYPER;
+
+/// replaces `MODS_MOD` with the modifiers of `mod_key`
+pub fn resolve_mod(mods: u8, mod_key: u8) -> u8 {
+    if mods & MODS_MOD > 0 {
+        (mods & !MODS_MOD) | mod_key
+    } else {
+        mods
+    }
+}
+
+/// the X modifiers that Alt, Super, Meta, Hyper and NumLock are mapped to, which depends on the
+/// keymap
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ModifierMap {
+    pub alt: XModMask,
+    pub super_: XModMask,
+    pub meta: XModMask,
+    pub hyper: XModMask,
+    pub num_lock: XModMask,
+}
+
+impl Default for ModifierMap {
+    /// the mapping of the usual pc keymaps
+    fn default() -> Self {
+        Self {
+            alt: XModMask::N1,
+            super_: XModMask::N4,
+            meta: XModMask::N1,
+            hyper: XModMask::N4,
+            num_lock: XModMask::N2,
+        }
+    }
+}
+
+impl ModifierMap {
+    /// looks up which real modifiers the virtual modifiers of the keymap are mapped to. a virtual
+    /// modifier that is missing from the keymap or isn't mapped keeps its default
+    pub fn from_keymap(keymap: &Keymap) -> Self {
+        const REAL_MODS: [(&str, XModMask); 8] = [
+            ("Shift", XModMask::SHIFT),
+            ("Lock", XModMask::LOCK),
+            ("Control", XModMask::CONTROL),
+            ("Mod1", XModMask::N1),
+            ("Mod2", XModMask::N2),
+            ("Mod3", XModMask::N3),
+            ("Mod4", XModMask::N4),
+            ("Mod5", XModMask::N5),
+        ];
+
+        let lookup = |name: &str, default: XModMask| {
+            let index = keymap.mod_get_index(name);
+            if index == MOD_INVALID {
+                return default;
+            }
+            // setting a virtual modifier sets the real modifiers it is mapped to as well
+            let mut state = State::new(keymap);
+            state.update_mask(1 << index, 0, 0, 0, 0, 0);
+            let mods = state.serialize_mods(STATE_MODS_EFFECTIVE);
+            let mask = REAL_MODS
+                .iter()
+                .filter(|(real, _)| {
+                    let real = keymap.mod_get_index(*real);
+                    real != MOD_INVALID && mods & (1 << real) > 0
+                })
+                .fold(XModMask::empty(), |mask, (_, x_mod)| mask | *x_mod);
+            if mask.is_empty() {
+                default
+            } else {
+                mask
+            }
+        };
+
+        let default = Self::default();
+        Self {
+            alt: lookup("Alt", default.alt),
+            super_: lookup("Super", default.super_),
+            meta: lookup("Meta", default.meta),
+            hyper: lookup("Hyper", default.hyper),
+            num_lock: lookup("NumLock", default.num_lock),
+        }
+    }
+
+    /// converts a combination of `MODS_*` into the modifier mask X uses
+    pub fn to_x_mods(&self, mods: u8) -> XModMask {
+        let mut modifiers = XModMask::empty();
+        if mods & MODS_CTRL > 0 {
+            modifiers |= XModMask::CONTROL;
+        }
+        if mods & MODS_SHIFT > 0 {
+            modifiers |= XModMask::SHIFT;
+        }
+        if mods & MODS_ALT > 0 {
+            modifiers |= self.alt;
+        }
+        if mods & MODS_SUPER > 0 {
+            modifiers |= self.super_;
+        }
+        if mods & MODS_META > 0 {
+            modifiers |= self.meta;
+        }
+        if mods & MODS_HYPER > 0 {
+            modifiers |= self.hyper;
+        }
+        modifiers
+    }
+
+    /// the lock modifiers, which shouldn't stop keybindings from working
+    fn locks(&self) -> XModMask {
+        XModMask::LOCK | self.num_lock
+    }
+
+    /// every combination of `modifiers` with CapsLock and NumLock, so that a binding can be
+    /// grabbed regardless of which of them are on
+    pub fn lock_combinations(&self, modifiers: XModMask) -> [XModMask; 4] {
+        [
+            modifiers,
+            modifiers | XModMask::LOCK,
+            modifiers | self.num_lock,
+            modifiers | self.locks(),
+        ]
+    }
+
+    /// removes CapsLock and NumLock from the modifiers of an event
+    pub fn without_locks(&self, modifiers: XModMask) -> XModMask {
+        modifiers - self.locks()
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct KeyboardEvent {
@@ -24,6 +159,8 @@ pub struct KeyboardEvent {
     pub characters: Box<str>,
     pub mods: XModMask,
     pub keycode: Keycode,
+    /// what `mods` mean for the keymap the event was read with
+    pub modifiers: ModifierMap,
 }
 
 macro_rules! is_mod {
@@ -41,10 +178,23 @@ impl KeyboardEvent {
         is_shift = XModMask::SHIFT;
         is_caps_lock = XModMask::LOCK;
         is_ctrl = XModMask::CONTROL;
-        is_alt = XModMask::N1;
         is_num_lock = XModMask::N2;
-        is_scroll_locl = XModMask::N3;
-        is_super = XModMask::N4;
+    }
+
+    pub fn is_alt(&self) -> bool {
+        self.mods.intersects(self.modifiers.alt)
+    }
+
+    pub fn is_super(&self) -> bool {
+        self.mods.intersects(self.modifiers.super_)
+    }
+
+    pub fn is_meta(&self) -> bool {
+        self.mods.intersects(self.modifiers.meta)
+    }
+
+    pub fn is_hyper(&self) -> bool {
+        self.mods.intersects(self.modifiers.hyper)
     }
 }
 
@@ -53,12 +203,15 @@ pub struct Keyboard {
     _keymap: Keymap,
     device_id: i32,
     state: RefCell<State>,
+    modifiers: ModifierMap,
 }
 
 #[derive(Debug)]
 pub struct BoundAction {
     pub key: Keycode,
     pub modifiers: XModMask,
+    /// the keys that have to follow the first one, empty for a single key binding
+    pub sequence: Vec<(Keycode, XModMask)>,
     pub action_index: usize,
 }
 
@@ -80,44 +233,58 @@ impl Keyboard {
         let mut cookies = vec![];
 
         for i in 0..actions.len() {
+            let sequence = actions[i]
+                .sequence
+                .iter()
+                .map(|&(key, mods)| {
+                    let mods = resolve_mod(mods, config::MOD_KEY);
+                    Some((*keycode_map.get(&key)?, self.modifiers.to_x_mods(mods)))
+                })
+                .collect::<Option<Vec<_>>>();
+            let Some(sequence) = sequence else {
+                error!(
+                    "Failed to bind action #{i} ({:?}): a key of the sequence isn't on the keyboard",
+                    actions[i]
+                );
+                continue;
+            };
+
+            // only the first key is grabbed, the rest of the sequence is read with the keyboard
+            // grabbed once it was pressed
             if let Some(key) = keycode_map.get(&actions[i].key) {
-                let mut modifiers = XModMask::empty();
-                if actions[i].mods & MODS_CTRL > 0 {
-                    modifiers |= XModMask::CONTROL;
-                }
-                if actions[i].mods & MODS_SHIFT > 0 {
-                    modifiers |= XModMask::SHIFT;
-                }
-                if actions[i].mods & MODS_ALT > 0 {
-                    modifiers |= XModMask::N1;
-                }
-                if actions[i].mods & MODS_SUPER > 0 {
-                    modifiers |= XModMask::N4;
-                }
+                let modifiers = self
+                    .modifiers
+                    .to_x_mods(resolve_mod(actions[i].mods, config::MOD_KEY));
 
-                cookies.push(conn.send_request_checked(&GrabKey {
-                    grab_window: root_window,
-                    key: (*key).into(),
-                    modifiers,
-                    keyboard_mode: xcb::x::GrabMode::Async,
-                    pointer_mode: xcb::x::GrabMode::Async,
-                    owner_events: false,
-                }));
+                for grab_modifiers in self.modifiers.lock_combinations(modifiers) {
+                    cookies.push((
+                        i,
+                        conn.send_request_checked(&GrabKey {
+                            grab_window: root_window,
+                            key: (*key).into(),
+                            modifiers: grab_modifiers,
+                            keyboard_mode: xcb::x::GrabMode::Async,
+                            pointer_mode: xcb::x::GrabMode::Async,
+                            owner_events: false,
+                        }),
+                    ));
+                }
                 bound_actions.push(BoundAction {
                     key: *key,
                     modifiers,
+                    sequence,
                     action_index: i,
                 });
             }
         }
 
-        for (i, cookie) in cookies.into_iter().enumerate() {
+        for (i, cookie) in cookies.into_iter() {
             if let Err(e) = conn.check_request(cookie) {
                 error!("Failed to bind action #{i} ({:?}):\n{e:?}", actions[i]);
             }
         }
 
-        println!("Bound Actions");
+        debug!("Bound {} actions", bound_actions.len());
 
         bound_actions
     }
@@ -130,12 +297,16 @@ impl Keyboard {
     ) {
         let cookies = bound_actions
             .iter()
-            .map(|bound_action| {
-                conn.send_request_checked(&UngrabKey {
-                    grab_window: root_window,
-                    key: bound_action.key.into(),
-                    modifiers: bound_action.modifiers,
-                })
+            .flat_map(|bound_action| {
+                self.modifiers
+                    .lock_combinations(bound_action.modifiers)
+                    .map(|modifiers| {
+                        conn.send_request_checked(&UngrabKey {
+                            grab_window: root_window,
+                            key: bound_action.key.into(),
+                            modifiers,
+                        })
+                    })
             })
             .collect::<Vec<_>>();
 
@@ -145,7 +316,7 @@ impl Keyboard {
             }
         }
 
-        println!("Unbound Actions");
+        debug!("Unbound {} actions", bound_actions.len());
     }
 
     pub fn new(conn: &Connection) -> anyhow::Result<Self> {
@@ -189,19 +360,41 @@ impl Keyboard {
         let device_id = get_core_keyboard_device_id(conn);
         let keymap = keymap_new_from_device(&context, conn, device_id, KEYMAP_COMPILE_NO_FLAGS);
         let state = state_new_from_device(&keymap, conn, device_id);
+        let modifiers = ModifierMap::from_keymap(&keymap);
+        debug!("Modifier mapping: {modifiers:?}");
 
         Ok(Keyboard {
             _context: context,
             _keymap: keymap,
             device_id,
             state: RefCell::new(state),
+            modifiers,
         })
     }
 
+    /// rebuilds the keymap and the keyboard state from the current layout of the core keyboard.
+    /// the actions have to be bound again afterwards
+    pub fn reload_keymap(&mut self, conn: &Connection) {
+        let device_id = get_core_keyboard_device_id(conn);
+        let keymap =
+            keymap_new_from_device(&self._context, conn, device_id, KEYMAP_COMPILE_NO_FLAGS);
+        let state = state_new_from_device(&keymap, conn, device_id);
+
+        self.device_id = device_id;
+        self.modifiers = ModifierMap::from_keymap(&keymap);
+        self._keymap = keymap;
+        self.state = RefCell::new(state);
+    }
+
     pub fn device_id(&self) -> i32 {
         self.device_id
     }
 
+    /// the X modifiers of the current keymap
+    pub fn modifiers(&self) -> ModifierMap {
+        self.modifiers
+    }
+
     pub fn update_state(&self, event: StateNotifyEvent) {
         self.state.borrow_mut().update_mask(
             event.base_mods().bits() as ModMask,
@@ -225,6 +418,7 @@ impl Keyboard {
                 characters: state.key_get_utf8(keycode).into_boxed_str(),
                 mods,
                 keycode,
+                modifiers: self.modifiers,
             })
         } else {
             Event::KeyRelease(KeyboardEvent {
@@ -232,7 +426,97 @@ impl Keyboard {
                 characters: Box::<str>::default(),
                 mods,
                 keycode,
+                modifiers: self.modifiers,
             })
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lock_combinations_cover_every_lock_state() {
+        let mods = XModMask::CONTROL | XModMask::N1;
+        let map = ModifierMap::default();
+        let combinations = map.lock_combinations(mods);
+
+        assert_eq!(
+            combinations,
+            [
+                mods,
+                mods | XModMask::LOCK,
+                mods | XModMask::N2,
+                mods | XModMask::LOCK | XModMask::N2,
+            ]
+        );
+        for combination in combinations {
+            assert_eq!(map.without_locks(combination), mods);
+        }
+    }
+
+    #[test]
+    fn num_lock_follows_the_keymap() {
+        let map = ModifierMap {
+            num_lock: XModMask::N3,
+            ..ModifierMap::default()
+        };
+        let mods = XModMask::N4;
+        assert_eq!(
+            map.lock_combinations(mods),
+            [
+                mods,
+                mods | XModMask::LOCK,
+                mods | XModMask::N3,
+                mods | XModMask::LOCK | XModMask::N3,
+            ]
+        );
+        // Mod2 isn't a lock in this keymap
+        assert_eq!(
+            map.without_locks(mods | XModMask::N2 | XModMask::N3),
+            mods | XModMask::N2
+        );
+    }
+
+    #[test]
+    fn resolve_mod_replaces_only_the_placeholder() {
+        assert_eq!(
+            resolve_mod(MODS_MOD | MODS_SHIFT, MODS_SUPER),
+            MODS_SUPER | MODS_SHIFT
+        );
+        assert_eq!(resolve_mod(MODS_MOD, MODS_ALT), MODS_ALT);
+        // explicit modifiers are left alone
+        assert_eq!(
+            resolve_mod(MODS_ALT | MODS_CTRL, MODS_SUPER),
+            MODS_ALT | MODS_CTRL
+        );
+    }
+
+    #[test]
+    fn without_locks_keeps_other_modifiers() {
+        assert_eq!(
+            ModifierMap::default().without_locks(XModMask::SHIFT | XModMask::N4 | XModMask::LOCK),
+            XModMask::SHIFT | XModMask::N4
+        );
+    }
+
+    #[test]
+    fn to_x_mods_follows_the_keymap() {
+        let mods = MODS_CTRL | MODS_SUPER;
+        assert_eq!(
+            ModifierMap::default().to_x_mods(mods),
+            XModMask::CONTROL | XModMask::N4
+        );
+        let modifiers = ModifierMap {
+            super_: XModMask::N3,
+            hyper: XModMask::N5,
+            ..ModifierMap::default()
+        };
+        assert_eq!(modifiers.to_x_mods(mods), XModMask::CONTROL | XModMask::N3);
+        assert_eq!(
+            modifiers.to_x_mods(MODS_HYPER | MODS_META),
+            XModMask::N5 | XModMask::N1
+        );
+    }
+}
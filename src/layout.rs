@@ -2,7 +2,11 @@ use std::fmt::Debug;
 
 use xcb::x::Rectangle;
 
-use crate::{screen::Context, tiling::Layout};
+use crate::{
+    config::{self, NewWindowPosition},
+    screen::{Context, Role},
+    tiling::{Gaps, Layout},
+};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Position {
@@ -22,6 +26,24 @@ impl Position {
         }
     }
 }
+impl Position {
+    /// the center of the rectangle
+    fn center(&self) -> (i32, i32) {
+        (
+            self.x as i32 + self.width as i32 / 2,
+            self.y as i32 + self.height as i32 / 2,
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
 impl Into<Rectangle> for Position {
     fn into(self) -> Rectangle {
         Rectangle {
@@ -38,33 +60,462 @@ pub struct Workspace {
     pub windows: Vec<usize>,
     floating_windows: Vec<usize>,
     pos: Position,
-    gap: u16,
     layout: Layout,
     is_showing: bool,
     name: String,
     id: u32,
     focused: Option<(usize, bool)>,
+    /// the window that was focused when the workspace was hidden
+    last_focused: Option<usize>,
+    /// the window covering the entire screen, if any
+    fullscreen: Option<usize>,
+    /// the area of the entire screen, including reserved space
+    screen: Position,
+    /// the windows from bottom to top. tiled windows are below floating ones, and the fullscreen
+    /// window is above all of them
+    stack: Vec<usize>,
+    /// the minimized windows and whether they were floating, the most recently minimized last
+    minimized: Vec<(usize, bool)>,
+    /// the width of the master column, or half the workspace if it was never resized
+    master_width: Option<u16>,
+    /// the gaps are toggled off. the configured ones are kept, so they come back when toggled on
+    gaps_disabled: bool,
+    /// the window that covers the workspace until it is toggled back or loses focus. the layout
+    /// of the other windows stays the same
+    expanded: Option<usize>,
+    /// how many new floating windows without a position were cascaded since the workspace last
+    /// had no floating windows
+    cascaded: u32,
 }
 
 impl Workspace {
-    pub fn new(pos: Position, gap: u16, id: u32) -> Self {
+    pub fn new(pos: Position, id: u32, name: String, layout: Layout) -> Self {
         Self {
             windows: vec![],
             floating_windows: vec![],
             focused: None,
+            last_focused: None,
+            fullscreen: None,
+            screen: pos,
+            stack: vec![],
+            minimized: vec![],
+            master_width: None,
+            gaps_disabled: false,
+            expanded: None,
+            cascaded: 0,
             pos,
-            gap,
-            layout: Layout::Grid,
+            layout,
             is_showing: false,
-            name: format!("Desktop {id}"),
+            name,
             id,
         }
     }
 
     fn retile(&mut self, context: &mut Context) {
-        if self.windows.len() > 0 && self.is_showing {
-            self.layout
-                .retile(&self.windows, self.gap, self.pos, context);
+        if !self.is_showing {
+            return;
+        }
+
+        let positions = self
+            .windows
+            .iter()
+            .map(|&idx| (idx, context.windows[idx].position()))
+            .collect::<Vec<_>>();
+        self.retile_windows(context);
+        self.update_roles(context);
+        // dialogs follow their parent around
+        for (idx, position) in positions {
+            if context.windows[idx].position() != position {
+                self.center_transients(idx, context);
+            }
+        }
+        // the windows moving under the pointer shouldn't take the focus
+        context.ignore_pending_enters();
+    }
+
+    fn retile_windows(&mut self, context: &mut Context) {
+        let Some(fullscreen) = self.fullscreen else {
+            if !self.windows.is_empty() {
+                self.layout.retile(
+                    &self.windows,
+                    self.gaps(context),
+                    self.pos,
+                    self.master_width(),
+                    self.smart_gaps(context),
+                    context,
+                );
+            }
+            if let Some(expanded) = self.expanded {
+                let pos = self.pos;
+                context.windows[expanded].update(
+                    pos.width,
+                    pos.height,
+                    pos.x,
+                    pos.y,
+                    &context.connection,
+                );
+                self.raise_window(expanded, context);
+            }
+            return;
+        };
+
+        let windows = self
+            .windows
+            .iter()
+            .copied()
+            .filter(|&window| window != fullscreen)
+            .collect::<Vec<_>>();
+        if !windows.is_empty() {
+            self.layout.retile(
+                &windows,
+                self.gaps(context),
+                self.pos,
+                self.master_width(),
+                self.smart_gaps(context),
+                context,
+            );
+        }
+        context.windows[fullscreen].update(
+            self.screen.width,
+            self.screen.height,
+            self.screen.x,
+            self.screen.y,
+            &context.connection,
+        );
+        self.raise_window(fullscreen, context);
+    }
+
+    /// tells the windows whether they are the master, floating or fullscreen
+    fn update_roles(&self, ctx: &mut Context) {
+        let master = self
+            .layout
+            .has_master()
+            .then(|| self.windows.last().copied())
+            .flatten();
+        for window_idx in self.windows() {
+            let role = if self.fullscreen == Some(window_idx) {
+                Role::Fullscreen
+            } else if self.floating_windows.contains(&window_idx) {
+                Role::Floating
+            } else if master == Some(window_idx) {
+                Role::Master
+            } else {
+                Role::Tiled
+            };
+            ctx.windows[window_idx].set_role(role);
+        }
+    }
+
+    fn gaps(&self, ctx: &Context) -> Gaps {
+        if self.gaps_disabled {
+            Gaps { inner: 0, outer: 0 }
+        } else {
+            Gaps::from(&ctx.config)
+        }
+    }
+
+    pub fn toggle_gaps(&mut self, ctx: &mut Context) {
+        self.gaps_disabled = !self.gaps_disabled;
+        self.retile_preserving_focus(ctx);
+    }
+
+    /// whether a lone tiled window should fill the workspace. floating windows would overlap it
+    fn smart_gaps(&self, ctx: &Context) -> bool {
+        ctx.config.smart_gaps && self.floating_windows.is_empty()
+    }
+
+    /// the width of the master column, fitted to the current size of the workspace
+    fn master_width(&self) -> u16 {
+        self.clamp_master_width(self.master_width.unwrap_or(self.pos.width / 2))
+    }
+
+    /// keeps both the master and the stack column at least MIN_COLUMN_WIDTH wide, if there's
+    /// enough space for that
+    fn clamp_master_width(&self, width: u16) -> u16 {
+        let min = config::MIN_COLUMN_WIDTH.min(self.pos.width / 2);
+        width.clamp(min, self.pos.width - min)
+    }
+
+    /// grows or shrinks the master column by `delta` pixels
+    pub fn resize_master(&mut self, delta: i16, ctx: &mut Context) {
+        let width = (self.master_width() as i32 + delta as i32).clamp(0, u16::MAX as i32);
+        self.master_width = Some(self.clamp_master_width(width as u16));
+        self.retile_preserving_focus(ctx);
+    }
+
+    /// forgets the width the master column was resized to, which is the only ratio that can be
+    /// adjusted. the workspace is only retiled if it had been resized
+    pub fn balance(&mut self, ctx: &mut Context) {
+        if self.master_width.take().is_some() {
+            self.retile_preserving_focus(ctx);
+        }
+    }
+
+    /// the x coordinate of the boundary between the master and the stack column, if there is one
+    pub fn master_edge(&self) -> Option<u16> {
+        if self.windows.len() < 2 || self.fullscreen.is_some() || self.expanded.is_some() {
+            return None;
+        }
+        let master_width = self.master_width();
+        Some(if self.layout.master_is_left()? {
+            self.pos.x + master_width
+        } else {
+            self.pos.x + self.pos.width - master_width
+        })
+    }
+
+    /// moves the boundary between the master and the stack column to `x`. the pointer stays
+    /// where it is, as the boundary is dragged with it
+    pub fn set_master_edge(&mut self, x: i16, ctx: &mut Context) {
+        let Some(master_is_left) = self.layout.master_is_left() else {
+            return;
+        };
+        let width = if master_is_left {
+            x as i32 - self.pos.x as i32
+        } else {
+            self.pos.x as i32 + self.pos.width as i32 - x as i32
+        };
+        let width = self.clamp_master_width(width.clamp(0, u16::MAX as i32) as u16);
+        if self.master_width == Some(width) {
+            return;
+        }
+        self.master_width = Some(width);
+        self.retile(ctx);
+    }
+
+    /// the layer the window is stacked in
+    fn layer(&self, window_idx: usize, ctx: &Context) -> u8 {
+        if self.fullscreen == Some(window_idx) {
+            3
+        } else if ctx.windows[window_idx].above {
+            2
+        } else if self.is_floating(window_idx) {
+            1
+        } else {
+            0
+        }
+    }
+
+    /// puts the window above all other windows in its layer. its transient windows are kept above
+    /// it
+    pub fn raise_window(&mut self, window_idx: usize, ctx: &Context) {
+        self.raise_single_window(window_idx, ctx);
+        for transient in self.transients_of(window_idx, ctx) {
+            self.raise_single_window(transient, ctx);
+        }
+    }
+
+    fn raise_single_window(&mut self, window_idx: usize, ctx: &Context) {
+        self.stack.retain(|&idx| idx != window_idx);
+        let layer = self.layer(window_idx, ctx);
+        let pos = self
+            .stack
+            .iter()
+            .position(|&idx| self.layer(idx, ctx) > layer)
+            .unwrap_or(self.stack.len());
+        self.stack.insert(pos, window_idx);
+
+        if self.is_showing {
+            // the windows of the higher layers have to go back above it
+            for idx in self.stack[pos..].iter().copied() {
+                ctx.windows[idx].raise(&ctx.connection);
+            }
+        }
+    }
+
+    /// the floating windows that are transient for `parent`
+    fn transients_of(&self, parent: usize, ctx: &Context) -> Vec<usize> {
+        let parent_window = ctx.windows[parent].window;
+        self.floating_windows
+            .iter()
+            .copied()
+            .filter(|&idx| idx != parent && ctx.windows[idx].transient_for == Some(parent_window))
+            .collect()
+    }
+
+    /// the window on this workspace that `window_idx` is transient for
+    fn parent_of(&self, window_idx: usize, ctx: &Context) -> Option<usize> {
+        let parent = ctx.windows[window_idx].transient_for?;
+        let parent = ctx.window_lookup.get(&parent).copied()?;
+        (parent != window_idx && self.get_window(parent).is_some()).then_some(parent)
+    }
+
+    /// centers the window over `parent`, keeping its size
+    fn center_over(&self, window_idx: usize, parent: usize, ctx: &mut Context) {
+        let parent = ctx.windows[parent].position();
+        let client = &mut ctx.windows[window_idx];
+        let x = parent.x as i32 + (parent.width as i32 - client.width as i32) / 2;
+        let y = parent.y as i32 + (parent.height as i32 - client.height as i32) / 2;
+        client.update(
+            client.width,
+            client.height,
+            x.max(0) as u16,
+            y.max(0) as u16,
+            &ctx.connection,
+        );
+    }
+
+    /// moves the transient windows of `parent` back over it
+    pub fn center_transients(&mut self, parent: usize, ctx: &mut Context) {
+        for transient in self.transients_of(parent, ctx) {
+            self.center_over(transient, parent, ctx);
+        }
+    }
+
+    /// finds the window of `candidates` closest to `window_idx` whose center lies in the given
+    /// direction. ties are broken by the offset across the direction, then by the window index
+    fn window_in_direction(
+        candidates: impl Iterator<Item = usize>,
+        window_idx: usize,
+        direction: Direction,
+        ctx: &Context,
+    ) -> Option<usize> {
+        let position = |idx: usize| ctx.windows[idx].position();
+        let (from_x, from_y) = position(window_idx).center();
+
+        candidates
+            .filter(|&idx| idx != window_idx)
+            .filter_map(|idx| {
+                let (x, y) = position(idx).center();
+                let (dx, dy) = (x - from_x, y - from_y);
+                let (along, across) = match direction {
+                    Direction::Left => (-dx, dy),
+                    Direction::Right => (dx, dy),
+                    Direction::Up => (-dy, dx),
+                    Direction::Down => (dy, dx),
+                };
+                (along > 0).then(|| {
+                    let distance = along as i64 * along as i64 + across as i64 * across as i64;
+                    (distance, across.abs(), idx)
+                })
+            })
+            .min()
+            .map(|(_, _, idx)| idx)
+    }
+
+    /// focuses the closest window in the given direction from the focused one. returns the
+    /// newly focused window
+    pub fn focus_direction(&mut self, direction: Direction, ctx: &mut Context) -> Option<usize> {
+        let focused = self.focused_window()?;
+        let window_idx = Self::window_in_direction(self.windows(), focused, direction, ctx)?;
+        if !self.focus_client(window_idx, ctx) {
+            return None;
+        }
+        if config::WARP_POINTER {
+            ctx.windows[window_idx].warp_pointer(&ctx.connection);
+        }
+        Some(window_idx)
+    }
+
+    /// swaps the focused tiled window with its neighbor in the given direction, or moves the
+    /// focused floating window a bit into that direction
+    pub fn move_direction(&mut self, direction: Direction, ctx: &mut Context) {
+        let Some((idx, is_floating)) = self.focused else {
+            return;
+        };
+
+        if is_floating {
+            let client = &mut ctx.windows[self.floating_windows[idx]];
+            let step = config::MOVE_STEP;
+            let (x, y) = match direction {
+                Direction::Left => (client.x.saturating_sub(step), client.y),
+                Direction::Right => (client.x.saturating_add(step), client.y),
+                Direction::Up => (client.x, client.y.saturating_sub(step)),
+                Direction::Down => (client.x, client.y.saturating_add(step)),
+            };
+            client.update(client.width, client.height, x, y, &ctx.connection);
+            return;
+        }
+
+        let window_idx = self.windows[idx];
+        let Some(neighbor) =
+            Self::window_in_direction(self.windows.iter().copied(), window_idx, direction, ctx)
+        else {
+            return;
+        };
+        let Some((neighbor_pos, _)) = self.get_window(neighbor) else {
+            return;
+        };
+        self.windows.swap(idx, neighbor_pos);
+        self.focused = Some((neighbor_pos, false));
+        self.retile_preserving_focus(ctx);
+    }
+
+    /// swaps the focused tiled window with the master. the master itself trades places with the
+    /// top of the stack. the focus stays on the same window
+    pub fn swap_master(&mut self, ctx: &mut Context) {
+        if self.swap_focused_with_master() {
+            self.retile_preserving_focus(ctx);
+        }
+    }
+
+    /// moves the focus along with the swapped window. returns whether anything moved, and the
+    /// windows have to be retiled
+    fn swap_focused_with_master(&mut self) -> bool {
+        let Some((idx, false)) = self.focused else {
+            return false;
+        };
+        let Some(idx) = swap_with_master(&mut self.windows, idx) else {
+            return false;
+        };
+        self.focused = Some((idx, false));
+        true
+    }
+
+    /// shifts all tiled windows by one place. up and left move them towards the master, which
+    /// becomes the bottom of the stack. the focus stays on the same window
+    pub fn rotate_stack(&mut self, direction: Direction, ctx: &mut Context) {
+        if self.windows.len() < 2 {
+            return;
+        }
+        self.rotate(matches!(direction, Direction::Up | Direction::Left));
+        self.retile_preserving_focus(ctx);
+    }
+
+    /// rotates the tiled windows, the master being the last one, and moves the focus along
+    fn rotate(&mut self, towards_master: bool) {
+        let len = self.windows.len();
+        if len == 0 {
+            return;
+        }
+        if towards_master {
+            self.windows.rotate_right(1);
+        } else {
+            self.windows.rotate_left(1);
+        }
+        if let Some((idx, false)) = self.focused {
+            let idx = if towards_master {
+                (idx + 1) % len
+            } else {
+                (idx + len - 1) % len
+            };
+            self.focused = Some((idx, false));
+        }
+    }
+
+    pub fn fullscreen_window(&self) -> Option<usize> {
+        self.fullscreen
+    }
+
+    /// the windows of the workspace from bottom to top
+    pub fn stacking_order(&self) -> &[usize] {
+        &self.stack
+    }
+
+    /// retiles the workspace and afterwards re-focuses the window that is supposed to be focused.
+    /// if enabled, the pointer is warped onto that window, so that focus-follows-mouse doesn't
+    /// focus whatever window ended up under the cursor.
+    pub fn retile_preserving_focus(&mut self, ctx: &mut Context) {
+        self.retile(ctx);
+
+        if !self.is_showing {
+            return;
+        }
+        let Some(window_idx) = ctx.focused_window else {
+            return;
+        };
+        if self.focus_client(window_idx, ctx) && config::WARP_POINTER {
+            ctx.windows[window_idx].warp_pointer(&ctx.connection);
         }
     }
 
@@ -72,39 +523,44 @@ impl Workspace {
         self.is_showing = true;
 
         for win in self.windows.iter().copied() {
-            ctx.windows[win].show(&ctx.connection);
+            ctx.windows[win].show(&ctx.atoms, &ctx.connection);
         }
         self.retile(ctx);
 
-        for win in self.windows.iter().copied() {
+        for win in self
+            .windows
+            .iter()
+            .chain(self.floating_windows.iter())
+            .copied()
+        {
             let win = &mut ctx.windows[win];
-            win.show(&ctx.connection);
+            win.show(&ctx.atoms, &ctx.connection);
             win.update(win.width, win.height, win.x, win.y, &ctx.connection);
         }
+        // other workspaces might have changed the stacking order in the meantime
+        for win in self.stack.iter().copied() {
+            ctx.windows[win].raise(&ctx.connection);
+        }
     }
 
     pub fn hide(&mut self, ctx: &mut Context) {
         self.is_showing = false;
+        self.last_focused = self.focused_window();
         self.unfocus_all(ctx);
         for win in self.windows.iter().copied() {
-            ctx.windows[win].hide(&ctx.connection);
+            ctx.windows[win].hide(&ctx.atoms, &ctx.connection);
         }
         for win in self.floating_windows.iter().copied() {
-            ctx.windows[win].hide(&ctx.connection);
+            ctx.windows[win].hide(&ctx.atoms, &ctx.connection);
         }
     }
 
     pub fn cycle_layout(&mut self, ctx: &mut Context) {
-        self.layout = match self.layout {
-            Layout::Grid => Layout::MasterLeft,
-            Layout::MasterLeft => Layout::MasterRight,
-            Layout::MasterRight => Layout::MasterLeftGrid,
-            Layout::MasterLeftGrid => Layout::MasterRightGrid,
-            Layout::MasterRightGrid => Layout::Monocle,
-            Layout::Monocle => Layout::Grid,
-        };
+        let layouts = Layout::all();
+        let current = layouts.iter().position(|&l| l == self.layout).unwrap_or(0);
+        self.layout = layouts[(current + 1) % layouts.len()];
 
-        self.retile(ctx);
+        self.retile_preserving_focus(ctx);
     }
 
     pub fn set_layout(&mut self, new_layout: Layout, ctx: &mut Context) {
@@ -113,53 +569,376 @@ impl Workspace {
         }
         self.layout = new_layout;
 
-        self.retile(ctx);
+        self.retile_preserving_focus(ctx);
     }
 
     pub fn spawn_window(&mut self, index: usize, ctx: &mut Context) {
-        ctx.windows[index].show(&ctx.connection);
-        self.windows.push(index);
+        if self.is_showing {
+            ctx.windows[index].show(&ctx.atoms, &ctx.connection);
+        }
+        if ctx.windows[index].fullscreen {
+            self.replace_fullscreen(Some(index), ctx);
+        }
+        self.insert_tiled(index, ctx.config.new_window_position);
+        self.raise_window(index, ctx);
         self.retile(ctx);
     }
 
-    /// finds the window to toggle floating on. Usize is the window index and the boolean is if it is currently not floating
-    fn find_floating_window(&mut self, window_idx: usize) -> Option<(usize, bool)> {
-        for i in 0..self.windows.len() {
-            if self.windows[i] == window_idx {
-                return Some((i, true));
+    /// puts a new window into the tiled ones, keeping the focus on the same window
+    fn insert_tiled(&mut self, index: usize, position: NewWindowPosition) {
+        // the master is the last window
+        let pos = match (position, self.focused) {
+            (NewWindowPosition::NextToFocused, Some((focused, false))) => focused + 1,
+            (NewWindowPosition::StackBottom, _) => 0,
+            _ => self.windows.len(),
+        };
+        self.windows.insert(pos, index);
+        // the focused window moved back if the new one was put in front of it
+        if let Some((focused, false)) = self.focused {
+            if focused >= pos {
+                self.focused = Some((focused + 1, false));
             }
         }
-        for i in 0..self.floating_windows.len() {
-            if self.floating_windows[i] == window_idx {
-                return Some((i, false));
+    }
+
+    pub fn spawn_floating_window(&mut self, index: usize, ctx: &mut Context) {
+        ctx.windows[index].floating = true;
+        if self.is_showing {
+            ctx.windows[index].show(&ctx.atoms, &ctx.connection);
+        }
+        self.floating_windows.push(index);
+        self.place_requested(index, ctx);
+        // dialogs that don't say where they want to be go over their parent
+        if ctx.windows[index].requested_position.is_none() {
+            if let Some(parent) = self.parent_of(index, ctx) {
+                self.center_over(index, parent, ctx);
             }
         }
-        None
+        if ctx.windows[index].fullscreen {
+            self.replace_fullscreen(Some(index), ctx);
+            self.retile(ctx);
+        }
+    }
+
+    /// makes the window cover the entire screen, or puts it back where it belongs
+    pub fn set_fullscreen(&mut self, index: usize, fullscreen: bool, ctx: &mut Context) {
+        if self.get_window(index).is_none() || ctx.windows[index].fullscreen == fullscreen {
+            return;
+        }
+
+        if fullscreen {
+            if self.expanded == Some(index) {
+                self.collapse(ctx);
+            }
+            self.replace_fullscreen(Some(index), ctx);
+        } else {
+            self.replace_fullscreen(None, ctx);
+        }
+        self.retile_preserving_focus(ctx);
+    }
+
+    /// sets the fullscreen window, taking fullscreen away from the current one
+    fn replace_fullscreen(&mut self, new: Option<usize>, ctx: &mut Context) {
+        if let Some(old) = self.fullscreen.take() {
+            ctx.windows[old].fullscreen = false;
+            if self.is_floating(old) {
+                self.center_window(old, ctx);
+            }
+        }
+        if let Some(new) = new {
+            ctx.windows[new].fullscreen = true;
+        }
+        self.fullscreen = new;
+    }
+
+    /// gives the window half the size of the workspace and centers it
+    fn center_window(&mut self, index: usize, ctx: &mut Context) {
+        let width = self.pos.width / 2;
+        let height = self.pos.height / 2;
+        ctx.windows[index].update(
+            width,
+            height,
+            self.pos.x + (self.pos.width - width) / 2,
+            self.pos.y + (self.pos.height - height) / 2,
+            &ctx.connection,
+        );
+        self.raise_window(index, ctx);
+    }
+
+    /// gives a new floating window the geometry it asked for, fitted into the workspace. windows
+    /// that didn't ask for a size get half the workspace, and ones without a position are
+    /// cascaded from the center, so that they don't cover each other exactly
+    fn place_requested(&mut self, index: usize, ctx: &mut Context) {
+        let pos = self.pos;
+        let client = &ctx.windows[index];
+        let (width, height) = client
+            .requested_size
+            .unwrap_or((pos.width / 2, pos.height / 2));
+        let (width, height) = (width.min(pos.width), height.min(pos.height));
+        let (x, y) = match client.requested_position {
+            Some((x, y)) => (
+                x.clamp(pos.x as i16, (pos.x + pos.width - width) as i16) as u16,
+                y.clamp(pos.y as i16, (pos.y + pos.height - height) as i16) as u16,
+            ),
+            None => {
+                // the new window is already in the list
+                if self.floating_windows.len() <= 1 {
+                    self.cascaded = 0;
+                }
+                self.cascaded += 1;
+                cascade_position(pos, width, height, self.cascaded - 1)
+            }
+        };
+        ctx.windows[index].update(width, height, x, y, &ctx.connection);
+        self.raise_window(index, ctx);
+    }
+
+    /// the window that was focused when the workspace was hidden, if it is still on it
+    pub fn take_last_focused(&mut self) -> Option<usize> {
+        self.last_focused.take()
+    }
+
+    /// makes the window cover the workspace, or puts it back where it was
+    pub fn toggle_expanded(&mut self, window_idx: usize, ctx: &mut Context) {
+        if self.expanded == Some(window_idx) {
+            self.collapse(ctx);
+            self.retile_preserving_focus(ctx);
+            return;
+        }
+        if self.get_window(window_idx).is_none() || self.fullscreen == Some(window_idx) {
+            return;
+        }
+        self.collapse(ctx);
+        ctx.windows[window_idx].expanded_from = Some(ctx.windows[window_idx].position());
+        self.expanded = Some(window_idx);
+        self.retile_preserving_focus(ctx);
+    }
+
+    /// puts the expanded window back where it was before. doesn't retile
+    fn collapse(&mut self, ctx: &mut Context) {
+        let Some(window_idx) = self.expanded.take() else {
+            return;
+        };
+        // tiled windows get their place from the layout on the next retile anyways
+        if let Some(pos) = ctx.windows[window_idx].expanded_from.take() {
+            ctx.windows[window_idx].update(pos.width, pos.height, pos.x, pos.y, &ctx.connection);
+        }
+    }
+
+    /// moves the window from the tiled to the floating windows or back. `focused` stores a
+    /// position in one of the lists, so it is looked up again to stay on the same window. returns
+    /// whether the window floats now, or None if it isn't on this workspace
+    fn move_between_lists(&mut self, window_idx: usize) -> Option<bool> {
+        let (idx, is_floating) = self.get_window(window_idx)?;
+        let focused = self.focused_window();
+
+        if is_floating {
+            self.floating_windows.remove(idx);
+            self.windows.push(window_idx);
+        } else {
+            self.windows.remove(idx);
+            self.floating_windows.push(window_idx);
+        }
+        self.focused = focused.and_then(|window_idx| self.get_window(window_idx));
+        Some(!is_floating)
     }
 
     pub fn toggle_floating(&mut self, window_idx: usize, ctx: &mut Context) {
-        let Some((idx, enable)) = self.find_floating_window(window_idx) else {
+        if self.expanded == Some(window_idx) {
+            self.collapse(ctx);
+        }
+        let Some(floating) = self.move_between_lists(window_idx) else {
             return;
         };
-        if let Some((idx, _)) = self.focused {
-            if idx == window_idx {
-                self.focused = Some((idx, !enable));
+
+        let client = &mut ctx.windows[window_idx];
+        if floating {
+            client.floating = true;
+            client.borderless = false;
+            // go back to where the window last floated, or center it on the workspace the first
+            // time instead of keeping its tiled position
+            match client.floating_geometry {
+                Some(pos) => {
+                    client.update(pos.width, pos.height, pos.x, pos.y, &ctx.connection);
+                    self.raise_window(window_idx, ctx);
+                }
+                None => self.center_window(window_idx, ctx),
             }
+        } else {
+            client.floating_geometry = Some(client.position());
+            client.floating = false;
+            self.raise_window(window_idx, ctx);
+        }
+
+        self.retile_preserving_focus(ctx);
+    }
+
+    /// makes all tiled windows float. they go back to where they last floated, the others are
+    /// cascaded from the top left of the workspace
+    pub fn float_all(&mut self, ctx: &mut Context) {
+        self.collapse(ctx);
+        let focused = self.focused_window();
+        let fullscreen = self.fullscreen;
+        let (floated, tiled) = std::mem::take(&mut self.windows)
+            .into_iter()
+            .partition::<Vec<_>, _>(|&window_idx| Some(window_idx) != fullscreen);
+        self.windows = tiled;
+
+        let (width, height) = (self.pos.width / 2, self.pos.height / 2);
+        let mut cascaded = 0;
+        // the master is the last window, it ends up on top
+        for window_idx in floated {
+            let client = &mut ctx.windows[window_idx];
+            client.floating = true;
+            client.borderless = false;
+            let pos = client.floating_geometry.unwrap_or_else(|| {
+                let offset = cascaded * config::CASCADE_STEP;
+                cascaded += 1;
+                Position::new(
+                    self.pos.x + offset % (self.pos.width - width).max(1),
+                    self.pos.y + offset % (self.pos.height - height).max(1),
+                    width,
+                    height,
+                )
+            });
+            client.update(pos.width, pos.height, pos.x, pos.y, &ctx.connection);
+            self.floating_windows.push(window_idx);
+            self.raise_window(window_idx, ctx);
+        }
+        self.focused = focused.and_then(|window_idx| self.get_window(window_idx));
+
+        self.retile_preserving_focus(ctx);
+    }
+
+    /// puts all floating windows back into the layout, remembering where they floated
+    pub fn tile_all(&mut self, ctx: &mut Context) {
+        let focused = self.focused_window();
+        for window_idx in std::mem::take(&mut self.floating_windows) {
+            let client = &mut ctx.windows[window_idx];
+            client.floating_geometry = Some(client.position());
+            client.floating = false;
+            self.windows.push(window_idx);
+            self.raise_window(window_idx, ctx);
+        }
+        self.focused = focused.and_then(|window_idx| self.get_window(window_idx));
+
+        self.retile_preserving_focus(ctx);
+    }
+
+    /// makes a tiled window floating without moving it
+    pub fn float_in_place(&mut self, window_idx: usize, ctx: &mut Context) {
+        if self.is_floating(window_idx) || self.move_between_lists(window_idx).is_none() {
+            return;
         }
+        ctx.windows[window_idx].floating = true;
+        ctx.windows[window_idx].borderless = false;
+        self.raise_window(window_idx, ctx);
+
+        // no focus-preserving retile, that would warp the pointer away
+        self.retile(ctx);
+    }
+
+    /// removes the window from the workspace. returns if it was floating
+    pub fn take_window(&mut self, window_idx: usize, ctx: &mut Context) -> Option<bool> {
+        let (_, is_floating) = self.get_window(window_idx)?;
+        self.remove_window(window_idx, ctx);
+        Some(is_floating)
+    }
 
-        if enable {
-            let val = self.windows.remove(idx);
-            self.floating_windows.push(val);
+    /// adds a window that was on another workspace, keeping its geometry if it is floating
+    pub fn insert_window(&mut self, window_idx: usize, floating: bool, ctx: &mut Context) {
+        if floating {
+            self.floating_windows.push(window_idx);
         } else {
-            let val = self.floating_windows.remove(idx);
-            self.windows.push(val);
+            self.windows.push(window_idx);
         }
+        self.raise_window(window_idx, ctx);
+        self.retile(ctx);
+    }
 
+    /// puts `new` in the place of `old`, which is taken out of the workspace. returns false if
+    /// `old` isn't on this workspace
+    pub fn replace_window(&mut self, old: usize, new: usize, ctx: &mut Context) -> bool {
+        let Some((idx, is_floating)) = self.get_window(old) else {
+            return false;
+        };
+        if self.expanded == Some(old) {
+            self.collapse(ctx);
+        }
+        if self.fullscreen == Some(old) {
+            self.fullscreen = None;
+        }
+        self.unfocus(old, ctx);
+
+        if is_floating {
+            self.floating_windows[idx] = new;
+            let pos = ctx.windows[old].position();
+            ctx.windows[new].update(pos.width, pos.height, pos.x, pos.y, &ctx.connection);
+        } else {
+            self.windows[idx] = new;
+        }
+        ctx.windows[new].floating = is_floating;
+        for window_idx in self.stack.iter_mut() {
+            if *window_idx == old {
+                *window_idx = new;
+            }
+        }
+        if self.is_showing {
+            ctx.windows[new].show(&ctx.atoms, &ctx.connection);
+        }
         self.retile(ctx);
+        true
+    }
+
+    /// hides the window and takes it out of the layout until it is restored. returns false if the
+    /// window isn't on this workspace
+    pub fn minimize_window(&mut self, window_idx: usize, ctx: &mut Context) -> bool {
+        let Some(floating) = self.take_window(window_idx, ctx) else {
+            return false;
+        };
+        ctx.windows[window_idx].hide(&ctx.atoms, &ctx.connection);
+        self.minimized.push((window_idx, floating));
+        true
+    }
+
+    /// brings back the most recently minimized window, returning it
+    pub fn restore_last(&mut self, ctx: &mut Context) -> Option<usize> {
+        let &(window_idx, _) = self.minimized.last()?;
+        self.restore_window(window_idx, ctx);
+        Some(window_idx)
+    }
+
+    /// brings back a minimized window. returns false if it isn't minimized on this workspace
+    pub fn restore_window(&mut self, window_idx: usize, ctx: &mut Context) -> bool {
+        let Some(i) = self
+            .minimized
+            .iter()
+            .position(|&(idx, _)| idx == window_idx)
+        else {
+            return false;
+        };
+        let (_, floating) = self.minimized.remove(i);
+        if self.is_showing {
+            ctx.windows[window_idx].show(&ctx.atoms, &ctx.connection);
+        }
+        self.insert_window(window_idx, floating, ctx);
+        true
     }
 
     pub fn remove_window(&mut self, window_idx: usize, ctx: &mut Context) {
+        if self.last_focused == Some(window_idx) {
+            self.last_focused = None;
+        }
+        if self.expanded == Some(window_idx) {
+            self.expanded = None;
+            ctx.windows[window_idx].expanded_from = None;
+        }
+        self.minimized.retain(|&(idx, _)| idx != window_idx);
         self.unfocus(window_idx, ctx);
+        if self.fullscreen == Some(window_idx) {
+            self.fullscreen = None;
+        }
+        self.stack.retain(|&idx| idx != window_idx);
 
         let len = self.windows.len();
         for i in 0..self.windows.len() {
@@ -204,6 +983,11 @@ impl Workspace {
         self.retile(ctx);
     }
 
+    /// sets the area fullscreen windows cover. doesn't retile
+    pub fn set_fullscreen_position(&mut self, screen: Position) {
+        self.screen = screen;
+    }
+
     pub fn set_screen_position(&mut self, pos: Position, ctx: &mut Context) {
         self.pos = pos;
 
@@ -221,6 +1005,14 @@ impl Workspace {
             .copied()
     }
 
+    pub fn minimized_windows<'a>(&'a self) -> impl Iterator<Item = usize> + 'a {
+        self.minimized.iter().map(|&(idx, _)| idx)
+    }
+
+    pub fn layout(&self) -> Layout {
+        self.layout
+    }
+
     pub fn id(&self) -> u32 {
         self.id
     }
@@ -229,6 +1021,11 @@ impl Workspace {
         &self.name
     }
 
+    /// the names are published null-separated, so null bytes are left out
+    pub fn set_name(&mut self, name: &str) {
+        self.name = name.replace('\0', "");
+    }
+
     fn get_window(&self, window_idx: usize) -> Option<(usize, bool)> {
         for idx in 0..self.windows.len() {
             if self.windows[idx] == window_idx {
@@ -244,7 +1041,25 @@ impl Workspace {
         None
     }
 
+    pub fn is_floating(&self, window_idx: usize) -> bool {
+        self.floating_windows.contains(&window_idx)
+    }
+
+    /// the index of the focused window
+    pub fn focused_window(&self) -> Option<usize> {
+        let (idx, is_floating) = self.focused?;
+        Some(if is_floating {
+            self.floating_windows[idx]
+        } else {
+            self.windows[idx]
+        })
+    }
+
     pub fn focus_client(&mut self, window_idx: usize, ctx: &mut Context) -> bool {
+        if self.expanded.is_some_and(|expanded| expanded != window_idx) {
+            self.collapse(ctx);
+            self.retile(ctx);
+        }
         if let Some((idx, is_floating)) = self.focused.take() {
             let window_idx = if is_floating {
                 self.floating_windows[idx]
@@ -261,7 +1076,8 @@ impl Workspace {
             } else {
                 self.windows[idx]
             };
-            ctx.windows[window_idx].focus(&ctx.connection);
+            ctx.windows[window_idx].focus(&ctx.atoms, &ctx.connection);
+            self.raise_window(window_idx, ctx);
         }
         self.focused.is_some()
     }
@@ -293,13 +1109,232 @@ impl Workspace {
         }
     }
 
+    /// exchanges the windows with another workspace. both have to be hidden
+    pub fn swap_windows(&mut self, other: &mut Workspace) {
+        std::mem::swap(&mut self.windows, &mut other.windows);
+        std::mem::swap(&mut self.floating_windows, &mut other.floating_windows);
+        std::mem::swap(&mut self.focused, &mut other.focused);
+        std::mem::swap(&mut self.last_focused, &mut other.last_focused);
+        std::mem::swap(&mut self.fullscreen, &mut other.fullscreen);
+        std::mem::swap(&mut self.stack, &mut other.stack);
+        std::mem::swap(&mut self.minimized, &mut other.minimized);
+        std::mem::swap(&mut self.expanded, &mut other.expanded);
+    }
+
     pub fn clear_windows(&mut self) {
         self.windows.clear();
         self.floating_windows.clear();
+        self.minimized.clear();
         self.focused = None;
+        self.last_focused = None;
+        self.fullscreen = None;
+        self.expanded = None;
     }
 
     pub(crate) fn window_amount(&self) -> usize {
         self.windows.len() + self.floating_windows.len()
     }
 }
+
+/// swaps the window at `focused` with the master, which is the last window, or the master with the
+/// top of the stack right before it. returns where the window at `focused` ended up, or None if
+/// there was nothing to swap with
+fn swap_with_master(windows: &mut [usize], focused: usize) -> Option<usize> {
+    let master = windows.len().checked_sub(1)?;
+    if focused > master {
+        return None;
+    }
+    let other = if focused == master {
+        master.checked_sub(1)?
+    } else {
+        master
+    };
+    windows.swap(focused, other);
+    Some(other)
+}
+
+/// where the `n`th cascaded window of the given size goes. the first one is centered, every
+/// following one is moved down and to the right by `CASCADE_STEP`, wrapping around to the top or
+/// the left when it would leave the area
+fn cascade_position(area: Position, width: u16, height: u16, n: u32) -> (u16, u16) {
+    let offset = n as u64 * config::CASCADE_STEP as u64;
+    let place = |start: u16, len: u16, size: u16| {
+        let room = len.saturating_sub(size) as u64;
+        start + ((room / 2 + offset) % (room + 1)) as u16
+    };
+    (
+        place(area.x, area.width, width),
+        place(area.y, area.height, height),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stack_window_swaps_with_master() {
+        let mut windows = vec![10, 11, 12, 13];
+        assert_eq!(swap_with_master(&mut windows, 1), Some(3));
+        // the old master takes the old place of the window instead of everything rotating
+        assert_eq!(windows, [10, 13, 12, 11]);
+    }
+
+    #[test]
+    fn master_swaps_with_top_of_stack() {
+        let mut windows = vec![10, 11, 12];
+        assert_eq!(swap_with_master(&mut windows, 2), Some(1));
+        assert_eq!(windows, [10, 12, 11]);
+    }
+
+    #[test]
+    fn lone_window_is_not_swapped() {
+        let mut windows = vec![10];
+        assert_eq!(swap_with_master(&mut windows, 0), None);
+        assert_eq!(windows, [10]);
+    }
+
+    fn workspace(tiled: &[usize], floating: &[usize]) -> Workspace {
+        let mut workspace = Workspace::new(
+            Position::new(0, 0, 800, 600),
+            1,
+            String::new(),
+            Layout::Grid,
+        );
+        workspace.windows = tiled.to_vec();
+        workspace.floating_windows = floating.to_vec();
+        workspace
+    }
+
+    #[test]
+    fn focused_window_stays_focused_when_it_floats() {
+        let mut workspace = workspace(&[10, 11, 12], &[]);
+        workspace.focused = workspace.get_window(11);
+
+        assert_eq!(workspace.move_between_lists(11), Some(true));
+        assert_eq!(workspace.focused, Some((0, true)));
+        assert_eq!(workspace.focused_window(), Some(11));
+
+        assert_eq!(workspace.move_between_lists(11), Some(false));
+        assert_eq!(workspace.focused, Some((2, false)));
+        assert_eq!(workspace.focused_window(), Some(11));
+    }
+
+    #[test]
+    fn focus_follows_its_window_when_another_one_floats() {
+        let mut workspace = workspace(&[10, 11, 12], &[20]);
+        workspace.focused = workspace.get_window(12);
+
+        // the focused window moves up in the tiled list
+        assert_eq!(workspace.move_between_lists(10), Some(true));
+        assert_eq!(workspace.focused_window(), Some(12));
+
+        // and the focused floating window moves up in the floating list
+        workspace.focused = workspace.get_window(10);
+        assert_eq!(workspace.focused, Some((1, true)));
+        assert_eq!(workspace.move_between_lists(20), Some(false));
+        assert_eq!(workspace.focused, Some((0, true)));
+        assert_eq!(workspace.focused_window(), Some(10));
+    }
+
+    #[test]
+    fn windows_of_other_workspaces_are_not_moved() {
+        let mut workspace = workspace(&[10], &[20]);
+        workspace.focused = workspace.get_window(20);
+
+        assert_eq!(workspace.move_between_lists(30), None);
+        assert_eq!(workspace.windows, [10]);
+        assert_eq!(workspace.floating_windows, [20]);
+        assert_eq!(workspace.focused_window(), Some(20));
+    }
+
+    #[test]
+    fn cascaded_windows_start_centered_and_wrap_around() {
+        let area = Position::new(100, 20, 400, 300);
+        assert_eq!(cascade_position(area, 200, 100, 0), (200, 120));
+        let step = config::CASCADE_STEP;
+        assert_eq!(
+            cascade_position(area, 200, 100, 1),
+            (200 + step, 120 + step)
+        );
+        for n in 0..50 {
+            let (x, y) = cascade_position(area, 200, 100, n);
+            assert!((100..=300).contains(&x), "{n}: {x}");
+            assert!((20..=220).contains(&y), "{n}: {y}");
+        }
+        // a window as large as the area stays in place
+        assert_eq!(cascade_position(area, 400, 300, 3), (100, 20));
+    }
+
+    #[test]
+    fn rotating_the_stack_keeps_the_focus() {
+        let mut workspace = workspace(&[10, 11, 12], &[20]);
+        workspace.focused = workspace.get_window(12);
+
+        // the master moves to the bottom of the stack
+        workspace.rotate(true);
+        assert_eq!(workspace.windows, [12, 10, 11]);
+        assert_eq!(workspace.focused_window(), Some(12));
+
+        workspace.rotate(false);
+        workspace.rotate(false);
+        assert_eq!(workspace.windows, [11, 12, 10]);
+        assert_eq!(workspace.focused_window(), Some(12));
+
+        // a focused floating window isn't affected
+        workspace.focused = workspace.get_window(20);
+        workspace.rotate(true);
+        assert_eq!(workspace.focused_window(), Some(20));
+    }
+
+    #[test]
+    fn swapping_with_the_master_keeps_the_focus() {
+        let mut workspace = workspace(&[10, 11, 12, 13], &[20]);
+        workspace.focused = workspace.get_window(11);
+
+        assert!(workspace.swap_focused_with_master());
+        assert_eq!(workspace.windows, [10, 13, 12, 11]);
+        assert_eq!(workspace.focused_window(), Some(11));
+
+        // the master trades places with the top of the stack
+        assert!(workspace.swap_focused_with_master());
+        assert_eq!(workspace.windows, [10, 13, 11, 12]);
+        assert_eq!(workspace.focused_window(), Some(11));
+
+        // floating windows aren't part of the layout, so nothing has to be retiled
+        workspace.focused = workspace.get_window(20);
+        assert!(!workspace.swap_focused_with_master());
+        assert_eq!(workspace.windows, [10, 13, 11, 12]);
+        assert_eq!(workspace.focused_window(), Some(20));
+    }
+
+    #[test]
+    fn new_windows_go_where_the_config_says() {
+        let mut workspace = workspace(&[10, 11, 12], &[]);
+        workspace.focused = workspace.get_window(11);
+
+        workspace.insert_tiled(13, NewWindowPosition::Master);
+        assert_eq!(workspace.windows, [10, 11, 12, 13]);
+        assert_eq!(workspace.focused_window(), Some(11));
+
+        // next to the focused window, on the side of the master
+        workspace.insert_tiled(14, NewWindowPosition::NextToFocused);
+        assert_eq!(workspace.windows, [10, 11, 14, 12, 13]);
+        assert_eq!(workspace.focused_window(), Some(11));
+
+        workspace.insert_tiled(15, NewWindowPosition::StackBottom);
+        assert_eq!(workspace.windows, [15, 10, 11, 14, 12, 13]);
+        assert_eq!(workspace.focused, Some((2, false)));
+        assert_eq!(workspace.focused_window(), Some(11));
+    }
+
+    #[test]
+    fn new_windows_next_to_a_floating_window_become_the_master() {
+        let mut workspace = workspace(&[10, 11], &[20]);
+        workspace.focused = workspace.get_window(20);
+
+        workspace.insert_tiled(12, NewWindowPosition::NextToFocused);
+        assert_eq!(workspace.windows, [10, 11, 12]);
+        assert_eq!(workspace.focused_window(), Some(20));
+    }
+}
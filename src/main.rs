@@ -1,4 +1,4 @@
-use tracing::info;
+use tracing::{info, warn};
 use wm::Wm;
 
 macro_rules! trace_result {
@@ -33,31 +33,58 @@ macro_rules! request_sync {
 
 pub mod actions;
 pub mod atoms;
+pub mod bar;
 mod config;
 pub mod drawing;
 pub mod events;
 pub mod ewmh;
+pub mod icccm;
+mod ipc;
 pub mod keyboard;
 pub mod layout;
 pub mod screen;
+mod session;
+mod signals;
 pub mod slab;
 pub mod tiling;
 mod wm;
 
 fn main() -> anyhow::Result<()> {
+    // checking the config doesn't need the display, so it works over ssh or in scripts
+    if std::env::args_os()
+        .nth(1)
+        .is_some_and(|arg| arg == "--check-config")
+    {
+        return config::check_config();
+    }
+
     let (dir, log_file) = config::get_log_file()?;
     let writer = tracing_appender::rolling::daily(dir, log_file);
-    let (non_blocking, _guard) = tracing_appender::non_blocking(writer);
+    let (non_blocking, log_guard) = tracing_appender::non_blocking(writer);
+    let (level, level_error) = config::get_log_level();
     let subscriber = tracing_subscriber::FmtSubscriber::builder()
-        .with_max_level(tracing::Level::TRACE)
+        .with_max_level(level)
         .with_ansi(false)
         .with_writer(non_blocking)
         .finish();
     tracing::subscriber::set_global_default(subscriber).expect("Setting the subscriber failed");
+    if let Some(e) = level_error {
+        warn!("{e:?}");
+    }
 
-    info!("acd");
+    info!(version = env!("CARGO_PKG_VERSION"), %level, "starting");
 
-    let mut wm = Wm::new()?;
+    let actions = config::load_actions();
+    let mut config = config::Config::default();
+    config::load_settings(&mut config);
+    let mut wm = Wm::new(config)?;
 
-    wm.run(actions::ACTIONS)
+    if wm.run(actions)? {
+        // close the connection first, the new instance has to become the window manager
+        drop(wm);
+        // exec doesn't run destructors, so the buffered log lines have to be written now
+        drop(log_guard);
+        return Err(wm::restart());
+    }
+    Ok(())
 }
@@ -5,22 +5,39 @@ use std::{
 };
 
 const WINDOW_BAR_HEIGHT: u16 = 20;
+const TITLE_FONT: &str = "fixed";
+
+// the actions of a _NET_WM_STATE client message
+const NET_WM_STATE_REMOVE: u32 = 0;
+const NET_WM_STATE_ADD: u32 = 1;
+const NET_WM_STATE_TOGGLE: u32 = 2;
 
 use anyhow::{Context as _, Result};
+use serde::Serialize;
 use tracing::{error, warn};
 use xcb::{
     x::{
-        ChangeWindowAttributes, ConfigWindow, ConfigureWindow, CreateWindow, Cw, DestroyWindow,
-        EventMask, GetProperty, GetPropertyReply, MapWindow, ReparentWindow, SetInputFocus,
-        UnmapWindow, Window as XWindow, ATOM_ANY, ATOM_CARDINAL, COPY_FROM_PARENT, CURRENT_TIME,
+        Atom, ButtonIndex, ChangeWindowAttributes, ConfigWindow, ConfigureNotifyEvent,
+        ConfigureWindow, CreateWindow, Cursor, Cw, DestroyWindow, Drawable, EventMask, GetGeometry,
+        GetProperty, GetWindowAttributes, GrabButton, GrabMode, KillClient, MapWindow, ModMask,
+        NoOperation, ReparentWindow, SendEvent, SendEventDest, SetInputFocus, StackMode,
+        UnmapWindow, WarpPointer, Window as XWindow, ATOM_ANY, ATOM_CARDINAL, ATOM_STRING,
+        ATOM_WM_HINTS, ATOM_WM_NAME, COPY_FROM_PARENT, CURRENT_TIME,
     },
-    Connection, Xid,
+    Connection, Cookie as _, Xid,
 };
 
 use crate::{
     atoms::Atoms,
-    config, ewmh,
-    layout::{Position, Workspace},
+    bar::{Bar, BarContent},
+    config::{self, Config},
+    drawing::{ArgbVisual, DrawContext},
+    events::ConfigureRequest,
+    ewmh,
+    icccm::{self, SizeHints, WmHints, WmState},
+    ipc,
+    layout::{Direction, Position, Workspace},
+    session::{Session, SessionEntry},
     slab::Slab,
     tiling::Layout,
 };
@@ -33,17 +50,79 @@ pub struct Context {
     pub(crate) root_window: XWindow,
     pub(crate) connection: Arc<Connection>,
     pub(crate) focused_window: Option<usize>,
+    pub(crate) depth: u8,
+    pub(crate) config: Config,
+    /// the sequence number of the first request after windows were moved around. EnterNotify
+    /// events from before it were caused by the windows moving, not by the pointer
+    pub(crate) enter_barrier: Option<u16>,
+}
+
+impl Context {
+    /// makes `Screen::enter_client` ignore the EnterNotify events caused by the requests that
+    /// were sent so far
+    pub(crate) fn ignore_pending_enters(&mut self) {
+        let cookie = self.connection.send_request(&NoOperation {});
+        self.enter_barrier = Some(cookie.sequence() as u16);
+        trace_result!(self.connection.flush());
+    }
+}
+
+/// the EWMH properties that changed since they were last set. they are set at most once per
+/// iteration of the event loop, by `Screen::flush_atoms`
+#[derive(Debug, Default, Clone, Copy)]
+struct StaleAtoms {
+    viewport: bool,
+    current_desktop: bool,
+    desktop_names: bool,
+    wm_desktop: bool,
+    client_list: bool,
+    stacking: bool,
+    active_window: bool,
+}
+
+impl StaleAtoms {
+    fn all() -> Self {
+        Self {
+            viewport: true,
+            current_desktop: true,
+            desktop_names: true,
+            wm_desktop: true,
+            client_list: true,
+            stacking: true,
+            active_window: true,
+        }
+    }
 }
 
 pub struct Screen {
     width: u16,
     height: u16,
-    reserved_space_bottom: u16,
-    reserved_space_top: u16,
-    reserved_space_left: u16,
-    reserved_space_right: u16,
-    workspaces: [Workspace; 10],
+    reserved: ReservedSpace,
+    /// the reserved space is ignored, so that the windows cover the panels and the bar. the
+    /// reserved clients are still tracked, so the space comes back as they are now
+    struts_disabled: bool,
+    workspaces: Vec<Workspace>,
     context: Context,
+    bar: Option<Bar>,
+    focus_follows_mouse: bool,
+    /// the scratchpad window. it isn't part of any workspace
+    scratchpad: Option<usize>,
+    /// the windows that want attention, the most recent one last
+    urgent: Vec<usize>,
+    /// the windows in the order they were focused in, the most recent one first
+    mru: Vec<usize>,
+    /// the position in `mru` that is being previewed while cycling through the windows
+    mru_cycle: Option<usize>,
+    /// the workspace that was shown before the current one
+    last_workspace: Option<u8>,
+    /// the session that was saved last
+    session: Session,
+    /// the saved session while the existing windows are adopted
+    restoring: Option<Session>,
+    /// the root window properties that have to be set again
+    stale: StaleAtoms,
+    /// the client _NET_ACTIVE_WINDOW was set to last, to notice when the focus moved
+    active_window: XWindow,
 
     global_windows: Slab<ReservedClient>,
 }
@@ -52,35 +131,57 @@ impl Screen {
     pub fn new(
         width: u16,
         height: u16,
-        gap: u16,
+        config: Config,
         atoms: Atoms,
         root_window: XWindow,
         connection: Arc<Connection>,
         depth: u8,
     ) -> anyhow::Result<Self, xcb::ProtocolError> {
-        // let mut draw = DrawContext::new(root_window, Position::new(0, 0, width, 25), connection.clone(), depth)?;
-        // draw.open_font("fixed")?;
+        let argb = if config::BAR_ARGB {
+            ArgbVisual::find(root_window, &connection).unwrap_or_else(|e| {
+                error!("failed to create a colormap for the bar: {e:?}");
+                None
+            })
+        } else {
+            None
+        };
+        let bar = match Bar::new(root_window, width, connection.clone(), depth, argb) {
+            Ok(bar) => Some(bar),
+            Err(e) => {
+                error!("failed to create the status bar: {e:?}");
+                None
+            }
+        };
 
+        // current_workspace is a u8
+        let workspace_count = config.workspaces.clamp(1, u8::MAX as usize);
         let mut me = Self {
             width,
             height,
-            reserved_space_bottom: 0,
-            reserved_space_left: 0,
-            reserved_space_right: 0,
-            reserved_space_top: 0,
-            // draw,
-            workspaces: [
-                Workspace::new(Position::new(0, 25, width, height), gap, 1),
-                Workspace::new(Position::new(0, 25, width, height), gap, 2),
-                Workspace::new(Position::new(0, 25, width, height), gap, 3),
-                Workspace::new(Position::new(0, 25, width, height), gap, 4),
-                Workspace::new(Position::new(0, 25, width, height), gap, 5),
-                Workspace::new(Position::new(0, 25, width, height), gap, 6),
-                Workspace::new(Position::new(0, 25, width, height), gap, 7),
-                Workspace::new(Position::new(0, 25, width, height), gap, 8),
-                Workspace::new(Position::new(0, 25, width, height), gap, 9),
-                Workspace::new(Position::new(0, 25, width, height), gap, 10),
-            ],
+            reserved: ReservedSpace::default(),
+            struts_disabled: false,
+            bar,
+            focus_follows_mouse: config::FOCUS_FOLLOWS_MOUSE,
+            scratchpad: None,
+            urgent: vec![],
+            mru: vec![],
+            mru_cycle: None,
+            last_workspace: None,
+            session: Session::default(),
+            restoring: None,
+            stale: StaleAtoms::all(),
+            active_window: XWindow::none(),
+            workspaces: (1..=workspace_count as u32)
+                .map(|id| {
+                    let pos = Position::new(0, 25, width, height);
+                    Workspace::new(
+                        pos,
+                        id,
+                        config.workspace_name(id),
+                        config.workspace_layout(id),
+                    )
+                })
+                .collect(),
             global_windows: Slab::new(),
             context: Context {
                 connection,
@@ -90,49 +191,61 @@ impl Screen {
                 root_window,
                 focused_window: None,
                 current_workspace: 0,
+                depth,
+                config,
+                enter_barrier: None,
             },
         };
-        ewmh::set_number_of_desktops(10, root_window, &atoms, &me.context.connection)?;
-        me.switch_workspace(1)?;
+        ewmh::set_supported(root_window, &atoms, &me.context.connection)?;
+        ewmh::set_number_of_desktops(
+            me.workspaces.len() as u32,
+            root_window,
+            &atoms,
+            &me.context.connection,
+        )?;
+        ewmh::set_showing_desktop(false, root_window, &atoms, &me.context.connection)?;
+        me.switch_workspace(0)?;
 
         me.size_updated();
-        _ = me.update_atoms();
+        me.flush_atoms();
         Ok(me)
     }
 
     pub fn update_size(&mut self, width: u16, height: u16) {
+        if let Some(bar) = &mut self.bar {
+            trace_result!(bar.resize(width); "failed to resize the status bar");
+        }
         self.width = width;
         self.height = height;
         self.size_updated();
     }
 
+    /// recomputes the reserved space from the bar and the live reserved clients and resizes the
+    /// workspaces to the remaining area
     fn size_updated(&mut self) {
-        if self.reserved_space_bottom + self.reserved_space_top >= self.height {
-            warn!("The window is smaller than the reserved space (top: {}, bottom: {}, total: {}, window height: {})\nUnreserving Space",
-                self.reserved_space_top, self.reserved_space_bottom, self.reserved_space_top + self.reserved_space_bottom, self.height);
-            self.reserved_space_top = 0;
-            self.reserved_space_bottom = 0;
+        let mut reserved = ReservedSpace::default();
+        if !self.struts_disabled {
+            reserved.top = self.bar.as_ref().map_or(0, |_| config::BAR_HEIGHT);
+            for client in self.global_windows.iter() {
+                reserved.reserve(client.direction, client.reserved);
+            }
         }
-        if self.reserved_space_left + self.reserved_space_right >= self.width {
-            warn!("The window is smaller than the reserved space (left: {}, right: {}, total: {}, window width: {})\nUnreserving Space",
-                self.reserved_space_left, self.reserved_space_right, self.reserved_space_left+self.reserved_space_right, self.width);
-
-            self.reserved_space_left = 0;
-            self.reserved_space_right = 0;
+        let requested = reserved;
+        let unreserved = reserved.fit(self.width, self.height);
+        if !unreserved.is_empty() {
+            warn!(
+                "The window ({}x{}) is smaller than the reserved space ({requested:?})\nUnreserving {unreserved:?}",
+                self.width, self.height
+            );
         }
+        self.reserved = reserved;
 
+        let area = self.reserved.area(self.width, self.height);
         for workspace in self.workspaces.iter_mut() {
-            workspace.set_screen_position(
-                Position::new(
-                    self.reserved_space_left,
-                    self.reserved_space_top,
-                    self.width - self.reserved_space_left - self.reserved_space_right,
-                    self.height - self.reserved_space_top - self.reserved_space_bottom,
-                ),
-                &mut self.context,
-            );
+            workspace.set_fullscreen_position(Position::new(0, 0, self.width, self.height));
+            workspace.set_screen_position(area, &mut self.context);
         }
-        _ = self.update_atoms();
+        self.stale.viewport = true;
     }
 
     pub fn add_reserved_client(&mut self, client: ReservedClient) -> anyhow::Result<()> {
@@ -148,7 +261,9 @@ impl Screen {
                 .connection
                 .send_request_checked(&ChangeWindowAttributes {
                     window: client.window,
-                    value_list: &[Cw::EventMask(EventMask::ENTER_WINDOW)],
+                    value_list: &[Cw::EventMask(
+                        EventMask::ENTER_WINDOW | EventMask::PROPERTY_CHANGE,
+                    )],
                 });
 
         self.context.connection.check_request(map_cookie)?;
@@ -156,75 +271,326 @@ impl Screen {
             .connection
             .check_request(change_attributes_cookie)?;
         self.global_windows.push(client);
-        self.update_atoms()?;
+        self.stale.stacking = true;
         Ok(())
     }
 
     pub fn switch_workspace(&mut self, new_workspace: u8) -> Result<(), xcb::ProtocolError> {
+        if new_workspace as usize >= self.workspaces.len() {
+            warn!("tried to switch to the non-existent workspace #{new_workspace}");
+            return Ok(());
+        }
         let old_workspace = self.context.current_workspace;
         self.context.current_workspace = new_workspace;
-        self.update_atoms()?;
+        if old_workspace != new_workspace {
+            self.last_workspace = Some(old_workspace);
+        }
+
+        // sticky windows move along, so they never get hidden
+        let sticky = self.take_sticky(old_workspace as usize);
+        let focused_sticky = self
+            .context
+            .focused_window
+            .filter(|idx| sticky.iter().any(|&(sticky, _)| sticky == *idx));
         self.workspaces[old_workspace as usize].hide(&mut self.context);
+        self.insert_sticky(new_workspace as usize, sticky);
         self.workspaces[new_workspace as usize].show(&mut self.context);
+        // a sticky window keeps the focus, otherwise the workspace gets back the window that was
+        // focused when it was left
+        let last_focused = self.workspaces[new_workspace as usize].take_last_focused();
+        match focused_sticky.or(last_focused) {
+            Some(idx) => self.focus_window(self.context.windows[idx].window),
+            None => self.focus_last_used(),
+        }
+        self.restack();
+        if config::WARP_POINTER_ON_SWITCH {
+            self.warp_pointer_to_workspace();
+        }
+        self.stale.current_desktop = true;
+        self.stale.wm_desktop = true;
+        Ok(())
+    }
+
+    /// takes the sticky windows off the workspace, returning them and whether they are floating
+    fn take_sticky(&mut self, workspace: usize) -> Vec<(usize, bool)> {
+        let sticky = self.workspaces[workspace]
+            .windows()
+            .filter(|&idx| self.context.windows[idx].sticky)
+            .collect::<Vec<_>>();
+        sticky
+            .into_iter()
+            .filter_map(|idx| {
+                self.workspaces[workspace]
+                    .take_window(idx, &mut self.context)
+                    .map(|floating| (idx, floating))
+            })
+            .collect()
+    }
+
+    fn insert_sticky(&mut self, workspace: usize, sticky: Vec<(usize, bool)>) {
+        for (idx, floating) in sticky {
+            self.context.windows[idx].workspace = workspace as u8;
+            self.workspaces[workspace].insert_window(idx, floating, &mut self.context);
+        }
+    }
+
+    /// exchanges the windows of the workspaces `a` and `b`. sticky windows stay on the current
+    /// workspace
+    pub fn swap_workspaces(&mut self, a: u8, b: u8) -> Result<(), xcb::ProtocolError> {
+        let (a, b) = (a as usize, b as usize);
+        if a == b || a >= self.workspaces.len() || b >= self.workspaces.len() {
+            return Ok(());
+        }
+        let current = self.context.current_workspace as usize;
+        let showing = current == a || current == b;
+
+        let sticky = self.take_sticky(current);
+        if showing {
+            self.workspaces[current].hide(&mut self.context);
+        }
+        let (first, second) = self.workspaces.split_at_mut(a.max(b));
+        first[a.min(b)].swap_windows(&mut second[0]);
+        for workspace in [a, b] {
+            let workspace_windows = self.workspaces[workspace]
+                .windows()
+                .chain(self.workspaces[workspace].minimized_windows())
+                .collect::<Vec<_>>();
+            for idx in workspace_windows {
+                self.context.windows[idx].workspace = workspace as u8;
+            }
+        }
+        self.insert_sticky(current, sticky);
+
+        if showing {
+            self.workspaces[current].show(&mut self.context);
+            self.focus_last_used();
+        }
+        self.stale.wm_desktop = true;
+        Ok(())
+    }
+
+    /// moves the pointer to the center of the current workspace
+    fn warp_pointer_to_workspace(&self) {
+        let pos = self.workspaces[self.context.current_workspace as usize].get_screen_position();
+        trace_result!(self.context.connection.send_and_check_request(&WarpPointer {
+            src_window: XWindow::none(),
+            dst_window: self.context.root_window,
+            src_x: 0,
+            src_y: 0,
+            src_width: 0,
+            src_height: 0,
+            dst_x: (pos.x + pos.width / 2) as i16,
+            dst_y: (pos.y + pos.height / 2) as i16,
+        }); "failed to warp the pointer");
+    }
+
+    /// switches to the workspace with the given id. returns false if there is no such workspace
+    pub fn switch_to_workspace_id(&mut self, id: u32) -> Result<bool, xcb::ProtocolError> {
+        let Some(idx) = self.workspaces.iter().position(|ws| ws.id() == id) else {
+            return Ok(false);
+        };
+        self.switch_workspace(idx as u8)?;
+        Ok(true)
+    }
+
+    /// switches back to the workspace that was shown before the current one
+    pub fn switch_to_last_workspace(&mut self) -> Result<(), xcb::ProtocolError> {
+        match self.last_workspace {
+            Some(workspace) => self.switch_workspace(workspace),
+            None => Ok(()),
+        }
+    }
+
+    /// renames the current workspace and tells pagers about it
+    pub fn rename_workspace(&mut self, name: &str) -> Result<(), xcb::ProtocolError> {
+        self.workspaces[self.context.current_workspace as usize].set_name(name);
+        self.stale.desktop_names = true;
         Ok(())
     }
 
-    pub fn update_atoms(&self) -> Result<(), xcb::ProtocolError> {
+    /// switches to the next workspace, or the previous one if `direction` is negative. wraps
+    /// around at either end. with `config::SKIP_EMPTY_WORKSPACES`, only workspaces with windows on
+    /// them are switched to
+    pub fn cycle_workspace(&mut self, direction: i32) -> Result<(), xcb::ProtocolError> {
+        let len = self.workspaces.len() as i32;
+        let step = direction.signum();
+        let mut idx = self.context.current_workspace as i32;
+
+        for _ in 1..len {
+            idx = (idx + step).rem_euclid(len);
+            if !config::SKIP_EMPTY_WORKSPACES || self.workspaces[idx as usize].window_amount() > 0 {
+                return self.switch_workspace(idx as u8);
+            }
+        }
+        Ok(())
+    }
+
+    /// sets the EWMH properties that changed since the last call
+    pub fn flush_atoms(&mut self) {
+        let stale = std::mem::take(&mut self.stale);
+        // the focus changes in many places, so it is compared instead of being marked as stale
+        let active = self
+            .context
+            .focused_window
+            .map_or(XWindow::none(), |idx| self.context.windows[idx].window);
+        if stale.active_window || active != self.active_window {
+            self.active_window = active;
+            // a title that changed while a PropertyNotify was missed is caught up on here, so
+            // that bars reading the title of the active window get the current one
+            if let Some(idx) = self.context.focused_window {
+                self.context.windows[idx]
+                    .update_name(&self.context.atoms, &self.context.connection);
+            }
+            trace_result!(ewmh::set_active_window(
+                active,
+                self.context.root_window,
+                &self.context.atoms,
+                &self.context.connection,
+            ); "failed to set _NET_ACTIVE_WINDOW");
+        }
         let atoms = &self.context.atoms;
         let conn = &self.context.connection;
+        let root = self.context.root_window;
 
-        ewmh::set_desktop_viewport(
-            self.reserved_space_left as u32,
-            self.reserved_space_top as u32,
-            self.context.root_window,
-            atoms,
-            conn,
-        )?;
-        ewmh::set_number_of_desktops(
-            self.workspaces.len() as u32,
-            self.context.root_window,
-            atoms,
-            conn,
-        )?;
-        ewmh::set_current_desktop(
-            self.context.current_workspace as u32,
-            self.context.root_window,
-            atoms,
-            conn,
-        )?;
-        ewmh::set_desktop_names(&self.workspaces, self.context.root_window, atoms, conn)?;
-        ewmh::set_wm_desktop(&self.workspaces, &self.context)?;
+        if stale.viewport {
+            trace_result!(ewmh::set_desktop_viewport(
+                self.reserved.left as u32,
+                self.reserved.top as u32,
+                root,
+                atoms,
+                conn,
+            ); "failed to set _NET_DESKTOP_VIEWPORT");
+        }
+        if stale.current_desktop {
+            trace_result!(ewmh::set_current_desktop(
+                self.context.current_workspace as u32,
+                root,
+                atoms,
+                conn,
+            ); "failed to set _NET_CURRENT_DESKTOP");
+        }
+        if stale.desktop_names {
+            trace_result!(ewmh::set_desktop_names(&self.workspaces, root, atoms, conn); "failed to set _NET_DESKTOP_NAMES");
+        }
+        if stale.wm_desktop {
+            trace_result!(ewmh::set_wm_desktop(&self.workspaces, &self.context); "failed to set _NET_WM_DESKTOP");
+        }
+        if stale.client_list {
+            trace_result!(ewmh::set_client_list(
+                &self
+                    .clients()
+                    .map(|client| client.window)
+                    .collect::<Vec<_>>(),
+                root,
+                atoms,
+                conn,
+            ); "failed to set _NET_CLIENT_LIST");
+        }
+        if stale.stacking {
+            trace_result!(self.update_stacking(); "failed to update _NET_CLIENT_LIST_STACKING");
+        }
+    }
 
+    /// sets _NET_CLIENT_LIST_STACKING. the reserved windows are always on top
+    fn update_stacking(&self) -> Result<(), xcb::ProtocolError> {
         let current_workspace = &self.workspaces[self.context.current_workspace as usize];
-        ewmh::set_client_list(
-            &self
-                .context
-                .window_lookup
-                .values()
-                .map(|v| self.context.windows[*v].window)
-                .collect::<Vec<_>>(),
-            self.context.root_window,
-            atoms,
-            conn,
-        )?;
         let mut windows =
             Vec::with_capacity(self.global_windows.max_len() + current_workspace.window_amount());
-        windows.extend(self.global_windows.iter().map(|v| v.window));
         windows.extend(
             current_workspace
-                .windows()
+                .stacking_order()
+                .iter()
+                .map(|&v| self.context.windows[v].window),
+        );
+        windows.extend(
+            self.visible_scratchpad()
                 .map(|v| self.context.windows[v].window),
         );
-        ewmh::set_client_list_stacking(&windows, self.context.root_window, atoms, conn)?;
-        ewmh::set_showing_desktop(false, self.context.root_window, atoms, conn)?;
+        windows.extend(self.global_windows.iter().map(|v| v.window));
+        ewmh::set_client_list_stacking(
+            &windows,
+            self.context.root_window,
+            &self.context.atoms,
+            &self.context.connection,
+        )
+    }
 
-        Ok(())
+    /// puts the reserved windows and the bar back above the managed windows, unless a window is
+    /// fullscreen. also updates _NET_CLIENT_LIST_STACKING
+    fn restack(&mut self) {
+        let current_workspace = &self.workspaces[self.context.current_workspace as usize];
+        if current_workspace.fullscreen_window().is_none() {
+            self.raise_reserved_windows();
+        }
+        if let Some(idx) = self.visible_scratchpad() {
+            self.context.windows[idx].raise(&self.context.connection);
+        }
+        self.stale.stacking = true;
+    }
+
+    fn raise_reserved_windows(&self) {
+        let conn = &self.context.connection;
+        let windows = self
+            .global_windows
+            .iter()
+            .map(|client| client.window)
+            .chain(self.bar.as_ref().map(Bar::window));
+        for window in windows {
+            trace_result!(conn.send_and_check_request(&ConfigureWindow {
+                window,
+                value_list: &[ConfigWindow::StackMode(StackMode::Above)],
+            }); "failed to raise a reserved window");
+        }
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.context.config
+    }
+
+    pub fn config_mut(&mut self) -> &mut Config {
+        &mut self.context.config
+    }
+
+    pub fn focus_follows_mouse(&self) -> bool {
+        self.focus_follows_mouse
+    }
+
+    /// focuses the window the pointer entered, unless it only got under the pointer because the
+    /// windows were rearranged
+    pub fn enter_client(&mut self, client: XWindow, sequence: u16) {
+        if let Some(barrier) = self.context.enter_barrier {
+            // sequence numbers wrap around
+            if (barrier.wrapping_sub(sequence) as i16) > 0 {
+                return;
+            }
+            self.context.enter_barrier = None;
+        }
+        // previewed windows are raised, which moves other windows under the pointer
+        if self.focus_follows_mouse && self.mru_cycle.is_none() {
+            self.focus_window(client);
+        }
+    }
+
+    pub fn click_client(&mut self, client: XWindow) {
+        if !self.focus_follows_mouse {
+            self.focus_window(client);
+        }
     }
 
-    pub fn enter_client(&mut self, client: XWindow) {
+    fn focus_window(&mut self, client: XWindow) {
+        // windows we don't know about, like menus and tooltips, don't take the focus away
+        if client != self.context.root_window
+            && self.client_idx(client).is_none()
+            && !self.global_windows.iter().any(|c| c.window == client)
+        {
+            return;
+        }
         for workspace in self.workspaces.iter_mut() {
             workspace.unfocus_all(&mut self.context);
         }
+        if let Some(idx) = self.scratchpad {
+            self.context.windows[idx].unfocus(&self.context.connection);
+        }
         self.context.focused_window = None;
 
         if client == self.context.root_window {
@@ -237,11 +603,19 @@ impl Screen {
             return;
         }
 
-        if let Some(idx) = self.context.window_lookup.get(&client).copied() {
+        if let Some(idx) = self.client_idx(client) {
+            if self.scratchpad == Some(idx) {
+                self.context.windows[idx].focus(&self.context.atoms, &self.context.connection);
+                self.context.focused_window = Some(idx);
+                self.touch_mru(idx);
+                return;
+            }
             if self.workspaces[self.context.current_workspace as usize]
                 .focus_client(idx, &mut self.context)
             {
                 self.context.focused_window = Some(idx);
+                self.touch_mru(idx);
+                self.restack();
                 return;
             }
         }
@@ -260,354 +634,1417 @@ impl Screen {
         }
     }
 
-    fn free_reserved_space(&mut self, amount: u16, direction: ScreenSide) {
-        match direction {
-            ScreenSide::Bottom => self.free_space_bottom(amount),
-            ScreenSide::Left => self.free_space_left(amount),
-            ScreenSide::Right => self.free_space_right(amount),
-            ScreenSide::Top => self.free_space_top(amount),
-        }
+    /// the index of the client that owns the window, which can be either its frame or the client
+    /// window itself
+    fn client_idx(&self, window: XWindow) -> Option<usize> {
+        self.context.window_lookup.get(&window).copied()
+    }
+
+    /// the client that owns the window, which can be either its frame or the client window itself
+    pub fn client_for_window(&self, window: XWindow) -> Option<&Client> {
+        self.client_idx(window)
+            .map(|idx| &self.context.windows[idx])
+    }
+
+    /// every managed client, once each
+    pub fn clients(&self) -> impl Iterator<Item = &Client> {
+        self.context.windows.iter()
     }
 
     pub fn remove_window(&mut self, window: XWindow) {
-        if let Some(window_idx) = self.context.window_lookup.get(&window).copied() {
-            for ws in self.workspaces.iter_mut() {
-                ws.remove_window(window_idx, &mut self.context);
-            }
+        if self.client_idx(window).is_none()
+            && !self.global_windows.iter().any(|c| c.window == window)
+        {
+            // not one of ours, e.g. an override-redirect window
+            return;
+        }
+        // the frame and the client window lead to the same client. untracking it forgets both, so
+        // a second call for the other one ends up here without finding anything
+        if let Some(window_idx) = self.client_idx(window) {
             self.context.windows[window_idx].destroy(&self.context.connection);
-
-            self.context.windows.remove(window_idx);
-            let mut to_remove = vec![];
-            for (k, v) in self.context.window_lookup.iter() {
-                if *v == window_idx {
-                    to_remove.push(*k);
-                }
-            }
-            for k in to_remove {
-                self.context.window_lookup.remove(&k);
-            }
+            self.untrack_client(window_idx);
         };
 
-        for i in 0..self.global_windows.max_len() {
-            let Some(global_window) = self.global_windows.get(i) else {
-                continue;
-            };
-            if global_window.window == window {
-                let child = self
-                    .global_windows
-                    .remove(i)
-                    .expect("we should have a child");
-                self.free_reserved_space(child.reserved, child.direction);
-                _ = self
-                    .context
-                    .connection
-                    .send_and_check_request(&UnmapWindow {
-                        window: child.window,
-                    });
-                _ = self
-                    .context
-                    .connection
-                    .send_and_check_request(&DestroyWindow {
-                        window: child.window,
-                    });
-            }
+        let reserved = self
+            .global_windows
+            .iter_indexed()
+            .filter(|(_, global_window)| global_window.window == window)
+            .map(|(i, _)| i)
+            .collect::<Vec<_>>();
+        for i in reserved {
+            let child = self
+                .global_windows
+                .remove(i)
+                .expect("we should have a child");
+            self.size_updated();
+            _ = self
+                .context
+                .connection
+                .send_and_check_request(&UnmapWindow {
+                    window: child.window,
+                });
+            _ = self
+                .context
+                .connection
+                .send_and_check_request(&DestroyWindow {
+                    window: child.window,
+                });
         }
 
         trace_result!(self.context.connection.flush(); "failed to flush the connection after window remove");
     }
 
+    /// a client unmapped its window, so it doesn't want to be managed anymore (ICCCM 4.1.4)
+    pub fn unmap_window(&mut self, window: XWindow) {
+        let Some(window_idx) = self.client_idx(window) else {
+            return;
+        };
+        let client = &mut self.context.windows[window_idx];
+        // the frame only gets unmapped by us
+        if client.window != window {
+            return;
+        }
+        if client.ignore_unmaps > 0 {
+            client.ignore_unmaps -= 1;
+            return;
+        }
+
+        client.withdraw(
+            self.context.root_window,
+            &self.context.atoms,
+            &self.context.connection,
+        );
+        self.untrack_client(window_idx);
+        trace_result!(self.context.connection.flush(); "failed to flush the connection after window unmap");
+    }
+
+    /// forgets about a client whose window is already gone or has been given back to the root
+    fn untrack_client(&mut self, window_idx: usize) {
+        self.unswallow(window_idx);
+        for client in self.context.windows.iter_mut() {
+            if client.swallowed == Some(window_idx) {
+                client.swallowed = None;
+            }
+        }
+        for ws in self.workspaces.iter_mut() {
+            ws.remove_window(window_idx, &mut self.context);
+        }
+        let was_focused = self.context.focused_window == Some(window_idx);
+        if was_focused {
+            self.context.focused_window = None;
+        }
+        if self.scratchpad == Some(window_idx) {
+            self.scratchpad = None;
+        }
+        self.urgent.retain(|&idx| idx != window_idx);
+        self.mru.retain(|&idx| idx != window_idx);
+
+        self.context.windows.remove(window_idx);
+        self.context.window_lookup.retain(|_, v| *v != window_idx);
+        // otherwise keyboard input would go to the root until the pointer moves
+        if was_focused {
+            self.focus_last_used();
+        }
+        self.stale.client_list = true;
+        self.stale.stacking = true;
+    }
+
     fn handle_reserved_client(&mut self, window: XWindow, values: [u32; 12]) -> anyhow::Result<()> {
-        // _NET_WM_STRUT: https://specifications.freedesktop.org/wm-spec/latest/ar01s05.html#id-1.6.10
-        // _NET_WM_STRUT_PARTIAL: https://specifications.freedesktop.org/wm-spec/latest/ar01s05.html#id-1.6.11
-        let left = values[0];
-        let right = values[1];
-        let top = values[2];
-        let bottom = values[3];
-        let left_start_y = values[4];
-        let left_end_y = values[5];
-        let right_start_y = values[6];
-        let right_end_y = values[7];
-        let top_start_x = values[8];
-        let top_end_x = values[9];
-        let bottom_start_x = values[10];
-        let bottom_end_x = values[11];
-
-        let (position, direction, reserved) = if left > 0 {
-            self.reserve_space_left(left as u16);
-            (
-                Position {
-                    x: 0,
-                    y: left_start_y as u16,
-                    width: left as u16,
-                    height: (left_end_y - left_start_y) as u16,
-                },
-                ScreenSide::Left,
-                left as u16,
-            )
-        } else if bottom > 0 {
-            self.reserve_space_bottom(bottom as u16);
-            (
-                Position {
-                    x: bottom_start_x as u16,
-                    y: self.height - bottom as u16,
-                    width: (bottom_end_x - bottom_start_x) as u16,
-                    height: bottom as u16,
-                },
-                ScreenSide::Bottom,
-                bottom as u16,
-            )
-        } else if top > 0 {
-            self.reserve_space_top(top as u16);
-            (
-                Position {
-                    x: top_start_x as u16,
-                    y: 0,
-                    width: (top_end_x - top_start_x) as u16,
-                    height: top as u16,
-                },
-                ScreenSide::Top,
-                top as u16,
-            )
-        } else if right > 0 {
-            self.reserve_space_right(right as u16);
-            (
-                Position {
-                    x: self.width - right as u16,
-                    y: right_start_y as u16,
-                    width: right as u16,
-                    height: (right_end_y - right_start_y) as u16,
-                },
-                ScreenSide::Right,
-                right as u16,
-            )
-        } else {
+        let Some((position, direction, amount)) = strut_area(values, self.width, self.height)
+        else {
             anyhow::bail!(
                 "Invalid _NET_WM_STRUT/_NET_WM_STRUT_PARTIAL values: [left,right,top,bottom]=0"
             );
         };
 
-        if let Err(e) = self.add_reserved_client(ReservedClient {
-            window,
-            direction,
-            position,
-            reserved,
-        }) {
-            self.free_reserved_space(reserved, direction);
-
-            Err(e)
+        if let Some(client) = self.global_windows.iter_mut().find(|c| c.window == window) {
+            client.position = position;
+            client.direction = direction;
+            client.reserved = amount;
         } else {
-            Ok(())
+            self.add_reserved_client(ReservedClient {
+                window,
+                direction,
+                position,
+                reserved: amount,
+            })?;
         }
+        self.size_updated();
+        Ok(())
     }
 
-    pub fn add_window(&mut self, window: XWindow) -> anyhow::Result<()> {
-        // checking for strut and partial strut
+    /// reads _NET_WM_STRUT_PARTIAL, or _NET_WM_STRUT if the window doesn't have a partial strut
+    fn read_strut(&self, window: XWindow) -> anyhow::Result<Option<[u32; 12]>> {
+        let strut_partial_cookie = self.context.connection.send_request(&xcb::x::GetProperty {
+            delete: false,
+            window,
+            property: self.context.atoms.net_wm_strut_partial,
+            r#type: ATOM_CARDINAL,
+            long_offset: 0,
+            long_length: 12,
+        });
+        let strut_cookie = self.context.connection.send_request(&xcb::x::GetProperty {
+            delete: false,
+            window,
+            property: self.context.atoms.net_wm_strut,
+            r#type: ATOM_CARDINAL,
+            long_offset: 0,
+            long_length: 4,
+        });
+
+        if let Some(values) = self
+            .context
+            .connection
+            .wait_for_reply(strut_partial_cookie)?
+            .value::<u32>()
+            .get(0..12)
         {
-            let strut_partial_cookie = self.context.connection.send_request(&xcb::x::GetProperty {
-                delete: false,
-                window,
-                property: self.context.atoms.net_wm_strut_partial,
-                r#type: ATOM_CARDINAL,
-                long_offset: 0,
-                long_length: 12,
-            });
-            let strut_cookie = self.context.connection.send_request(&xcb::x::GetProperty {
-                delete: false,
-                window,
-                property: self.context.atoms.net_wm_strut,
-                r#type: ATOM_CARDINAL,
-                long_offset: 0,
-                long_length: 4,
-            });
+            return Ok(Some(
+                values
+                    .try_into()
+                    .context("strut_partial_cookie returned in invalid value")?,
+            ));
+        }
+        Ok(self
+            .context
+            .connection
+            .wait_for_reply(strut_cookie)?
+            .value::<u32>()
+            .get(0..4)
+            .map(|values| {
+                [
+                    values[0], values[1], values[2], values[3], 0, 0, 0, 0, 0, 0, 0, 0,
+                ]
+            }))
+    }
 
-            if let Some(values) = self
-                .context
-                .connection
-                .wait_for_reply(strut_partial_cookie)?
-                .value::<u32>()
-                .get(0..12)
-            {
-                self.handle_reserved_client(
-                    window,
-                    values
-                        .try_into()
-                        .context("strut_partial_cookie returned in invalid value")?,
-                )?;
-                let _ = self.update_atoms();
-                return Ok(());
+    /// re-reads the strut of a window after it changed. managed windows that gain a strut stop
+    /// being managed and become reserved clients
+    fn strut_changed(&mut self, window: XWindow) -> anyhow::Result<()> {
+        let values = self
+            .read_strut(window)?
+            .filter(|values| values[..4].iter().any(|&v| v > 0));
+
+        if let Some(client) = self.global_windows.iter_mut().find(|c| c.window == window) {
+            match values {
+                Some(values) => self.handle_reserved_client(window, values)?,
+                None => {
+                    client.reserved = 0;
+                    self.size_updated();
+                }
             }
-            if let Some(values) = self
-                .context
-                .connection
-                .wait_for_reply(strut_cookie)?
-                .value::<u32>()
-                .get(0..4)
-            {
-                self.handle_reserved_client(
-                    window,
-                    [
-                        values[0], values[1], values[2], values[3], 0, 0, 0, 0, 0, 0, 0, 0,
-                    ],
-                )?;
-                let _ = self.update_atoms();
-                return Ok(());
+            return Ok(());
+        }
+
+        let Some(values) = values else {
+            return Ok(());
+        };
+        let Some(idx) = self.client_idx(window) else {
+            return Ok(());
+        };
+        if self.context.windows[idx].window != window {
+            return Ok(());
+        }
+        self.context.windows[idx].withdraw(
+            self.context.root_window,
+            &self.context.atoms,
+            &self.context.connection,
+        );
+        self.untrack_client(idx);
+        self.handle_reserved_client(window, values)
+    }
+
+    /// manages a window that asked to be mapped, and focuses it if it should be
+    pub fn add_window(&mut self, window: XWindow) -> anyhow::Result<()> {
+        if let Some(idx) = self.manage_window(window, false)? {
+            let client = &self.context.windows[idx];
+            // windows that were sent to another workspace by a rule don't pull the focus there
+            if client.workspace == self.context.current_workspace || client.sticky {
+                self.focus_window(client.window);
             }
         }
+        Ok(())
+    }
+
+    /// frames the window and puts it on its workspace. returns the new client if it should be
+    /// focused: dialogs and transients always are, other windows with `FOCUS_NEW_WINDOWS`.
+    /// `adopted` windows were already there when we started
+    fn manage_window(&mut self, window: XWindow, adopted: bool) -> anyhow::Result<Option<usize>> {
+        // menus, tooltips and the like place themselves and must not be framed
+        let attributes = request_sync!(self.context.connection => GetWindowAttributes { window });
+        if attributes.override_redirect() {
+            return Ok(None);
+        }
+
+        // checking for strut and partial strut
+        if let Some(values) = self.read_strut(window)? {
+            self.handle_reserved_client(window, values)?;
+            return Ok(None);
+        }
 
         // if we have neither of those elements
-        let client = Client::new(
+        let window_types =
+            ewmh::get_window_type(window, &self.context.atoms, &self.context.connection);
+        let atoms = &self.context.atoms;
+        if window_types.contains(&atoms.net_wm_window_type_dock) {
+            // docks without a strut don't reserve any space, but are still not managed by us
+            let geometry = request_sync!(self.context.connection => GetGeometry {
+                drawable: Drawable::Window(window),
+            });
+            self.add_reserved_client(ReservedClient {
+                window,
+                position: Position::new(
+                    geometry.x().max(0) as u16,
+                    geometry.y().max(0) as u16,
+                    geometry.width(),
+                    geometry.height(),
+                ),
+                reserved: 0,
+                direction: ScreenSide::Top,
+            })?;
+            return Ok(None);
+        }
+        let is_dialog = window_types.contains(&atoms.net_wm_window_type_dialog);
+        let mut floating = window_types.iter().any(|&window_type| {
+            window_type == atoms.net_wm_window_type_dialog
+                || window_type == atoms.net_wm_window_type_utility
+                || window_type == atoms.net_wm_window_type_splash
+                || window_type == atoms.net_wm_window_type_toolbar
+        });
+
+        let mut client = Client::new(
             window,
             self.context.root_window,
             &self.context.connection,
             &self.context.atoms,
             self.context.current_workspace,
+            self.context.depth,
+            &self.context.config,
         )?;
+        floating |= client.transient_for.is_some();
+        let focus = config::FOCUS_NEW_WINDOWS || is_dialog || client.transient_for.is_some();
+        let mut workspace = self.context.current_workspace as usize;
+        // windows we adopt after a restart remember their workspace
+        if adopted {
+            if let Some(desktop) =
+                ewmh::get_wm_desktop(window, &self.context.atoms, &self.context.connection)
+            {
+                if (desktop as usize) < self.workspaces.len() {
+                    workspace = desktop as usize;
+                }
+            }
+        }
+        if let Some(rule) = config::WINDOW_RULES
+            .iter()
+            .find(|rule| rule.matches(&client))
+        {
+            floating |= rule.floating;
+            client.fullscreen = rule.fullscreen;
+            if let Some(id) = rule.workspace {
+                match self.workspaces.iter().position(|ws| ws.id() == id) {
+                    Some(idx) => workspace = idx,
+                    None => warn!(
+                        "window rule for {:?} uses a non-existent workspace {id}",
+                        rule.class
+                    ),
+                }
+            }
+        }
+        // the saved session knows where the user put the window before a restart
+        if let Some(entry) = self
+            .restoring
+            .as_mut()
+            .and_then(|session| session.take(client.pid, &client.class))
+        {
+            if let Some(idx) = self
+                .workspaces
+                .iter()
+                .position(|ws| ws.id() == entry.workspace)
+            {
+                workspace = idx;
+            }
+            floating = entry.floating;
+        }
+        client.workspace = workspace as u8;
+        if !self.focus_follows_mouse {
+            client.grab_click(&self.context.connection);
+        }
+
+        let is_scratchpad = self.scratchpad.is_none()
+            && (client.instance == config::SCRATCHPAD_CLASS
+                || client.class == config::SCRATCHPAD_CLASS);
 
         let frame = client.frame;
         let window = client.window;
         let idx = self.context.windows.push(client);
         self.context.window_lookup.insert(frame, idx);
         self.context.window_lookup.insert(window, idx);
-        self.workspaces[self.context.current_workspace as usize]
-            .spawn_window(idx, &mut self.context);
-        Ok(())
-    }
-
+        self.stale.client_list = true;
+        self.stale.wm_desktop = true;
+        if self.context.windows[idx].urgent {
+            self.urgent.push(idx);
+        }
+        if is_scratchpad {
+            self.scratchpad = Some(idx);
+            self.show_scratchpad(idx);
+            let client = &self.context.windows[idx];
+            ewmh::set_wm_state(
+                client.window,
+                &client.wm_state(&self.context.atoms),
+                &self.context.atoms,
+                &self.context.connection,
+            )?;
+            return Ok(None);
+        }
+        if !floating && self.swallow(idx) {
+            return Ok(focus.then_some(idx));
+        }
+        let workspace = &mut self.workspaces[workspace];
+        if floating {
+            workspace.spawn_floating_window(idx, &mut self.context);
+        } else {
+            workspace.spawn_window(idx, &mut self.context);
+        }
+        self.restack();
+        let client = &self.context.windows[idx];
+        ewmh::set_wm_state(
+            client.window,
+            &client.wm_state(&self.context.atoms),
+            &self.context.atoms,
+            &self.context.connection,
+        )?;
+        Ok(focus.then_some(idx))
+    }
+
+    /// hides the terminal the new client was started from and puts the client in its place.
+    /// returns false if there is no such terminal
+    fn swallow(&mut self, idx: usize) -> bool {
+        if !config::SWALLOW {
+            return false;
+        }
+        let Some(pid) = self.context.windows[idx].pid else {
+            return false;
+        };
+        // swallowed and minimized terminals aren't on any workspace, so they can't be swallowed
+        let terminals = self
+            .workspaces
+            .iter()
+            .flat_map(|workspace| workspace.windows())
+            .filter(|&terminal| {
+                let client = &self.context.windows[terminal];
+                terminal != idx
+                    && (config::SWALLOW_CLASSES.contains(&client.class.as_str())
+                        || config::SWALLOW_CLASSES.contains(&client.instance.as_str()))
+            })
+            .filter_map(|terminal| Some((self.context.windows[terminal].pid?, terminal)))
+            .collect::<Vec<_>>();
+        // the program was most likely started by a shell running in the terminal
+        let Some(terminal) = parent_pids(pid).find_map(|ancestor| {
+            terminals
+                .iter()
+                .find(|&&(pid, _)| pid == ancestor)
+                .map(|&(_, terminal)| terminal)
+        }) else {
+            return false;
+        };
+
+        let workspace = self.context.windows[terminal].workspace;
+        if !self.workspaces[workspace as usize].replace_window(terminal, idx, &mut self.context) {
+            return false;
+        }
+        self.context.windows[idx].workspace = workspace;
+        self.context.windows[idx].swallowed = Some(terminal);
+        self.context.windows[terminal].hide(&self.context.atoms, &self.context.connection);
+        if self.context.focused_window == Some(terminal) {
+            self.focus_window(self.context.windows[idx].window);
+        }
+        self.restack();
+        let client = &self.context.windows[idx];
+        trace_result!(ewmh::set_wm_state(
+            client.window,
+            &client.wm_state(&self.context.atoms),
+            &self.context.atoms,
+            &self.context.connection,
+        ); "failed to set _NET_WM_STATE");
+        true
+    }
+
+    /// brings back the terminal the client swallowed, in the place of the client
+    fn unswallow(&mut self, idx: usize) {
+        let Some(terminal) = self.context.windows[idx].swallowed.take() else {
+            return;
+        };
+        let workspace = self.context.windows[idx].workspace;
+        self.context.windows[terminal].workspace = workspace;
+        let workspace = &mut self.workspaces[workspace as usize];
+        if !workspace.replace_window(idx, terminal, &mut self.context) {
+            // the window was minimized or stashed away, the terminal goes back on its own
+            if self.context.windows[terminal].workspace == self.context.current_workspace {
+                let client = &mut self.context.windows[terminal];
+                client.show(&self.context.atoms, &self.context.connection);
+            }
+            let floating = self.context.windows[terminal].floating;
+            workspace.insert_window(terminal, floating, &mut self.context);
+            return;
+        }
+        if self.context.focused_window == Some(idx) {
+            self.focus_window(self.context.windows[terminal].window);
+        }
+    }
+
+    /// manages a window that was already mapped before we started
+    pub fn adopt_window(&mut self, window: XWindow) -> anyhow::Result<()> {
+        // adopted windows were there before us, none of them is new enough to take the focus
+        self.manage_window(window, true)?;
+        if let Some(idx) = self.client_idx(window) {
+            // reparenting the mapped window into the frame unmaps it
+            self.context.windows[idx].ignore_unmaps += 1;
+        }
+        Ok(())
+    }
+
+    /// places the windows that are adopted until [`Screen::finish_restore`] like they were in
+    /// `session`
+    pub fn start_restore(&mut self, session: Session) {
+        self.restoring = Some(session);
+    }
+
+    /// drops the entries of windows that no longer exist
+    pub fn finish_restore(&mut self) {
+        self.restoring = None;
+        self.save_session();
+    }
+
+    /// saves where the windows are if that changed since the last time
+    pub fn save_session(&mut self) {
+        let entries = self
+            .context
+            .windows
+            .iter_indexed()
+            .filter(|&(idx, _)| self.scratchpad != Some(idx))
+            .map(|(_, client)| SessionEntry {
+                pid: client.pid,
+                class: client.class.clone(),
+                workspace: self.workspaces[client.workspace as usize].id(),
+                floating: client.floating,
+            })
+            .collect();
+        let session = Session::new(entries);
+        if session == self.session {
+            return;
+        }
+        trace_result!(session.save(); "failed to save the session");
+        self.session = session;
+    }
+
     pub fn close_focused_window(&mut self) {
-        let Some(idx) = self.context.focused_window.take() else {
+        // stays focused until it is gone, in case it doesn't want to close
+        let Some(idx) = self.context.focused_window else {
             return;
         };
 
         if self.context.windows[idx].close(&self.context.atoms, &self.context.connection) {
-            self.workspaces
-                .iter_mut()
-                .for_each(|v| v.remove_window(idx, &mut self.context));
-
-            self.context.windows.remove(idx);
-            let mut to_remove = vec![];
-            for (k, v) in self.context.window_lookup.iter() {
-                if *v == idx {
-                    to_remove.push(*k);
-                }
-            }
-            for k in to_remove {
-                self.context.window_lookup.remove(&k);
-            }
+            self.untrack_client(idx);
         }
     }
 
+    /// gets rid of the focused window even if its program is hung
+    pub fn kill_focused_window(&mut self) {
+        let Some(idx) = self.context.focused_window else {
+            return;
+        };
+
+        self.context.windows[idx].kill(&self.context.connection);
+        self.untrack_client(idx);
+    }
+
     pub fn cycle_layout(&mut self) {
         self.workspaces[self.context.current_workspace as usize].cycle_layout(&mut self.context);
-        _ = self.update_atoms();
+    }
+
+    /// whether the point is close enough to the boundary between the master and the stack of the
+    /// current workspace to drag it
+    pub fn near_master_edge(&self, x: i16, y: i16) -> bool {
+        let workspace = &self.workspaces[self.context.current_workspace as usize];
+        let Some(edge) = workspace.master_edge() else {
+            return false;
+        };
+        let pos = workspace.get_screen_position();
+        (x as i32 - edge as i32).unsigned_abs() <= config::MASTER_EDGE_GRAB_DISTANCE as u32
+            && y as i32 >= pos.y as i32
+            && (y as i32) < pos.y as i32 + pos.height as i32
+    }
+
+    pub fn set_master_edge(&mut self, x: i16) {
+        self.workspaces[self.context.current_workspace as usize]
+            .set_master_edge(x, &mut self.context);
+    }
+
+    pub fn resize_master(&mut self, delta: i16) {
+        self.workspaces[self.context.current_workspace as usize]
+            .resize_master(delta, &mut self.context);
+    }
+
+    pub fn balance_layout(&mut self) {
+        self.workspaces[self.context.current_workspace as usize].balance(&mut self.context);
+    }
+
+    /// grows or shrinks the gaps of all workspaces. neither gap can take up more than a quarter
+    /// of the screen
+    pub fn adjust_gaps(&mut self, inner_delta: i16, outer_delta: i16) {
+        let max = self.width.min(self.height) as i32 / 4;
+        let config = &mut self.context.config;
+        config.inner_gap = (config.inner_gap as i32 + inner_delta as i32).clamp(0, max) as u16;
+        config.outer_gap = (config.outer_gap as i32 + outer_delta as i32).clamp(0, max) as u16;
+        for workspace in self.workspaces.iter_mut() {
+            workspace.retile_preserving_focus(&mut self.context);
+        }
+    }
+
+    /// changes the border of all windows. the border can't take up more than a quarter of the
+    /// screen, so that there is always space left for the content
+    pub fn set_border_size(&mut self, size: u16) {
+        let size = size.min(self.width.min(self.height) / 4);
+        self.context.config.border_size = size;
+        for client in self.context.windows.iter_mut() {
+            client.set_border_size(size, &self.context.connection);
+        }
+        // tiled windows have to make room for the new border
+        for workspace in self.workspaces.iter_mut() {
+            workspace.retile_preserving_focus(&mut self.context);
+        }
+    }
+
+    /// turns smart gaps on or off for all workspaces
+    pub fn toggle_smart_gaps(&mut self) {
+        self.context.config.smart_gaps = !self.context.config.smart_gaps;
+        for workspace in self.workspaces.iter_mut() {
+            workspace.retile_preserving_focus(&mut self.context);
+        }
+    }
+
+    /// turns the gaps of the current workspace off, or back on
+    pub fn toggle_gaps(&mut self) {
+        self.workspaces[self.context.current_workspace as usize].toggle_gaps(&mut self.context);
+    }
+
+    /// stops keeping the space of panels and the bar free of windows, or starts again
+    pub fn toggle_struts(&mut self) {
+        self.struts_disabled = !self.struts_disabled;
+        self.size_updated();
+    }
+
+    /// makes the focused window cover the current workspace, or puts it back
+    pub fn toggle_expanded(&mut self) {
+        let Some(idx) = self.context.focused_window else {
+            return;
+        };
+        self.workspaces[self.context.current_workspace as usize]
+            .toggle_expanded(idx, &mut self.context);
+    }
+
+    pub fn toggle_floating(&mut self) {
+        let Some(idx) = self.context.focused_window else {
+            return;
+        };
+        self.workspaces[self.context.current_workspace as usize]
+            .toggle_floating(idx, &mut self.context);
+    }
+
+    pub fn float_all(&mut self) {
+        self.workspaces[self.context.current_workspace as usize].float_all(&mut self.context);
+    }
+
+    pub fn tile_all(&mut self) {
+        self.workspaces[self.context.current_workspace as usize].tile_all(&mut self.context);
+    }
+
+    /// moves the window to the front of the most-recently-used list, unless the windows are being
+    /// cycled through
+    fn touch_mru(&mut self, idx: usize) {
+        if self.mru_cycle.is_some() {
+            return;
+        }
+        self.mru.retain(|&v| v != idx);
+        self.mru.insert(0, idx);
+    }
+
+    pub fn cycling_mru(&self) -> bool {
+        self.mru_cycle.is_some()
+    }
+
+    /// previews the next window of the current workspace in most-recently-used order. the order
+    /// stays the same until `end_mru_cycle` is called
+    pub fn cycle_mru(&mut self) {
+        let workspace = &self.workspaces[self.context.current_workspace as usize];
+        let candidates = self
+            .mru
+            .iter()
+            .copied()
+            .filter(|&idx| workspace.windows().any(|v| v == idx))
+            .collect::<Vec<_>>();
+        if candidates.is_empty() {
+            return;
+        }
+        let position = self.mru_cycle.map_or(1, |position| position + 1) % candidates.len();
+        self.mru_cycle = Some(position);
+        self.focus_window(self.context.windows[candidates[position]].window);
+    }
+
+    /// commits the window that was selected by cycling as the most recently used one
+    pub fn end_mru_cycle(&mut self) {
+        if self.mru_cycle.take().is_none() {
+            return;
+        }
+        if let Some(idx) = self.context.focused_window {
+            self.touch_mru(idx);
+        }
+    }
+
+    pub fn focus_direction(&mut self, direction: Direction) {
+        if let Some(idx) = self.workspaces[self.context.current_workspace as usize]
+            .focus_direction(direction, &mut self.context)
+        {
+            self.context.focused_window = Some(idx);
+            self.touch_mru(idx);
+            self.restack();
+        }
+    }
+
+    /// swaps the focused window with the master window
+    pub fn swap_master(&mut self) {
+        self.workspaces[self.context.current_workspace as usize].swap_master(&mut self.context);
+    }
+
+    pub fn move_direction(&mut self, direction: Direction) {
+        self.workspaces[self.context.current_workspace as usize]
+            .move_direction(direction, &mut self.context);
+    }
+
+    pub fn rotate_stack(&mut self, direction: Direction) {
+        self.workspaces[self.context.current_workspace as usize]
+            .rotate_stack(direction, &mut self.context);
+    }
+
+    /// moves the focused window `delta` workspaces further, wrapping around at either end. with
+    /// `follow`, the destination is switched to and the window stays focused
+    pub fn move_focused_relative(
+        &mut self,
+        delta: i32,
+        follow: bool,
+    ) -> Result<(), xcb::ProtocolError> {
+        let Some(idx) = self.context.focused_window else {
+            return Ok(());
+        };
+        let source = self.context.current_workspace as usize;
+        let target = (source as i32 + delta).rem_euclid(self.workspaces.len() as i32) as usize;
+        if target == source {
+            return Ok(());
+        }
+        let Some(floating) = self.workspaces[source].take_window(idx, &mut self.context) else {
+            return Ok(());
+        };
+        self.context.focused_window = None;
+        // the destination isn't shown, switching to it shows the window again
+        self.context.windows[idx].hide(&self.context.atoms, &self.context.connection);
+        self.context.windows[idx].workspace = target as u8;
+        self.workspaces[target].insert_window(idx, floating, &mut self.context);
+
+        if follow {
+            self.switch_workspace(target as u8)?;
+            self.focus_window(self.context.windows[idx].window);
+        } else {
+            self.focus_last_used();
+            self.stale.wm_desktop = true;
+        }
+        Ok(())
+    }
+
+    /// focuses the most recently used window of the current workspace, or the root if it has no
+    /// windows
+    fn focus_last_used(&mut self) {
+        let workspace = &self.workspaces[self.context.current_workspace as usize];
+        let window = self
+            .mru
+            .iter()
+            .copied()
+            .find(|&idx| workspace.windows().any(|window| window == idx))
+            .or_else(|| workspace.windows().next())
+            .map_or(self.context.root_window, |idx| {
+                self.context.windows[idx].window
+            });
+        self.focus_window(window);
+        self.restack();
+    }
+
+    /// minimizes the focused window
+    pub fn minimize(&mut self) {
+        let Some(idx) = self.context.focused_window else {
+            return;
+        };
+        if !self.workspaces[self.context.current_workspace as usize]
+            .minimize_window(idx, &mut self.context)
+        {
+            return;
+        }
+        self.context.focused_window = None;
+        self.focus_last_used();
+    }
+
+    /// restores the most recently minimized window of the current workspace and focuses it
+    pub fn restore_last(&mut self) {
+        let Some(idx) = self.workspaces[self.context.current_workspace as usize]
+            .restore_last(&mut self.context)
+        else {
+            return;
+        };
+        self.focus_window(self.context.windows[idx].window);
+    }
+
+    /// shows or hides the scratchpad. returns false if there is no scratchpad window to toggle
+    pub fn toggle_scratchpad(&mut self) -> bool {
+        let Some(idx) = self.scratchpad else {
+            return false;
+        };
+        if self.context.windows[idx].visible {
+            self.context.windows[idx].hide(&self.context.atoms, &self.context.connection);
+            if self.context.focused_window == Some(idx) {
+                self.focus_window(self.context.root_window);
+            }
+            self.stale.stacking = true;
+        } else {
+            self.show_scratchpad(idx);
+        }
+        true
+    }
+
+    /// shows the scratchpad floating in the middle of the screen, on top of everything else
+    fn show_scratchpad(&mut self, idx: usize) {
+        let area = self.reserved.area(self.width, self.height);
+        let width = area.width / 3 * 2;
+        let height = area.height / 3 * 2;
+        let client = &mut self.context.windows[idx];
+        client.workspace = self.context.current_workspace;
+        client.update(
+            width,
+            height,
+            area.x + (area.width - width) / 2,
+            area.y + (area.height - height) / 2,
+            &self.context.connection,
+        );
+        client.show(&self.context.atoms, &self.context.connection);
+        self.focus_window(self.context.windows[idx].window);
+        self.restack();
+    }
+
+    /// shows the scratchpad if it is hidden. returns false if there is no scratchpad window
+    pub fn show_existing_scratchpad(&mut self) -> bool {
+        let Some(idx) = self.scratchpad else {
+            return false;
+        };
+        if !self.context.windows[idx].visible {
+            self.show_scratchpad(idx);
+        }
+        true
+    }
+
+    /// stashes the focused window in the scratchpad, handing the window that was there before
+    /// back to the current workspace. if the focused window is the scratchpad, it goes back to
+    /// the current workspace instead
+    pub fn move_to_scratchpad(&mut self) -> Result<(), xcb::ProtocolError> {
+        let Some(idx) = self.context.focused_window else {
+            return Ok(());
+        };
+        if let Some(old) = self.scratchpad.take() {
+            self.unstash(old);
+            if old == idx {
+                self.focus_window(self.context.windows[idx].window);
+                return Ok(());
+            }
+        }
+
+        if self.context.windows[idx].fullscreen {
+            self.set_fullscreen(idx, false)?;
+        }
+        let current = self.context.current_workspace as usize;
+        let Some(floating) = self.workspaces[current].take_window(idx, &mut self.context) else {
+            return Ok(());
+        };
+        // remembers where the window goes once it leaves the scratchpad again
+        self.context.windows[idx].floating = floating;
+        self.context.windows[idx].hide(&self.context.atoms, &self.context.connection);
+        self.scratchpad = Some(idx);
+        self.context.focused_window = None;
+        self.focus_last_used();
+        Ok(())
+    }
+
+    /// puts a window that was the scratchpad on the current workspace, tiled or floating like it
+    /// was before it was stashed
+    fn unstash(&mut self, idx: usize) {
+        let current = self.context.current_workspace;
+        let client = &mut self.context.windows[idx];
+        client.workspace = current;
+        let floating = client.floating;
+        if !client.visible {
+            client.show(&self.context.atoms, &self.context.connection);
+        }
+        self.workspaces[current as usize].insert_window(idx, floating, &mut self.context);
+        self.restack();
+    }
+
+    fn visible_scratchpad(&self) -> Option<usize> {
+        self.scratchpad
+            .filter(|&idx| self.context.windows[idx].visible)
+    }
+
+    /// shows the focused window on every workspace, or only on the current one again
+    pub fn toggle_sticky(&mut self) {
+        let Some(idx) = self.context.focused_window else {
+            return;
+        };
+        let client = &mut self.context.windows[idx];
+        client.sticky = !client.sticky;
+        trace_result!(ewmh::set_wm_state(
+            client.window,
+            &client.wm_state(&self.context.atoms),
+            &self.context.atoms,
+            &self.context.connection,
+        ); "failed to update _NET_WM_STATE");
+        self.stale.wm_desktop = true;
+    }
+
+    pub fn toggle_above(&mut self) {
+        let Some(idx) = self.context.focused_window else {
+            return;
+        };
+        let above = !self.context.windows[idx].above;
+        trace_result!(self.set_above(idx, above); "failed to update _NET_WM_STATE");
+    }
+
+    fn set_above(&mut self, idx: usize, above: bool) -> Result<(), xcb::ProtocolError> {
+        let client = &mut self.context.windows[idx];
+        client.above = above;
+        ewmh::set_wm_state(
+            client.window,
+            &client.wm_state(&self.context.atoms),
+            &self.context.atoms,
+            &self.context.connection,
+        )?;
+        // the scratchpad isn't part of a workspace
+        let workspace = &mut self.workspaces[client.workspace as usize];
+        if workspace.windows().any(|window| window == idx) {
+            workspace.raise_window(idx, &self.context);
+        }
+        self.restack();
+        Ok(())
+    }
+
+    /// makes the tiled client on the current workspace that owns `window` float where it is
+    pub fn float_client(&mut self, window: XWindow) {
+        let Some(idx) = self.client_idx(window) else {
+            return;
+        };
+        self.workspaces[self.context.current_workspace as usize]
+            .float_in_place(idx, &mut self.context);
+        self.restack();
+    }
+
+    /// returns the index of the floating client on the current workspace that owns `window`
+    pub fn floating_client(&self, window: XWindow) -> Option<usize> {
+        let idx = self.client_idx(window)?;
+        self.workspaces[self.context.current_workspace as usize]
+            .is_floating(idx)
+            .then_some(idx)
+    }
+
+    pub fn client_position(&self, idx: usize) -> Position {
+        self.context.windows[idx].position()
+    }
+
+    pub fn move_client(&mut self, idx: usize, x: u16, y: u16) {
+        let client = &mut self.context.windows[idx];
+        client.update(client.width, client.height, x, y, &self.context.connection);
+        self.center_transients(idx);
+    }
+
+    pub fn resize_client(&mut self, idx: usize, width: u16, height: u16) {
+        let client = &mut self.context.windows[idx];
+        client.update(width, height, client.x, client.y, &self.context.connection);
+        self.center_transients(idx);
+    }
+
+    fn center_transients(&mut self, idx: usize) {
+        let workspace = self.context.windows[idx].workspace as usize;
+        self.workspaces[workspace].center_transients(idx, &mut self.context);
+    }
+
+    /// handles requests from pagers and other clients. messages we don't know are ignored
+    pub fn client_message(
+        &mut self,
+        window: XWindow,
+        atom: Atom,
+        data: [u32; 5],
+    ) -> Result<(), xcb::ProtocolError> {
+        if atom == self.context.atoms.net_current_desktop {
+            if (data[0] as usize) < self.workspaces.len() {
+                self.switch_workspace(data[0] as u8)?;
+            }
+        } else if atom == self.context.atoms.net_active_window {
+            if let Some(idx) = self.client_idx(window) {
+                self.activate_window(idx)?;
+            }
+        } else if atom == self.context.atoms.net_wm_state {
+            let Some(idx) = self.client_idx(window) else {
+                return Ok(());
+            };
+            // data[0] is the action, data[1] and data[2] the states it applies to
+            let fullscreen = self.context.atoms.net_wm_state_fullscreen.resource_id();
+            if data[1] == fullscreen || data[2] == fullscreen {
+                let enable = match data[0] {
+                    NET_WM_STATE_REMOVE => false,
+                    NET_WM_STATE_ADD => true,
+                    NET_WM_STATE_TOGGLE => !self.context.windows[idx].fullscreen,
+                    _ => return Ok(()),
+                };
+                self.set_fullscreen(idx, enable)?;
+            }
+            let demands_attention = self
+                .context
+                .atoms
+                .net_wm_state_demands_attention
+                .resource_id();
+            if data[1] == demands_attention || data[2] == demands_attention {
+                let enable = match data[0] {
+                    NET_WM_STATE_REMOVE => false,
+                    NET_WM_STATE_ADD => true,
+                    NET_WM_STATE_TOGGLE => !self.context.windows[idx].urgent,
+                    _ => return Ok(()),
+                };
+                self.set_urgent(idx, enable)?;
+            }
+            let above = self.context.atoms.net_wm_state_above.resource_id();
+            if data[1] == above || data[2] == above {
+                let enable = match data[0] {
+                    NET_WM_STATE_REMOVE => false,
+                    NET_WM_STATE_ADD => true,
+                    NET_WM_STATE_TOGGLE => !self.context.windows[idx].above,
+                    _ => return Ok(()),
+                };
+                self.set_above(idx, enable)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// marks the window as wanting attention. the focused window never is urgent
+    fn set_urgent(&mut self, idx: usize, urgent: bool) -> Result<(), xcb::ProtocolError> {
+        let client = &mut self.context.windows[idx];
+        let urgent = urgent && !client.focused;
+        if client.urgent == urgent {
+            return Ok(());
+        }
+        client.set_urgent(urgent, &self.context.connection);
+        self.urgent.retain(|&v| v != idx);
+        if urgent {
+            self.urgent.push(idx);
+        }
+        ewmh::set_wm_state(
+            client.window,
+            &client.wm_state(&self.context.atoms),
+            &self.context.atoms,
+            &self.context.connection,
+        )
+    }
+
+    /// focuses the window that most recently wanted attention, switching to its workspace
+    pub fn focus_urgent(&mut self) -> Result<(), xcb::ProtocolError> {
+        // focusing a window clears its urgency without going through `set_urgent`
+        self.urgent.retain(|&idx| self.context.windows[idx].urgent);
+        let Some(idx) = self.urgent.last().copied() else {
+            return Ok(());
+        };
+        let Some(workspace) = self
+            .workspaces
+            .iter()
+            .position(|ws| ws.windows().any(|v| v == idx))
+        else {
+            return Ok(());
+        };
+        if workspace != self.context.current_workspace as usize {
+            self.switch_workspace(workspace as u8)?;
+        }
+        self.focus_window(self.context.windows[idx].window);
+        ewmh::set_wm_state(
+            self.context.windows[idx].window,
+            &self.context.windows[idx].wm_state(&self.context.atoms),
+            &self.context.atoms,
+            &self.context.connection,
+        )
+    }
+
+    pub fn set_fullscreen(&mut self, idx: usize, enable: bool) -> Result<(), xcb::ProtocolError> {
+        let Some(workspace) = self
+            .workspaces
+            .iter_mut()
+            .find(|ws| ws.windows().any(|v| v == idx))
+        else {
+            return Ok(());
+        };
+        workspace.set_fullscreen(idx, enable, &mut self.context);
+
+        // making a window fullscreen might have taken fullscreen away from another one
+        for window_idx in workspace.windows() {
+            let client = &self.context.windows[window_idx];
+            ewmh::set_wm_state(
+                client.window,
+                &client.wm_state(&self.context.atoms),
+                &self.context.atoms,
+                &self.context.connection,
+            )?;
+        }
+        Ok(())
+    }
+
+    /// switches to the workspace of the window and focuses it. a minimized window is restored
+    pub fn activate_window(&mut self, idx: usize) -> Result<(), xcb::ProtocolError> {
+        let Some(workspace) = self
+            .workspaces
+            .iter()
+            .position(|ws| ws.windows().chain(ws.minimized_windows()).any(|v| v == idx))
+        else {
+            return Ok(());
+        };
+        if workspace != self.context.current_workspace as usize {
+            self.switch_workspace(workspace as u8)?;
+        }
+        self.workspaces[workspace].restore_window(idx, &mut self.context);
+        self.focus_window(self.context.windows[idx].window);
+        Ok(())
+    }
+
+    /// redraws the title bar of the frame
+    pub fn expose(&mut self, window: XWindow) {
+        if self.bar.as_ref().is_some_and(|bar| bar.window() == window) {
+            self.draw_bar();
+            return;
+        }
+        let Some(idx) = self.client_idx(window) else {
+            return;
+        };
+        let client = &mut self.context.windows[idx];
+        if client.frame == window {
+            client.draw_title();
+        }
+    }
+
+    pub fn property_notify(&mut self, window: XWindow, atom: Atom) {
+        if atom == self.context.atoms.net_wm_strut_partial
+            || atom == self.context.atoms.net_wm_strut
+        {
+            trace_result!(self.strut_changed(window); "failed to update the strut of a window");
+            return;
+        }
+
+        let Some(idx) = self.client_idx(window) else {
+            return;
+        };
+
+        if atom == self.context.atoms.net_wm_name || atom == ATOM_WM_NAME {
+            self.context.windows[idx].update_name(&self.context.atoms, &self.context.connection);
+        } else if atom == ATOM_WM_HINTS {
+            let hints = WmHints::get(self.context.windows[idx].window, &self.context.connection);
+            self.context.windows[idx].accepts_input = hints.input;
+            trace_result!(self.set_urgent(idx, hints.urgent); "failed to update the urgency of a window");
+        }
+    }
+
+    /// windows we don't manage get exactly what they asked for, including their stacking, so
+    /// that menus and tooltips end up where they want. floating windows get the geometry they
+    /// asked for and tiled windows only get told where they are
+    pub fn configure_request(&mut self, request: ConfigureRequest) {
+        let Some(idx) = self.client_idx(request.window) else {
+            // we don't manage the window, so it can have whatever geometry it wants
+            let mut value_list = Vec::with_capacity(7);
+            value_list.extend(request.x.map(|x| ConfigWindow::X(x as i32)));
+            value_list.extend(request.y.map(|y| ConfigWindow::Y(y as i32)));
+            value_list.extend(request.width.map(|w| ConfigWindow::Width(w as u32)));
+            value_list.extend(request.height.map(|h| ConfigWindow::Height(h as u32)));
+            value_list.extend(
+                request
+                    .border_width
+                    .map(|b| ConfigWindow::BorderWidth(b as u32)),
+            );
+            value_list.extend(request.sibling.map(ConfigWindow::Sibling));
+            value_list.extend(request.stack_mode.map(ConfigWindow::StackMode));
+
+            trace_result!(self.context.connection.send_and_check_request(&ConfigureWindow {
+                window: request.window,
+                value_list: &value_list,
+            }); "failed to configure an unmanaged window");
+            return;
+        };
+
+        let is_floating = self.workspaces.iter().any(|ws| ws.is_floating(idx));
+        let client = &mut self.context.windows[idx];
+        if is_floating {
+            let border_double = self.context.config.border_size * 2;
+            client.update(
+                request
+                    .width
+                    .map_or(client.width, |w| w.saturating_add(border_double)),
+                request.height.map_or(client.height, |h| {
+                    h.saturating_add(border_double + WINDOW_BAR_HEIGHT)
+                }),
+                request.x.map_or(client.x, |x| x.max(0) as u16),
+                request.y.map_or(client.y, |y| y.max(0) as u16),
+                &self.context.connection,
+            );
+        } else {
+            // tiled windows keep their geometry, but the client still has to be told about it (ICCCM 4.1.5)
+            client.send_configure_notify(&self.context.connection);
+        }
     }
 
     pub fn set_layout(&mut self, new_layout: Layout) {
         self.workspaces[self.context.current_workspace as usize]
             .set_layout(new_layout, &mut self.context);
-        _ = self.update_atoms();
     }
 
+    /// gives all clients back to the root where they are on the screen and maps them, so that
+    /// they outlive the window manager or can be adopted by the next instance. only the frames
+    /// are destroyed. reserved windows aren't ours, so they are left alone
+    pub fn release_clients(&mut self) {
+        let windows = self
+            .context
+            .windows
+            .iter()
+            .map(|client| client.window)
+            .collect::<Vec<_>>();
+        for (_, mut client) in self.context.windows.drain() {
+            client.release(self.context.root_window, &self.context.connection);
+        }
+        trace_result!(ewmh::set_client_list(
+            &windows,
+            self.context.root_window,
+            &self.context.atoms,
+            &self.context.connection,
+        ); "failed to set _NET_CLIENT_LIST");
+
+        self.forget_clients();
+    }
+
+    /// destroys the frames along with the clients in them, for when the window manager goes down
+    /// without being asked to
     pub fn kill_children(&mut self) {
-        let mut cookies = vec![self
+        let conn = &self.context.connection;
+        // destroying a frame destroys the client window inside of it as well
+        let mut cookies = self
             .context
-            .connection
-            .send_request_checked(&SetInputFocus {
-                focus: self.context.root_window,
-                revert_to: xcb::x::InputFocus::Parent,
-                time: CURRENT_TIME,
-            })];
+            .windows
+            .iter()
+            .map(|client| client.frame)
+            .chain(self.global_windows.iter().map(|client| client.window))
+            .map(|window| conn.send_request_checked(&DestroyWindow { window }))
+            .collect::<Vec<_>>();
+        // the focus would otherwise revert to the root once the focused window is gone anyway
+        cookies.push(conn.send_request_checked(&SetInputFocus {
+            focus: self.context.root_window,
+            revert_to: xcb::x::InputFocus::PointerRoot,
+            time: CURRENT_TIME,
+        }));
 
-        for client in self.context.windows.iter() {
-            cookies.push(
-                self.context
-                    .connection
-                    .send_request_checked(&DestroyWindow {
-                        window: client.window,
-                    }),
-            );
-            cookies.push(
-                self.context
-                    .connection
-                    .send_request_checked(&DestroyWindow {
-                        window: client.frame,
-                    }),
-            );
+        for cookie in cookies {
+            match conn.check_request(cookie) {
+                // clients are free to destroy their windows while we shut down
+                Ok(()) | Err(xcb::ProtocolError::X(xcb::x::Error::Window(_), _)) => {}
+                Err(e) => error!("failed to destroy a window: {e:?}"),
+            }
         }
 
-        for window in self.global_windows.iter() {
-            cookies.push(
-                self.context
-                    .connection
-                    .send_request_checked(&DestroyWindow {
-                        window: window.window,
-                    }),
-            );
-        }
+        self.forget_clients();
+    }
 
+    /// drops every window we know about, after they were destroyed or handed back to the root
+    fn forget_clients(&mut self) {
         self.global_windows.clear();
-        self.reserved_space_bottom = 0;
-        self.reserved_space_left = 0;
-        self.reserved_space_right = 0;
-        self.reserved_space_top = 0;
+        self.reserved = ReservedSpace::default();
         self.context.windows.clear();
         self.context.focused_window = None;
         self.context.window_lookup.clear();
+        self.scratchpad = None;
+        self.urgent.clear();
+        self.mru.clear();
+        self.mru_cycle = None;
         self.workspaces
             .iter_mut()
             .for_each(Workspace::clear_windows);
+    }
+
+    /// the title of the focused client, as shown in the bar
+    pub fn focused_client_name(&self) -> Option<&str> {
+        self.context
+            .focused_window
+            .map(|idx| self.context.windows[idx].name.as_str())
+    }
 
-        for cookie in cookies.into_iter() {
-            _ = self.context.connection.check_request(cookie);
+    /// describes the workspaces, the clients and the reserved space for the `get-state` command.
+    /// the reply is a single line
+    pub fn state_json(&self) -> String {
+        let window_id = |idx: usize| self.context.windows[idx].window.resource_id();
+        ipc::State {
+            version: ipc::STATE_VERSION,
+            current_workspace: self.workspaces[self.context.current_workspace as usize].id(),
+            focused: self.context.focused_window.map(window_id),
+            focused_name: self.focused_client_name(),
+            workspaces: self
+                .workspaces
+                .iter()
+                .map(|ws| ipc::WorkspaceState {
+                    id: ws.id(),
+                    name: ws.name(),
+                    layout: ws.layout().to_string(),
+                    focused: ws.focused_window().map(window_id),
+                })
+                .collect(),
+            clients: self
+                .context
+                .windows
+                .iter()
+                .map(|client| ipc::ClientState {
+                    window: client.window.resource_id(),
+                    class: &client.class,
+                    name: &client.name,
+                    workspace: self.workspaces[client.workspace as usize].id(),
+                    floating: client.floating,
+                    fullscreen: client.fullscreen,
+                    x: client.x,
+                    y: client.y,
+                    width: client.width,
+                    height: client.height,
+                })
+                .collect(),
+            reserved: self.reserved,
         }
+        .to_json()
     }
 
-    // pub fn draw_bar(&mut self) {
-    //     _ = self.draw.draw_rect(Position::new(0, 0, self.width, 25), config::BORDER_COLOR_ACTIVE, config::BORDER_COLOR_ACTIVE);
-    //     _ = self.draw.draw_string(10, 15, "Xephyr on :1.0", 0xffffffff, config::BORDER_COLOR_ACTIVE);
-    //     _ = self.draw.finalise();
-    // }
-}
+    /// whether the window is the frame of a client
+    pub fn is_frame(&self, window: XWindow) -> bool {
+        self.client_for_window(window)
+            .is_some_and(|client| client.frame == window)
+    }
 
-// reserve_space_DIR/free_space_DIR
-impl Screen {
-    // reserve
-    pub fn reserve_space_top(&mut self, amount: u16) {
-        self.reserved_space_top += amount;
-        self.size_updated();
+    pub fn is_bar(&self, window: XWindow) -> bool {
+        self.bar.as_ref().is_some_and(|bar| bar.window() == window)
     }
-    pub fn reserve_space_bottom(&mut self, amount: u16) {
-        self.reserved_space_bottom += amount;
-        self.size_updated();
+
+    /// switches to the workspace whose label was clicked. scrolling cycles through the layouts
+    pub fn click_bar(&mut self, button: u8, x: i16) -> Result<(), xcb::ProtocolError> {
+        match button {
+            1 => {
+                let workspace = self
+                    .bar
+                    .as_ref()
+                    .and_then(|bar| bar.workspace_at(x.max(0) as u16));
+                if let Some(workspace) = workspace {
+                    self.switch_workspace(workspace as u8)?;
+                }
+            }
+            4 | 5 => self.cycle_layout(),
+            _ => {}
+        }
+        Ok(())
     }
-    pub fn reserve_space_left(&mut self, amount: u16) {
-        self.reserved_space_left += amount;
-        self.size_updated();
+
+    pub fn update_clock(&mut self) {
+        if let Some(bar) = &mut self.bar {
+            trace_result!(bar.update_clock(); "failed to draw the clock");
+        }
     }
-    pub fn reserve_space_right(&mut self, amount: u16) {
-        self.reserved_space_right += amount;
-        self.size_updated();
+
+    pub fn draw_bar(&mut self) {
+        let Some(bar) = &mut self.bar else {
+            return;
+        };
+        let workspaces = self
+            .workspaces
+            .iter()
+            .map(|ws| {
+                let urgent = ws.windows().any(|idx| self.context.windows[idx].urgent);
+                (ws.id(), ws.window_amount() > 0, urgent)
+            })
+            .collect::<Vec<_>>();
+        let current_workspace = self.context.current_workspace as usize;
+
+        trace_result!(bar.draw(&BarContent {
+            workspaces: &workspaces,
+            current_workspace,
+            layout: self.workspaces[current_workspace].layout(),
+            window_count: self.workspaces[current_workspace].window_amount(),
+            title: self
+                .context
+                .focused_window
+                .map_or("", |idx| &self.context.windows[idx].name),
+        }); "failed to draw the status bar");
     }
+}
 
-    // free
-    pub fn free_space_top(&mut self, amount: u16) {
-        self.reserved_space_top -= amount;
-        self.size_updated();
+/// the space at the edges of the screen that is kept free of tiled windows
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ReservedSpace {
+    pub top: u16,
+    pub bottom: u16,
+    pub left: u16,
+    pub right: u16,
+}
+
+impl ReservedSpace {
+    fn side_mut(&mut self, side: ScreenSide) -> &mut u16 {
+        match side {
+            ScreenSide::Top => &mut self.top,
+            ScreenSide::Bottom => &mut self.bottom,
+            ScreenSide::Left => &mut self.left,
+            ScreenSide::Right => &mut self.right,
+        }
     }
-    pub fn free_space_bottom(&mut self, amount: u16) {
-        self.reserved_space_bottom -= amount;
-        self.size_updated();
+
+    /// reserves `amount` at an edge. struts on the same edge overlap, so the edge only reserves
+    /// as much as the largest of them
+    pub fn reserve(&mut self, side: ScreenSide, amount: u16) {
+        let space = self.side_mut(side);
+        *space = (*space).max(amount);
     }
-    pub fn free_space_left(&mut self, amount: u16) {
-        self.reserved_space_left -= amount;
-        self.size_updated();
+
+    /// unreserves both sides of an axis if they would cover a screen of the given size entirely.
+    /// returns the sides that were unreserved
+    pub fn fit(&mut self, width: u16, height: u16) -> Vec<ScreenSide> {
+        let mut unreserved = vec![];
+        if self.top as u32 + self.bottom as u32 >= height as u32 {
+            self.top = 0;
+            self.bottom = 0;
+            unreserved.extend([ScreenSide::Top, ScreenSide::Bottom]);
+        }
+        if self.left as u32 + self.right as u32 >= width as u32 {
+            self.left = 0;
+            self.right = 0;
+            unreserved.extend([ScreenSide::Left, ScreenSide::Right]);
+        }
+        unreserved
     }
-    pub fn free_space_right(&mut self, amount: u16) {
-        self.reserved_space_right -= amount;
-        self.size_updated();
+
+    /// the area of the screen that isn't reserved. the space has to fit the screen
+    pub fn area(&self, width: u16, height: u16) -> Position {
+        Position::new(
+            self.left,
+            self.top,
+            width - self.left - self.right,
+            height - self.top - self.bottom,
+        )
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ScreenSide {
     Top,
     Bottom,
@@ -623,7 +2060,29 @@ pub struct ReservedClient {
     direction: ScreenSide,
 }
 
-#[derive(Debug, PartialEq, Eq, Clone)]
+/// the part a window plays in the layout of its workspace, shown in its title bar
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    #[default]
+    Tiled,
+    Master,
+    Floating,
+    Fullscreen,
+}
+
+impl Role {
+    /// the glyph drawn in the corner of the title bar, if any
+    fn glyph(self) -> Option<&'static str> {
+        match self {
+            Self::Tiled => None,
+            Self::Master => Some("M"),
+            Self::Floating => Some("~"),
+            Self::Fullscreen => Some("F"),
+        }
+    }
+}
+
+#[derive(Debug)]
 pub struct Client {
     pub window: XWindow,
     pub frame: XWindow,
@@ -635,37 +2094,96 @@ pub struct Client {
     pub x: u16,
     pub y: u16,
     pub workspace: u8,
+    pub floating: bool,
+    pub fullscreen: bool,
+    /// shown on every workspace
+    pub sticky: bool,
+    /// stacked above the other windows of its workspace, except for a fullscreen one
+    pub above: bool,
+    /// drawn without a border, because it's the only window with smart gaps
+    pub borderless: bool,
+    /// where the window was before it was expanded over its workspace
+    pub expanded_from: Option<Position>,
+    /// where the window was the last time it was floating, so that it goes back there when it
+    /// floats again
+    pub floating_geometry: Option<Position>,
+    /// the terminal that is hidden while this window, which was started from it, is open
+    pub swallowed: Option<usize>,
+    /// set by the workspace when it retiles
+    role: Role,
+    /// the size of the frame around the window as the client created or configured it before it
+    /// was managed, if it asked for one
+    pub requested_size: Option<(u16, u16)>,
+    /// where the client put its window before it was managed, if it asked for a position
+    pub requested_position: Option<(i16, i16)>,
+    /// whether we set the input focus on the window, from the WM_HINTS input field
+    accepts_input: bool,
+    /// the window supports the WM_TAKE_FOCUS protocol
+    takes_focus: bool,
+    /// wants attention, from the WM_HINTS urgency flag or _NET_WM_STATE_DEMANDS_ATTENTION
+    pub urgent: bool,
+    /// the window this one is a dialog of, from WM_TRANSIENT_FOR
+    pub transient_for: Option<XWindow>,
+    pub size_hints: SizeHints,
+    /// the process id from _NET_WM_PID
+    pub pid: Option<u32>,
+    /// the first part of WM_CLASS
+    pub instance: String,
+    /// the second part of WM_CLASS
+    pub class: String,
+    pub focused: bool,
+    /// the amount of UnmapNotify events for the window that were caused by us
+    ignore_unmaps: u32,
+    /// draws the title bar
+    draw: Option<DrawContext>,
+    config: Config,
+    /// the _NET_FRAME_EXTENTS atom and the extents that were last set
+    net_frame_extents: Atom,
+    frame_extents: [u32; 4],
 }
 
 impl Client {
     pub fn new(
         window: XWindow,
         root_window: XWindow,
-        conn: &Connection,
+        conn: &Arc<Connection>,
         atoms: &Atoms,
         workspace: u8,
+        depth: u8,
+        config: &Config,
     ) -> Result<Self> {
-        let name = conn.wait_for_reply(conn.send_request(&GetProperty {
-            window,
-            long_length: 128,
-            long_offset: 0,
-            property: atoms.net_wm_name,
-            delete: false,
-            r#type: ATOM_ANY,
-        }));
-        let name = name
-            .ok()
+        let name = Self::fetch_name(window, atoms, conn);
+        let (instance, class) = icccm::get_wm_class(window, conn);
+        let hints = WmHints::get(window, conn);
+        let size_hints = SizeHints::get(window, conn);
+        // unmanaged windows are configured the way they ask, so this is their requested geometry
+        let geometry = conn
+            .wait_for_reply(conn.send_request(&GetGeometry {
+                drawable: Drawable::Window(window),
+            }))
+            .ok();
+        let requested_size = geometry
             .as_ref()
-            .map(GetPropertyReply::value::<u8>)
-            .and_then(|v| str::from_utf8(v).ok())
-            .map(str::to_string)
-            .unwrap_or_default();
+            .filter(|geometry| size_hints.size || (geometry.width() > 1 && geometry.height() > 1))
+            .map(|geometry| {
+                let border_double = config.border_size * 2;
+                (
+                    geometry.width().saturating_add(border_double),
+                    geometry
+                        .height()
+                        .saturating_add(border_double + WINDOW_BAR_HEIGHT),
+                )
+            });
+        let requested_position = geometry
+            .as_ref()
+            .filter(|geometry| size_hints.position || geometry.x() != 0 || geometry.y() != 0)
+            .map(|geometry| (geometry.x(), geometry.y()));
 
         let frame = conn.generate_id();
         conn.send_and_check_request(&CreateWindow {
             depth: COPY_FROM_PARENT as u8,
             wid: frame,
-            border_width: config::BORDER_SIZE,
+            border_width: config.border_size,
             class: xcb::x::WindowClass::InputOutput,
             x: 0,
             y: 0,
@@ -675,11 +2193,12 @@ impl Client {
             visual: COPY_FROM_PARENT,
             value_list: &[
                 Cw::BackPixel(0),
-                Cw::BorderPixel(config::BORDER_COLOR),
+                Cw::BorderPixel(config.border_color),
                 Cw::EventMask(
                     EventMask::PROPERTY_CHANGE
                         | EventMask::SUBSTRUCTURE_NOTIFY
-                        | EventMask::ENTER_WINDOW,
+                        | EventMask::ENTER_WINDOW
+                        | EventMask::EXPOSURE,
                 ),
             ],
         })
@@ -693,12 +2212,31 @@ impl Client {
         })
         .context("failed to reparent the child to the frame")?;
 
+        trace_result!(conn.send_and_check_request(&ChangeWindowAttributes {
+            window,
+            value_list: &[Cw::EventMask(EventMask::PROPERTY_CHANGE)]
+        }); "failed to enable property events for the window");
         trace_result!(conn.send_and_check_request(&ChangeWindowAttributes {
             window: frame,
-            value_list: &[Cw::EventMask(EventMask::SUBSTRUCTURE_NOTIFY | EventMask::ENTER_WINDOW | EventMask::KEY_PRESS | EventMask::KEY_RELEASE)]
+            value_list: &[Cw::EventMask(EventMask::SUBSTRUCTURE_NOTIFY | EventMask::SUBSTRUCTURE_REDIRECT | EventMask::ENTER_WINDOW | EventMask::EXPOSURE | EventMask::KEY_PRESS | EventMask::KEY_RELEASE)]
         }); "failed to enable client events for the frame");
 
-        Ok(Self {
+        let draw = DrawContext::new(
+            frame,
+            Position::new(0, 0, 1, WINDOW_BAR_HEIGHT),
+            conn.clone(),
+            depth,
+        )
+        .and_then(|mut draw| draw.open_font(TITLE_FONT).map(|_| draw));
+        let draw = match draw {
+            Ok(draw) => Some(draw),
+            Err(e) => {
+                error!("failed to create the title bar: {e:?}");
+                None
+            }
+        };
+
+        let mut client = Self {
             window,
             visible: false,
             frame,
@@ -708,7 +2246,123 @@ impl Client {
             x: 0,
             y: 0,
             workspace,
-        })
+            floating: false,
+            fullscreen: false,
+            sticky: false,
+            above: false,
+            transient_for: icccm::get_transient_for(window, conn),
+            size_hints,
+            pid: ewmh::get_wm_pid(window, atoms, conn),
+            instance,
+            class,
+            focused: false,
+            borderless: false,
+            expanded_from: None,
+            floating_geometry: None,
+            swallowed: None,
+            role: Role::default(),
+            requested_size,
+            requested_position,
+            accepts_input: hints.input,
+            takes_focus: ewmh::window_supports(atoms.wm_take_focus, window, atoms, conn),
+            urgent: hints.urgent,
+            ignore_unmaps: 0,
+            draw,
+            config: *config,
+            net_frame_extents: atoms.net_frame_extents,
+            frame_extents: [0; 4],
+        };
+        client.update_frame_extents(conn);
+        Ok(client)
+    }
+
+    /// reads the title of the window, preferring _NET_WM_NAME over WM_NAME
+    fn fetch_name(window: XWindow, atoms: &Atoms, conn: &Connection) -> String {
+        let net_wm_name = conn.send_request(&GetProperty {
+            window,
+            long_length: 128,
+            long_offset: 0,
+            property: atoms.net_wm_name,
+            delete: false,
+            r#type: atoms.utf8_string,
+        });
+        let wm_name = conn.send_request(&GetProperty {
+            window,
+            long_length: 128,
+            long_offset: 0,
+            property: ATOM_WM_NAME,
+            delete: false,
+            r#type: ATOM_ANY,
+        });
+
+        [
+            conn.wait_for_reply(net_wm_name),
+            conn.wait_for_reply(wm_name),
+        ]
+        .iter()
+        .filter_map(|reply| reply.as_ref().ok())
+        .filter(|reply| !reply.value::<u8>().is_empty())
+        .find_map(|reply| TextEncoding::of(reply.r#type(), atoms)?.decode(reply.value()))
+        .unwrap_or_default()
+    }
+
+    /// re-reads the title of the window. returns if it changed
+    pub fn update_name(&mut self, atoms: &Atoms, conn: &Connection) -> bool {
+        let name = Self::fetch_name(self.window, atoms, conn);
+        if name == self.name {
+            return false;
+        }
+        self.name = name;
+        self.draw_title();
+        true
+    }
+
+    /// the _NET_WM_STATE atoms that apply to the window
+    pub fn wm_state(&self, atoms: &Atoms) -> Vec<Atom> {
+        let mut states = vec![];
+        if self.fullscreen {
+            states.push(atoms.net_wm_state_fullscreen);
+        }
+        if self.sticky {
+            states.push(atoms.net_wm_state_sticky);
+        }
+        if self.above {
+            states.push(atoms.net_wm_state_above);
+        }
+        if self.urgent {
+            states.push(atoms.net_wm_state_demands_attention);
+        }
+        states
+    }
+
+    /// gives the window back to the root and destroys the frame
+    pub fn withdraw(&mut self, root_window: XWindow, atoms: &Atoms, conn: &Connection) {
+        trace_result!(
+            icccm::set_wm_state(self.window, WmState::Withdrawn, atoms, conn);
+            "failed to set WM_STATE"
+        );
+        trace_result!(conn.send_and_check_request(&ReparentWindow {
+            window: self.window,
+            parent: root_window,
+            x: self.x as i16,
+            y: self.y as i16,
+        }); "failed to reparent the window to the root");
+        self.destroy(conn);
+    }
+
+    /// gives the window back to the root like `withdraw`, but keeps it mapped
+    pub fn release(&mut self, root_window: XWindow, conn: &Connection) {
+        let (x, y) = self.client_position();
+        trace_result!(conn.send_and_check_request(&ReparentWindow {
+            window: self.window,
+            parent: root_window,
+            x,
+            y,
+        }); "failed to reparent the window to the root");
+        trace_result!(conn.send_and_check_request(&MapWindow {
+            window: self.window,
+        }); "failed to map the window");
+        self.destroy(conn);
     }
 
     pub fn destroy(&mut self, conn: &Connection) {
@@ -724,27 +2378,135 @@ impl Client {
         }
     }
 
-    pub fn focus(&mut self, conn: &Connection) {
-        trace_result!(conn.send_and_check_request(&ChangeWindowAttributes {
-            window: self.frame,
-            value_list: &[Cw::BorderPixel(config::BORDER_COLOR_ACTIVE)],
-        }); "failed to set the border color");
-        trace_result!(conn.send_and_check_request(&SetInputFocus {
-            focus: self.window,
-            revert_to: xcb::x::InputFocus::Parent,
-            time: CURRENT_TIME,
-        }); "failed to focus the input");
+    /// closes the connection of the program that owns the window, which destroys all its windows
+    pub fn kill(&mut self, conn: &Connection) {
+        trace_result!(conn.send_and_check_request(&KillClient {
+            resource: self.window.resource_id(),
+        }); "failed to kill the client");
+        self.destroy(conn);
+    }
+
+    /// draws the name of the window into the title bar
+    pub fn draw_title(&mut self) {
+        let color = self.border_color();
+        let area = Position::new(
+            0,
+            0,
+            self.width.saturating_sub(self.border_size() * 2),
+            WINDOW_BAR_HEIGHT,
+        );
+        let Some(draw) = &mut self.draw else {
+            return;
+        };
+
+        trace_result!(draw.draw_rect(area, color, color); "failed to draw the title bar");
+        let (x, y) = match draw.centered_text(&self.name, area) {
+            Ok(position) => position,
+            Err(e) => {
+                error!("failed to measure the title: {e:?}");
+                (4, WINDOW_BAR_HEIGHT as i16 - 6)
+            }
+        };
+        trace_result!(draw.draw_string(
+            x,
+            y,
+            &self.name,
+            config::TITLE_COLOR,
+            color,
+        ); "failed to draw the title");
+        if config::ACTIVE_INDICATOR && self.focused {
+            let line = Position::new(0, 0, area.width, config::ACTIVE_INDICATOR_HEIGHT);
+            let color = config::ACTIVE_INDICATOR_COLOR;
+            trace_result!(draw.draw_rect(line, color, color); "failed to draw the active window indicator");
+        }
+        if let Some(glyph) = self.role.glyph() {
+            trace_result!(draw.draw_string(
+                4,
+                y,
+                glyph,
+                config::TITLE_ROLE_COLOR,
+                color,
+            ); "failed to draw the role of the window");
+        }
+        trace_result!(draw.finalise(); "failed to draw the title bar");
+    }
+
+    /// redraws the title bar if the role of the window changed
+    pub fn set_role(&mut self, role: Role) {
+        if self.role != role {
+            self.role = role;
+            self.draw_title();
+        }
+    }
+
+    /// gives the window the input focus, the way it asked for in WM_HINTS and WM_PROTOCOLS
+    /// (ICCCM 4.1.7)
+    pub fn focus(&mut self, atoms: &Atoms, conn: &Connection) {
+        self.focused = true;
+        self.urgent = false;
+        self.draw_title();
+        self.update_border(conn);
+        if self.accepts_input {
+            trace_result!(conn.send_and_check_request(&SetInputFocus {
+                focus: self.window,
+                revert_to: xcb::x::InputFocus::Parent,
+                time: CURRENT_TIME,
+            }); "failed to focus the input");
+        }
+        if self.takes_focus {
+            trace_result!(ewmh::take_focus(self.window, atoms, conn); "failed to send WM_TAKE_FOCUS");
+        }
     }
 
     pub fn unfocus(&mut self, conn: &Connection) {
+        self.focused = false;
+        self.draw_title();
+        self.update_border(conn);
+    }
+
+    pub fn set_urgent(&mut self, urgent: bool, conn: &Connection) {
+        self.urgent = urgent;
+        self.draw_title();
+        self.update_border(conn);
+    }
+
+    fn border_color(&self) -> u32 {
+        if self.focused {
+            self.config.border_color_active
+        } else if self.urgent {
+            self.config.border_color_urgent
+        } else {
+            self.config.border_color
+        }
+    }
+
+    fn update_border(&self, conn: &Connection) {
         trace_result!(conn.send_and_check_request(&ChangeWindowAttributes {
             window: self.frame,
-            value_list: &[Cw::BorderPixel(config::BORDER_COLOR)],
-        }); "failed to reset the border color");
+            value_list: &[Cw::BorderPixel(self.border_color())],
+        }); "failed to set the border color");
     }
 
     pub fn update(&mut self, width: u16, height: u16, x: u16, y: u16, conn: &Connection) {
-        let border_double = config::BORDER_SIZE * 2;
+        if self.fullscreen {
+            self.update_fullscreen(width, height, x, y, conn);
+            return;
+        }
+        let border_double = self.border_size() * 2;
+        let (content_width, content_height) = self.size_hints.constrain(
+            width.saturating_sub(border_double),
+            height.saturating_sub(border_double + WINDOW_BAR_HEIGHT),
+            self.floating,
+        );
+        // the server refuses windows without a size
+        let (content_width, content_height) = (content_width.max(1), content_height.max(1));
+        let width = content_width + border_double;
+        let height = content_height + border_double + WINDOW_BAR_HEIGHT;
+        let resized = self.width != width;
+        self.width = width;
+        self.height = height;
+        self.x = x;
+        self.y = y;
 
         trace_result!(conn.send_and_check_request(&ConfigureWindow {
             window: self.frame,
@@ -753,6 +2515,7 @@ impl Client {
                 ConfigWindow::Y(y as i32),
                 ConfigWindow::Width((width - border_double) as u32),
                 ConfigWindow::Height((height - border_double) as u32),
+                ConfigWindow::BorderWidth(self.border_size() as u32),
             ],
         }));
         trace_result!(conn.send_and_check_request(&ConfigureWindow {
@@ -764,9 +2527,181 @@ impl Client {
                 ConfigWindow::Height((height - border_double - WINDOW_BAR_HEIGHT) as u32),
             ],
         }));
+
+        if resized {
+            self.resize_title();
+        }
+        self.update_frame_extents(conn);
+        // toolkits rely on this to relayout, even when the size didn't change
+        self.send_configure_notify(conn);
+    }
+
+    /// fits the title bar to the width of the frame and redraws it
+    fn resize_title(&mut self) {
+        let width = self.width.saturating_sub(self.border_size() * 2).max(1);
+        if let Some(draw) = &mut self.draw {
+            trace_result!(
+                draw.resize(Position::new(0, 0, width, WINDOW_BAR_HEIGHT));
+                "failed to resize the title bar"
+            );
+        }
+        self.draw_title();
+    }
+
+    /// applies a new border size, keeping the outer size of the window. the content shrinks or
+    /// grows instead
+    pub fn set_border_size(&mut self, size: u16, conn: &Connection) {
+        self.config.border_size = size;
+        self.update(self.width, self.height, self.x, self.y, conn);
+        // the outer width stays the same, so `update` doesn't resize the title bar itself
+        self.resize_title();
+    }
+
+    fn border_size(&self) -> u16 {
+        if self.borderless {
+            0
+        } else {
+            self.config.border_size
+        }
+    }
+
+    /// fullscreen windows have neither a border nor a title bar
+    fn update_fullscreen(&mut self, width: u16, height: u16, x: u16, y: u16, conn: &Connection) {
+        self.width = width;
+        self.height = height;
+        self.x = x;
+        self.y = y;
+
+        trace_result!(conn.send_and_check_request(&ConfigureWindow {
+            window: self.frame,
+            value_list: &[
+                ConfigWindow::X(x as i32),
+                ConfigWindow::Y(y as i32),
+                ConfigWindow::Width(width as u32),
+                ConfigWindow::Height(height as u32),
+                ConfigWindow::BorderWidth(0),
+            ],
+        }));
+        trace_result!(conn.send_and_check_request(&ConfigureWindow {
+            window: self.window,
+            value_list: &[
+                ConfigWindow::X(0),
+                ConfigWindow::Y(0),
+                ConfigWindow::Width(width as u32),
+                ConfigWindow::Height(height as u32),
+            ],
+        }));
+        self.update_frame_extents(conn);
+        self.send_configure_notify(conn);
     }
 
-    pub fn hide(&mut self, conn: &Connection) {
+    /// sets _NET_FRAME_EXTENTS if the border or the title bar changed
+    fn update_frame_extents(&mut self, conn: &Connection) {
+        let extents = if self.fullscreen {
+            [0; 4]
+        } else {
+            let border = self.border_size() as u32;
+            [border, border, border + WINDOW_BAR_HEIGHT as u32, border]
+        };
+        if extents == self.frame_extents {
+            return;
+        }
+        self.frame_extents = extents;
+        trace_result!(
+            ewmh::set_frame_extents(self.window, extents, self.net_frame_extents, conn);
+            "failed to set _NET_FRAME_EXTENTS"
+        );
+    }
+
+    /// the border and the title bar around the client window, which fullscreen windows don't have
+    fn decorations(&self) -> (u16, u16) {
+        if self.fullscreen {
+            (0, 0)
+        } else {
+            (self.border_size(), WINDOW_BAR_HEIGHT)
+        }
+    }
+
+    /// where the client window is on the screen, inside of the frame
+    fn client_position(&self) -> (i16, i16) {
+        let (border, bar_height) = self.decorations();
+        (
+            (self.x + border) as i16,
+            (self.y + border + bar_height) as i16,
+        )
+    }
+
+    /// informs the client about its current geometry without changing it
+    pub fn send_configure_notify(&self, conn: &Connection) {
+        let (border, bar_height) = self.decorations();
+        let (x, y) = self.client_position();
+        let event = ConfigureNotifyEvent::new(
+            self.window,
+            self.window,
+            XWindow::none(),
+            x,
+            y,
+            self.width.saturating_sub(border * 2),
+            self.height.saturating_sub(border * 2 + bar_height),
+            0,
+            false,
+        );
+        trace_result!(conn.send_and_check_request(&SendEvent {
+            propagate: false,
+            destination: SendEventDest::Window(self.window),
+            event_mask: EventMask::STRUCTURE_NOTIFY,
+            event: &event,
+        }); "failed to send a synthetic ConfigureNotify");
+    }
+
+    /// intercepts left clicks on the frame, so that they can focus and raise the window before
+    /// being replayed to the client. scrolling and the other buttons go straight to the client
+    pub fn grab_click(&self, conn: &Connection) {
+        trace_result!(conn.send_and_check_request(&GrabButton {
+            owner_events: false,
+            grab_window: self.frame,
+            event_mask: EventMask::BUTTON_PRESS,
+            pointer_mode: GrabMode::Sync,
+            keyboard_mode: GrabMode::Async,
+            confine_to: XWindow::none(),
+            cursor: Cursor::none(),
+            button: ButtonIndex::N1,
+            modifiers: ModMask::ANY,
+        }); "failed to grab clicks on the frame");
+    }
+
+    /// the geometry of the frame, including the borders
+    pub fn position(&self) -> Position {
+        Position::new(self.x, self.y, self.width, self.height)
+    }
+
+    /// puts the window on top of all its siblings
+    pub fn raise(&self, conn: &Connection) {
+        trace_result!(conn.send_and_check_request(&ConfigureWindow {
+            window: self.frame,
+            value_list: &[ConfigWindow::StackMode(StackMode::Above)],
+        }); "failed to raise the frame");
+    }
+
+    /// moves the pointer to the center of the window
+    pub fn warp_pointer(&self, conn: &Connection) {
+        trace_result!(conn.send_and_check_request(&WarpPointer {
+            src_window: XWindow::none(),
+            dst_window: self.frame,
+            src_x: 0,
+            src_y: 0,
+            src_width: 0,
+            src_height: 0,
+            dst_x: (self.width / 2) as i16,
+            dst_y: (self.height / 2) as i16,
+        }); "failed to warp the pointer");
+    }
+
+    pub fn hide(&mut self, atoms: &Atoms, conn: &Connection) {
+        if self.visible {
+            // we'll get an UnmapNotify for the window, which isn't the client withdrawing it
+            self.ignore_unmaps += 1;
+        }
         self.visible = false;
         let window_unmap = conn.send_request_checked(&UnmapWindow {
             window: self.window,
@@ -777,9 +2712,13 @@ impl Client {
             "failed to unmap the window"
         );
         trace_result!(conn.check_request(frame_unmap); "failed to unmap the frame");
+        trace_result!(
+            icccm::set_wm_state(self.window, WmState::Iconic, atoms, conn);
+            "failed to set WM_STATE"
+        );
     }
 
-    pub fn show(&mut self, conn: &Connection) {
+    pub fn show(&mut self, atoms: &Atoms, conn: &Connection) {
         self.visible = true;
         let map_frame = conn.send_request_checked(&MapWindow { window: self.frame });
         let map_window = conn.send_request_checked(&MapWindow {
@@ -787,5 +2726,321 @@ impl Client {
         });
         trace_result!(conn.check_request(map_frame); "failed to map the frame");
         trace_result!(conn.check_request(map_window); "failed to map the window");
+        trace_result!(
+            icccm::set_wm_state(self.window, WmState::Normal, atoms, conn);
+            "failed to set WM_STATE"
+        );
+    }
+}
+
+/// how a text property is encoded, going by its type
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TextEncoding {
+    /// `STRING`
+    Latin1,
+    /// `UTF8_STRING`
+    Utf8,
+    /// `COMPOUND_TEXT`
+    CompoundText,
+}
+
+impl TextEncoding {
+    fn of(r#type: Atom, atoms: &Atoms) -> Option<Self> {
+        if r#type == ATOM_STRING {
+            Some(Self::Latin1)
+        } else if r#type == atoms.utf8_string {
+            Some(Self::Utf8)
+        } else if r#type == atoms.compound_text {
+            Some(Self::CompoundText)
+        } else {
+            None
+        }
+    }
+
+    fn decode(self, value: &[u8]) -> Option<String> {
+        match self {
+            Self::Latin1 => Some(value.iter().map(|&b| b as char).collect()),
+            Self::Utf8 => str::from_utf8(value).ok().map(str::to_string),
+            // plenty of programs put utf-8 into it. without escape sequences that can't be told
+            // apart from latin-1, which is then the less likely one
+            Self::CompoundText
+                if !value.iter().any(|&b| b == ESC || b == CSI)
+                    && str::from_utf8(value).is_ok() =>
+            {
+                str::from_utf8(value).ok().map(str::to_string)
+            }
+            Self::CompoundText => Some(decode_compound_text(value)),
+        }
+    }
+}
+
+const ESC: u8 = 0x1b;
+/// the control sequence introducer, used for the direction of the text
+const CSI: u8 = 0x9b;
+
+/// decodes the parts of COMPOUND_TEXT that are ascii, latin-1 or utf-8. text in any other
+/// character set, which would need the conversion tables of xlib, is replaced by a single U+FFFD
+fn decode_compound_text(value: &[u8]) -> String {
+    let mut text = String::with_capacity(value.len());
+    let unknown = |text: &mut String| {
+        if !text.ends_with(char::REPLACEMENT_CHARACTER) {
+            text.push(char::REPLACEMENT_CHARACTER);
+        }
+    };
+    // whether the left (0x20-0x7f) and the right half (0xa0-0xff) are ascii and latin-1
+    let (mut ascii, mut latin1) = (true, true);
+    let mut bytes = value.iter().copied().peekable();
+    while let Some(b) = bytes.next() {
+        match b {
+            ESC => {
+                let mut sequence = Vec::new();
+                while let Some(b) = bytes.next_if(|b| (0x20..=0x2f).contains(b)) {
+                    sequence.push(b);
+                }
+                sequence.extend(bytes.next());
+                match sequence.as_slice() {
+                    b"%G" => {
+                        let mut utf8 = Vec::new();
+                        while let Some(b) = bytes.next() {
+                            if b == ESC && bytes.peek() == Some(&b'%') {
+                                bytes.next();
+                                bytes.next();
+                                break;
+                            }
+                            utf8.push(b);
+                        }
+                        text.push_str(&String::from_utf8_lossy(&utf8));
+                    }
+                    b"(B" => ascii = true,
+                    b"-A" => latin1 = true,
+                    [b'(', ..] | [b'$', b'(', ..] => ascii = false,
+                    _ => latin1 = false,
+                }
+            }
+            CSI => {
+                // the direction doesn't matter for a title
+                while bytes.next_if(|b| (0x30..=0x3f).contains(b)).is_some() {}
+                bytes.next();
+            }
+            b'\t' | b'\n' => text.push(b as char),
+            0x20..=0x7f if ascii => text.push(b as char),
+            0xa0..=0xff if latin1 => text.push(b as char),
+            0x20..=0x7f | 0xa0..=0xff => unknown(&mut text),
+            // other control characters
+            _ => {}
+        }
+    }
+    text
+}
+
+/// the parent, grandparent and so on of the process, read from /proc
+fn parent_pids(pid: u32) -> impl Iterator<Item = u32> {
+    std::iter::successors(Some(pid), |&pid| {
+        let stat = std::fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+        // the name in parentheses might contain spaces, the parent pid is the second field after it
+        let (_, fields) = stat.rsplit_once(')')?;
+        let parent = fields.split_whitespace().nth(1)?.parse().ok()?;
+        (parent > 1).then_some(parent)
+    })
+    .skip(1)
+}
+
+/// the area a strut reserves on a screen of the given size, the edge it is on and how much it
+/// reserves. the values are clamped to the screen, as clients are known to send garbage. returns
+/// None if the strut doesn't reserve anything
+fn strut_area(values: [u32; 12], width: u16, height: u16) -> Option<(Position, ScreenSide, u16)> {
+    // _NET_WM_STRUT: https://specifications.freedesktop.org/wm-spec/latest/ar01s05.html#id-1.6.10
+    // _NET_WM_STRUT_PARTIAL: https://specifications.freedesktop.org/wm-spec/latest/ar01s05.html#id-1.6.11
+    let [left, right, top, bottom, left_start_y, left_end_y, right_start_y, right_end_y, top_start_x, top_end_x, bottom_start_x, bottom_end_x] =
+        values;
+
+    let amount = |value: u32, max: u16| {
+        if value > max as u32 {
+            warn!("a strut of {value} doesn't fit the screen, clamping it to {max}");
+        }
+        value.min(max as u32) as u16
+    };
+    // the range along the edge. _NET_WM_STRUT doesn't have one, so it covers the entire edge
+    let range = |start: u32, end: u32, max: u16| {
+        let (start, end) = (start.min(max as u32) as u16, end.min(max as u32) as u16);
+        if start < end {
+            return (start, end - start);
+        }
+        if start > end {
+            warn!("the strut range {start}..{end} is reversed, using the entire edge");
+        }
+        (0, max)
+    };
+
+    if left > 0 {
+        let amount = amount(left, width);
+        let (y, height) = range(left_start_y, left_end_y, height);
+        Some((
+            Position::new(0, y, amount, height),
+            ScreenSide::Left,
+            amount,
+        ))
+    } else if bottom > 0 {
+        let amount = amount(bottom, height);
+        let (x, width) = range(bottom_start_x, bottom_end_x, width);
+        Some((
+            Position::new(x, height - amount, width, amount),
+            ScreenSide::Bottom,
+            amount,
+        ))
+    } else if top > 0 {
+        let amount = amount(top, height);
+        let (x, width) = range(top_start_x, top_end_x, width);
+        Some((Position::new(x, 0, width, amount), ScreenSide::Top, amount))
+    } else if right > 0 {
+        let amount = amount(right, width);
+        let (y, height) = range(right_start_y, right_end_y, height);
+        Some((
+            Position::new(width - amount, y, amount, height),
+            ScreenSide::Right,
+            amount,
+        ))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod xvfb_tests;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reserved_space(clients: &Slab<ReservedClient>) -> ReservedSpace {
+        let mut space = ReservedSpace::default();
+        for client in clients.iter() {
+            space.reserve(client.direction, client.reserved);
+        }
+        space
+    }
+
+    fn client(direction: ScreenSide, reserved: u16) -> ReservedClient {
+        ReservedClient {
+            window: XWindow::none(),
+            position: Position::new(0, 0, 100, reserved),
+            reserved,
+            direction,
+        }
+    }
+
+    #[test]
+    fn removing_a_client_after_it_was_unreserved_does_not_underflow() {
+        let mut clients = Slab::new();
+        let idx = clients.push(client(ScreenSide::Top, 30));
+
+        // the screen shrinks below the reserved space
+        let mut space = reserved_space(&clients);
+        let unreserved = space.fit(100, 20);
+        assert_eq!(unreserved, [ScreenSide::Top, ScreenSide::Bottom]);
+        assert_eq!(space, ReservedSpace::default());
+
+        clients.remove(idx);
+        assert_eq!(reserved_space(&clients), ReservedSpace::default());
+    }
+
+    #[test]
+    fn struts_on_the_same_edge_overlap() {
+        let mut clients = Slab::new();
+        let bar = clients.push(client(ScreenSide::Bottom, 24));
+        clients.push(client(ScreenSide::Bottom, 16));
+        assert_eq!(reserved_space(&clients).bottom, 24);
+
+        clients.remove(bar);
+        assert_eq!(reserved_space(&clients).bottom, 16);
+    }
+
+    #[test]
+    fn huge_struts_are_clamped_to_the_screen() {
+        let mut values = [0; 12];
+        values[2] = u32::MAX;
+        values[9] = u32::MAX;
+        let (position, side, amount) = strut_area(values, 800, 600).unwrap();
+        assert_eq!(side, ScreenSide::Top);
+        assert_eq!(amount, 600);
+        assert_eq!(position, Position::new(0, 0, 800, 600));
+
+        let mut values = [0; 12];
+        values[1] = 70_000;
+        let (position, side, amount) = strut_area(values, 800, 600).unwrap();
+        assert_eq!(side, ScreenSide::Right);
+        assert_eq!(amount, 800);
+        assert_eq!(position, Position::new(0, 0, 800, 600));
+    }
+
+    #[test]
+    fn reversed_strut_ranges_cover_the_entire_edge() {
+        let mut values = [0; 12];
+        values[0] = 20;
+        values[4] = 500;
+        values[5] = 100;
+        let (position, _, _) = strut_area(values, 800, 600).unwrap();
+        assert_eq!(position, Position::new(0, 0, 20, 600));
+
+        // a partial strut that starts past the screen
+        let mut values = [0; 12];
+        values[3] = 24;
+        values[10] = 5000;
+        values[11] = 6000;
+        let (position, _, _) = strut_area(values, 800, 600).unwrap();
+        assert_eq!(position, Position::new(0, 576, 800, 24));
+    }
+
+    #[test]
+    fn struts_without_a_range_cover_the_entire_edge() {
+        let (position, side, amount) =
+            strut_area([0, 0, 25, 0, 0, 0, 0, 0, 0, 0, 0, 0], 800, 600).unwrap();
+        assert_eq!(side, ScreenSide::Top);
+        assert_eq!(amount, 25);
+        assert_eq!(position, Position::new(0, 0, 800, 25));
+    }
+
+    #[test]
+    fn partial_struts_keep_their_range() {
+        let (position, _, _) =
+            strut_area([0, 0, 25, 0, 0, 0, 0, 0, 100, 300, 0, 0], 800, 600).unwrap();
+        assert_eq!(position, Position::new(100, 0, 200, 25));
+    }
+
+    #[test]
+    fn empty_struts_reserve_nothing() {
+        assert_eq!(strut_area([0; 12], 800, 600), None);
+        assert_eq!(
+            strut_area([0, 0, 0, 0, 5, 10, 5, 10, 5, 10, 5, 10], 800, 600),
+            None
+        );
+    }
+
+    #[test]
+    fn latin1_and_utf8_titles() {
+        assert_eq!(
+            TextEncoding::Latin1.decode(b"caf\xe9").as_deref(),
+            Some("café")
+        );
+        assert_eq!(
+            TextEncoding::Utf8.decode("café".as_bytes()).as_deref(),
+            Some("café")
+        );
+        assert_eq!(TextEncoding::Utf8.decode(b"caf\xe9"), None);
+    }
+
+    #[test]
+    fn compound_text_titles() {
+        let decode = |value: &[u8]| TextEncoding::CompoundText.decode(value).unwrap();
+        // without escape sequences it is ascii and latin-1, unless it is valid utf-8
+        assert_eq!(decode(b"caf\xe9"), "café");
+        assert_eq!(decode("café".as_bytes()), "café");
+        // utf-8 segments
+        assert_eq!(decode(b"a \x1b%G\xe2\x86\x92\x1b%@ b"), "a → b");
+        // latin-1 designated explicitly, and a direction that is ignored
+        assert_eq!(decode(b"\x1b(B\x1b-A\x9b1]caf\xe9\x9b]"), "café");
+        // text in other character sets
+        assert_eq!(decode(b"x \x1b$)A\xc4\xe3\xba\xc3\x1b-A y"), "x \u{fffd} y");
+        assert_eq!(decode(b"\x1b$(B\x24\x22\x1b(B!"), "\u{fffd}!");
     }
 }
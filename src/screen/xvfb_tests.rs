@@ -0,0 +1,348 @@
+//! tests against a real X server. they start their own Xvfb on a free display, so they don't
+//! touch the running session, but need Xvfb to be installed. run them with
+//! `cargo test -- --ignored`
+
+use std::{
+    io::{BufRead, BufReader},
+    path::PathBuf,
+    process::{Child, Command, Stdio},
+    sync::{Arc, Mutex, OnceLock},
+};
+
+use xcb::{
+    x::{
+        self, ChangeProperty, ClientMessageData, CreateWindow, Cw, DestroyWindow, Drawable,
+        GetGeometry, GetWindowAttributes, MapState, PropMode, Window as XWindow, WindowClass,
+        ATOM_ATOM, COPY_FROM_PARENT,
+    },
+    Connection, Xid,
+};
+
+use super::{Client, Screen};
+use crate::{atoms::Atoms, config::Config, layout::Position, tiling::Layout};
+
+/// an Xvfb on a display it picked itself. it is killed when dropped
+struct Xvfb {
+    process: Child,
+    display: String,
+}
+
+impl Xvfb {
+    fn start() -> Self {
+        let mut process = Command::new("Xvfb")
+            .args([
+                "-displayfd",
+                "1",
+                "-screen",
+                "0",
+                "1280x720x24",
+                "-nolisten",
+                "tcp",
+            ])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .expect("failed to start Xvfb, is it installed?");
+        // the display number is written to stdout once the server accepts connections
+        let mut number = String::new();
+        BufReader::new(process.stdout.take().expect("stdout is piped"))
+            .read_line(&mut number)
+            .expect("failed to read the display of Xvfb");
+        assert!(!number.trim().is_empty(), "Xvfb exited before it was ready");
+
+        Self {
+            process,
+            display: format!(":{}", number.trim()),
+        }
+    }
+}
+
+impl Drop for Xvfb {
+    fn drop(&mut self) {
+        _ = self.process.kill();
+        _ = self.process.wait();
+    }
+}
+
+/// the data directory all harnesses of this process save their session in, so it doesn't end up
+/// in the real one. the tests run in parallel, so $XDG_DATA_HOME is only set once, before any of
+/// them started a process
+fn data_dir() -> &'static PathBuf {
+    static DATA_DIR: OnceLock<PathBuf> = OnceLock::new();
+    DATA_DIR.get_or_init(|| {
+        let dir = std::env::temp_dir().join(format!("wm-test-{}", std::process::id()));
+        std::env::set_var("XDG_DATA_HOME", &dir);
+        dir
+    })
+}
+
+/// how many harnesses are alive. the last one to be dropped removes the data directory
+static HARNESSES: Mutex<usize> = Mutex::new(0);
+
+/// a screen managing an Xvfb, with a second connection that plays the clients
+struct Harness {
+    screen: Screen,
+    clients: Connection,
+    atoms: Atoms,
+    root: XWindow,
+    // dropped last, the connections have to go first
+    _xvfb: Xvfb,
+}
+
+impl Harness {
+    fn new() -> Self {
+        // the session is saved whenever windows change
+        let data_dir = data_dir();
+        {
+            let mut harnesses = HARNESSES.lock().unwrap_or_else(|e| e.into_inner());
+            std::fs::create_dir_all(data_dir).expect("failed to create the data directory");
+            *harnesses += 1;
+        }
+        let xvfb = Xvfb::start();
+
+        let (conn, _) =
+            Connection::connect(Some(&xvfb.display)).expect("failed to connect to Xvfb");
+        let conn = Arc::new(conn);
+        let atoms = Atoms::get(&conn);
+        let setup = conn.get_setup();
+        let x_screen = setup.roots().next().expect("Xvfb has a screen");
+        let (root, width, height, depth) = (
+            x_screen.root(),
+            x_screen.width_in_pixels(),
+            x_screen.height_in_pixels(),
+            x_screen.root_depth(),
+        );
+        let screen = Screen::new(
+            width,
+            height,
+            Config::default(),
+            atoms,
+            root,
+            conn.clone(),
+            depth,
+        )
+        .expect("failed to create the screen");
+
+        let (clients, _) =
+            Connection::connect(Some(&xvfb.display)).expect("failed to connect to Xvfb");
+
+        Self {
+            screen,
+            clients,
+            atoms,
+            root,
+            _xvfb: xvfb,
+        }
+    }
+
+    /// creates a window that supports WM_DELETE_WINDOW and manages it, like after a MapRequest
+    fn open_window(&mut self) -> XWindow {
+        let window = self.clients.generate_id();
+        self.clients
+            .send_and_check_request(&CreateWindow {
+                depth: COPY_FROM_PARENT as u8,
+                wid: window,
+                parent: self.root,
+                x: 0,
+                y: 0,
+                width: 100,
+                height: 100,
+                border_width: 0,
+                class: WindowClass::InputOutput,
+                visual: COPY_FROM_PARENT,
+                value_list: &[Cw::BackPixel(0)],
+            })
+            .expect("failed to create a window");
+        self.clients
+            .send_and_check_request(&ChangeProperty {
+                mode: PropMode::Replace,
+                window,
+                property: self.atoms.wm_protocols,
+                r#type: ATOM_ATOM,
+                data: &[self.atoms.wm_delete_window],
+            })
+            .expect("failed to set WM_PROTOCOLS");
+
+        self.screen
+            .add_window(window)
+            .expect("failed to manage the window");
+        window
+    }
+
+    /// waits for the screen to ask a window to close and closes it, like a well-behaved client
+    fn answer_delete_window(&mut self) -> XWindow {
+        let window = loop {
+            let event = self
+                .clients
+                .wait_for_event()
+                .expect("failed to wait for an event");
+            let xcb::Event::X(x::Event::ClientMessage(event)) = event else {
+                continue;
+            };
+            let ClientMessageData::Data32([protocol, ..]) = event.data() else {
+                continue;
+            };
+            if event.r#type() == self.atoms.wm_protocols
+                && protocol == self.atoms.wm_delete_window.resource_id()
+            {
+                break event.window();
+            }
+        };
+        self.clients
+            .send_and_check_request(&DestroyWindow { window })
+            .expect("failed to destroy the window");
+        // what the DestroyNotify would do
+        self.screen.remove_window(window);
+        window
+    }
+
+    fn client(&self, window: XWindow) -> &Client {
+        self.screen
+            .client_for_window(window)
+            .expect("the window is managed")
+    }
+
+    /// the area the tiled windows of the current workspace are laid out in
+    fn area(&self) -> Position {
+        self.screen.workspaces[self.screen.context.current_workspace as usize].get_screen_position()
+    }
+
+    fn is_mapped(&self, window: XWindow) -> bool {
+        let conn = &self.screen.context.connection;
+        let attributes = conn
+            .wait_for_reply(conn.send_request(&GetWindowAttributes { window }))
+            .expect("failed to get the window attributes");
+        attributes.map_state() == MapState::Viewable
+    }
+
+    /// checks that the server put the frame where the client thinks it is
+    fn assert_on_server(&self, client: &Client) {
+        let conn = &self.screen.context.connection;
+        let geometry = conn
+            .wait_for_reply(conn.send_request(&GetGeometry {
+                drawable: Drawable::Window(client.frame),
+            }))
+            .expect("failed to get the frame geometry");
+        let border = geometry.border_width() * 2;
+        assert_eq!(
+            (
+                geometry.x(),
+                geometry.y(),
+                geometry.width() + border,
+                geometry.height() + border,
+            ),
+            (
+                client.x as i16,
+                client.y as i16,
+                client.width,
+                client.height,
+            ),
+            "frame of {:?}",
+            client.window,
+        );
+    }
+}
+
+impl Drop for Harness {
+    fn drop(&mut self) {
+        let mut harnesses = HARNESSES.lock().unwrap_or_else(|e| e.into_inner());
+        *harnesses -= 1;
+        if *harnesses == 0 {
+            _ = std::fs::remove_dir_all(data_dir());
+        }
+    }
+}
+
+fn overlap(a: &Client, b: &Client) -> bool {
+    a.x < b.x + b.width && b.x < a.x + a.width && a.y < b.y + b.height && b.y < a.y + a.height
+}
+
+#[test]
+#[ignore = "needs Xvfb"]
+fn every_layout_tiles_inside_the_workspace() {
+    let mut harness = Harness::new();
+    let windows = [(); 3].map(|_| harness.open_window());
+
+    for &layout in Layout::all() {
+        harness.screen.set_layout(layout);
+        let area = harness.area();
+        let clients = windows.map(|window| harness.client(window));
+        for client in clients {
+            assert!(
+                client.x >= area.x
+                    && client.y >= area.y
+                    && client.x + client.width <= area.x + area.width
+                    && client.y + client.height <= area.y + area.height,
+                "{layout:?}: {client:?} is outside of {area:?}"
+            );
+            harness.assert_on_server(client);
+        }
+        if layout != Layout::Monocle {
+            for (i, a) in clients.iter().enumerate() {
+                for b in &clients[i + 1..] {
+                    assert!(!overlap(a, b), "{layout:?}: {a:?} overlaps {b:?}");
+                }
+            }
+        }
+    }
+}
+
+#[test]
+#[ignore = "needs Xvfb"]
+fn switching_workspaces_hides_and_shows_the_windows() {
+    let mut harness = Harness::new();
+    let first = harness.open_window();
+    let second = harness.open_window();
+    let frames = [first, second].map(|window| harness.client(window).frame);
+
+    harness.screen.switch_workspace(1).unwrap();
+    for frame in frames {
+        assert!(!harness.is_mapped(frame));
+    }
+    // a window on the empty workspace gets all of it
+    let other = harness.open_window();
+    let other_frame = harness.client(other).frame;
+    assert_eq!(harness.client(other).workspace, 1);
+    assert!(harness.is_mapped(other_frame));
+
+    harness.screen.switch_workspace(0).unwrap();
+    for frame in frames {
+        assert!(harness.is_mapped(frame));
+    }
+    assert!(!harness.is_mapped(other_frame));
+    // the window that was focused when the workspace was left has the focus again
+    assert_eq!(
+        harness
+            .screen
+            .context
+            .focused_window
+            .map(|idx| harness.screen.context.windows[idx].window),
+        Some(second)
+    );
+}
+
+#[test]
+#[ignore = "needs Xvfb"]
+fn closing_the_focused_window_retiles_the_rest() {
+    let mut harness = Harness::new();
+    let first = harness.open_window();
+    let second = harness.open_window();
+    let shared_width = harness.client(first).width;
+
+    // new windows are focused, so this asks the second one to close
+    harness.screen.close_focused_window();
+    assert!(harness.screen.client_for_window(second).is_some());
+    assert_eq!(harness.answer_delete_window(), second);
+    assert!(harness.screen.client_for_window(second).is_none());
+    assert_eq!(harness.screen.clients().count(), 1);
+    assert!(harness.client(first).width > shared_width);
+    harness.assert_on_server(harness.client(first));
+
+    // a window that goes away by itself is forgotten as well
+    harness
+        .clients
+        .send_and_check_request(&DestroyWindow { window: first })
+        .unwrap();
+    harness.screen.remove_window(first);
+    assert_eq!(harness.screen.clients().count(), 0);
+}
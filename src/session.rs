@@ -0,0 +1,174 @@
+use std::io::ErrorKind;
+
+use anyhow::Context;
+
+use crate::config;
+
+/// where a window lived, identified by its _NET_WM_PID and WM_CLASS
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionEntry {
+    pub pid: Option<u32>,
+    pub class: String,
+    /// the id of the workspace
+    pub workspace: u32,
+    pub floating: bool,
+}
+
+/// the arrangement of the windows, saved so that it survives a restart or a crash
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Session {
+    entries: Vec<SessionEntry>,
+}
+
+impl Session {
+    pub fn new(entries: Vec<SessionEntry>) -> Self {
+        Self { entries }
+    }
+
+    /// reads the saved session. a missing file is an empty session
+    pub fn load() -> anyhow::Result<Self> {
+        let path = config::get_session_file()?;
+        match std::fs::read_to_string(&path) {
+            Ok(contents) => Ok(Self::parse(&contents)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e).with_context(|| format!("failed to read {path:?}")),
+        }
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        let path = config::get_session_file()?;
+        std::fs::write(&path, self.serialize()).with_context(|| format!("failed to write {path:?}"))
+    }
+
+    /// removes the entry of a window and returns it. an entry with the same pid is preferred over
+    /// one that only has the same class
+    pub fn take(&mut self, pid: Option<u32>, class: &str) -> Option<SessionEntry> {
+        let idx = self
+            .entries
+            .iter()
+            .position(|entry| pid.is_some() && entry.pid == pid && entry.class == class)
+            .or_else(|| self.entries.iter().position(|entry| entry.class == class))?;
+        Some(self.entries.remove(idx))
+    }
+
+    /// one window per line: the workspace id, whether it floats, the pid or `-` and the class.
+    /// tabs, newlines and backslashes in the class are escaped
+    fn serialize(&self) -> String {
+        self.entries
+            .iter()
+            .map(|entry| {
+                let pid = entry.pid.map_or("-".to_string(), |pid| pid.to_string());
+                format!(
+                    "{}\t{}\t{pid}\t{}\n",
+                    entry.workspace,
+                    entry.floating,
+                    escape(&entry.class)
+                )
+            })
+            .collect()
+    }
+
+    fn parse(contents: &str) -> Self {
+        let entries = contents
+            .lines()
+            .filter_map(|line| {
+                let mut parts = line.splitn(4, '\t');
+                let workspace = parts.next()?.parse().ok()?;
+                let floating = parts.next()?.parse().ok()?;
+                let pid = match parts.next()? {
+                    "-" => None,
+                    pid => Some(pid.parse().ok()?),
+                };
+                let class = unescape(parts.next()?)?;
+                Some(SessionEntry {
+                    pid,
+                    class,
+                    workspace,
+                    floating,
+                })
+            })
+            .collect();
+        Self { entries }
+    }
+}
+
+fn escape(class: &str) -> String {
+    let mut escaped = String::with_capacity(class.len());
+    for c in class.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// None if there is an unknown or unfinished escape
+fn unescape(escaped: &str) -> Option<String> {
+    let mut class = String::with_capacity(escaped.len());
+    let mut chars = escaped.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            class.push(c);
+            continue;
+        }
+        class.push(match chars.next()? {
+            '\\' => '\\',
+            't' => '\t',
+            'n' => '\n',
+            'r' => '\r',
+            _ => return None,
+        });
+    }
+    Some(class)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(pid: Option<u32>, class: &str, workspace: u32, floating: bool) -> SessionEntry {
+        SessionEntry {
+            pid,
+            class: class.to_string(),
+            workspace,
+            floating,
+        }
+    }
+
+    #[test]
+    fn sessions_survive_a_round_trip() {
+        let session = Session::new(vec![
+            entry(Some(1234), "Alacritty", 1, false),
+            entry(None, "", 3, true),
+            entry(Some(1), "with\ttab\nnewline\\backslash\r", 10, false),
+            entry(None, "trailing\\", 2, true),
+        ]);
+        let serialized = session.serialize();
+        assert_eq!(serialized.lines().count(), 4);
+        assert_eq!(Session::parse(&serialized), session);
+    }
+
+    #[test]
+    fn malformed_lines_are_skipped() {
+        let contents = "1\ttrue\t-\tkept\n\
+                        \n\
+                        x\tfalse\t-\tbad workspace\n\
+                        1\tmaybe\t-\tbad floating\n\
+                        1\tfalse\tpid\tbad pid\n\
+                        1\tfalse\t-\n\
+                        1\tfalse\t-\tunknown \\x escape\n\
+                        1\tfalse\t-\tunfinished escape\\\n\
+                        2\tfalse\t42\talso kept\n";
+        assert_eq!(
+            Session::parse(contents),
+            Session::new(vec![
+                entry(None, "kept", 1, true),
+                entry(Some(42), "also kept", 2, false),
+            ])
+        );
+    }
+}
@@ -0,0 +1,71 @@
+use std::{
+    fs::File,
+    io::Read,
+    os::fd::FromRawFd,
+    sync::atomic::{AtomicI32, Ordering},
+};
+
+use libc::c_int;
+
+/// the write end of the self-pipe the signal handler reports signals through
+static PIPE_WRITE: AtomicI32 = AtomicI32::new(-1);
+
+extern "C" fn on_signal(signal: c_int) {
+    let fd = PIPE_WRITE.load(Ordering::Relaxed);
+    let byte = signal as u8;
+    // write(2) is async-signal-safe, nothing else should be done in here. it may set errno,
+    // which the code that was interrupted might be about to read
+    unsafe {
+        let errno = *libc::__errno_location();
+        libc::write(fd, &byte as *const u8 as *const libc::c_void, 1);
+        *libc::__errno_location() = errno;
+    }
+}
+
+/// installs a handler for each of `signals` and calls `callback` with every signal that arrives.
+/// the callback runs on its own thread, so it is not limited to async-signal-safe functions.
+/// this can only be called once.
+pub fn listen(
+    signals: &[c_int],
+    mut callback: impl FnMut(c_int) + Send + 'static,
+) -> std::io::Result<()> {
+    let mut fds = [0; 2];
+    if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    let [read_fd, write_fd] = fds;
+    if PIPE_WRITE
+        .compare_exchange(-1, write_fd, Ordering::SeqCst, Ordering::SeqCst)
+        .is_err()
+    {
+        unsafe {
+            libc::close(read_fd);
+            libc::close(write_fd);
+        }
+        return Err(std::io::Error::other(
+            "signal handlers are already installed",
+        ));
+    }
+
+    for &signal in signals {
+        unsafe {
+            let mut action: libc::sigaction = std::mem::zeroed();
+            action.sa_sigaction = on_signal as extern "C" fn(c_int) as libc::sighandler_t;
+            action.sa_flags = libc::SA_RESTART;
+            libc::sigemptyset(&mut action.sa_mask);
+            if libc::sigaction(signal, &action, std::ptr::null_mut()) != 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+        }
+    }
+
+    let mut pipe = unsafe { File::from_raw_fd(read_fd) };
+    std::thread::spawn(move || {
+        let mut signal = [0u8];
+        while pipe.read_exact(&mut signal).is_ok() {
+            callback(signal[0] as c_int);
+        }
+    });
+
+    Ok(())
+}
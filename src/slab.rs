@@ -41,9 +41,65 @@ impl<'a, T> Iterator for SlabIterMut<'a, T> {
     }
 }
 
+pub struct SlabIterIndexed<'a, T> {
+    entries: std::iter::Enumerate<Iter<'a, Option<T>>>,
+}
+
+impl<'a, T> Iterator for SlabIterIndexed<'a, T> {
+    type Item = (usize, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.entries.next() {
+                None => return None,
+                Some((idx, Some(v))) => return Some((idx, v)),
+                Some((_, None)) => (),
+            }
+        }
+    }
+}
+
+pub struct SlabIterIndexedMut<'a, T> {
+    entries: std::iter::Enumerate<IterMut<'a, Option<T>>>,
+}
+
+impl<'a, T> Iterator for SlabIterIndexedMut<'a, T> {
+    type Item = (usize, &'a mut T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.entries.next() {
+                None => return None,
+                Some((idx, Some(v))) => return Some((idx, v)),
+                Some((_, None)) => (),
+            }
+        }
+    }
+}
+
+pub struct SlabDrain<'a, T> {
+    entries: std::iter::Enumerate<std::vec::Drain<'a, Option<T>>>,
+}
+
+impl<'a, T> Iterator for SlabDrain<'a, T> {
+    type Item = (usize, T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.entries.next() {
+                None => return None,
+                Some((idx, Some(v))) => return Some((idx, v)),
+                Some((_, None)) => (),
+            }
+        }
+    }
+}
+
 pub struct Slab<T> {
     entries: Vec<Option<T>>,
     last_free: usize,
+    /// the amount of entries that are `Some`
+    len: usize,
 }
 
 impl<T: Debug> Debug for Slab<T> {
@@ -57,6 +113,7 @@ impl<T> Slab<T> {
         Self {
             entries: Vec::new(),
             last_free: 0,
+            len: 0,
         }
     }
 
@@ -64,10 +121,12 @@ impl<T> Slab<T> {
         Self {
             entries: Vec::with_capacity(capacity),
             last_free: 0,
+            len: 0,
         }
     }
 
     pub fn push(&mut self, value: T) -> usize {
+        self.len += 1;
         if self.last_free < self.entries.len() {
             self.entries[self.last_free] = Some(value);
             let idx = self.last_free;
@@ -76,7 +135,7 @@ impl<T> Slab<T> {
                 return idx;
             }
 
-            for i in self.last_free..=self.entries.len() {
+            for i in self.last_free..self.entries.len() {
                 if self.entries[i].is_none() {
                     self.last_free = i;
                     return idx;
@@ -94,6 +153,9 @@ impl<T> Slab<T> {
 
     pub fn remove(&mut self, index: usize) -> Option<T> {
         let value = self.entries[index].take();
+        if value.is_some() {
+            self.len -= 1;
+        }
         if self.last_free > index {
             self.last_free = index;
         }
@@ -112,8 +174,44 @@ impl<T> Slab<T> {
         }
     }
 
+    /// iterates over the entries together with their index
+    pub fn iter_indexed<'a>(&'a self) -> SlabIterIndexed<'a, T> {
+        SlabIterIndexed {
+            entries: self.entries.iter().enumerate(),
+        }
+    }
+
+    pub fn iter_indexed_mut<'a>(&'a mut self) -> SlabIterIndexedMut<'a, T> {
+        SlabIterIndexedMut {
+            entries: self.entries.iter_mut().enumerate(),
+        }
+    }
+
+    /// removes every entry for which `f` returns false. the remaining entries keep their index
+    pub fn retain(&mut self, mut f: impl FnMut(usize, &mut T) -> bool) {
+        for idx in 0..self.entries.len() {
+            let Some(value) = &mut self.entries[idx] else {
+                continue;
+            };
+            if !f(idx, value) {
+                self.remove(idx);
+            }
+        }
+    }
+
+    /// removes all entries, yielding them together with their index
+    pub fn drain<'a>(&'a mut self) -> SlabDrain<'a, T> {
+        self.len = 0;
+        self.last_free = 0;
+        SlabDrain {
+            entries: self.entries.drain(..).enumerate(),
+        }
+    }
+
     pub fn clear(&mut self) {
-        self.entries.clear()
+        self.entries.clear();
+        self.last_free = 0;
+        self.len = 0;
     }
 
     pub fn max_len(&self) -> usize {
@@ -129,15 +227,17 @@ impl<T> Slab<T> {
     }
 
     pub fn len(&self) -> usize {
-        let mut len = 0;
+        self.len
+    }
 
-        for i in 0..self.entries.len() {
-            if self.entries[i].is_some() {
-                len += 1;
-            }
-        }
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
 
-        len
+impl<T> Default for Slab<T> {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -182,3 +282,149 @@ impl<T> IndexMut<usize> for Slab<T> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::Slab;
+
+    #[test]
+    fn push_reuses_freed_slot() {
+        let mut slab = Slab::new();
+        for i in 0..5 {
+            assert_eq!(slab.push(i), i);
+        }
+
+        assert_eq!(slab.remove(2), Some(2));
+        assert_eq!(slab.push(10), 2);
+        assert_eq!(slab[2], 10);
+
+        // no free slots are left, so the slab has to grow
+        assert_eq!(slab.push(11), 5);
+        assert_eq!(slab.max_len(), 6);
+    }
+
+    #[test]
+    fn push_after_removing_last_entries() {
+        let mut slab = Slab::new();
+        for i in 0..4 {
+            slab.push(i);
+        }
+
+        slab.remove(1);
+        slab.remove(3);
+        assert_eq!(slab.push(10), 1);
+        assert_eq!(slab.push(11), 3);
+        assert_eq!(slab.push(12), 4);
+        assert_eq!(slab.iter().copied().collect::<Vec<_>>(), [0, 10, 2, 11, 12]);
+    }
+
+    #[test]
+    fn len_follows_push_and_remove() {
+        let mut slab = Slab::new();
+        assert_eq!(slab.len(), 0);
+        for i in 0..3 {
+            slab.push(i);
+        }
+        assert_eq!(slab.len(), 3);
+
+        slab.remove(1);
+        assert_eq!(slab.len(), 2);
+        // removing an empty slot doesn't change anything
+        assert_eq!(slab.remove(1), None);
+        assert_eq!(slab.len(), 2);
+
+        slab.push(3);
+        assert_eq!(slab.len(), 3);
+        assert_eq!(slab.max_len(), 3);
+    }
+
+    #[test]
+    fn len_after_clear() {
+        let mut slab = Slab::new();
+        slab.push(0);
+        slab.push(1);
+        slab.clear();
+        assert_eq!(slab.len(), 0);
+
+        slab.push(2);
+        assert_eq!(slab.len(), 1);
+        assert_eq!(slab.len(), slab.iter().count());
+    }
+
+    #[test]
+    fn push_after_clear_starts_at_zero() {
+        let mut slab = Slab::new();
+        for i in 0..4 {
+            slab.push(i);
+        }
+        slab.remove(3);
+
+        slab.clear();
+        assert!(slab.is_empty());
+        assert_eq!(slab.push(10), 0);
+        assert_eq!(slab.push(11), 1);
+        assert!(!slab.is_empty());
+    }
+
+    #[test]
+    fn retain_keeps_indices() {
+        let mut slab = Slab::new();
+        for i in 0..6 {
+            slab.push(i * 10);
+        }
+
+        slab.retain(|idx, _| idx % 2 == 0);
+        assert_eq!(slab.len(), 3);
+        assert_eq!(slab.max_len(), 6);
+        assert_eq!(slab[0], 0);
+        assert_eq!(slab[2], 20);
+        assert_eq!(slab[4], 40);
+        assert_eq!(slab.get(1), None);
+        assert_eq!(slab.get(3), None);
+
+        // the first slot that was freed is reused
+        assert_eq!(slab.push(50), 1);
+    }
+
+    #[test]
+    fn iter_indexed_yields_the_pushed_indices() {
+        let mut slab = Slab::new();
+        let indices = (0..5).map(|i| slab.push(i * 10)).collect::<Vec<_>>();
+        slab.remove(indices[1]);
+        slab.remove(indices[3]);
+        let reused = slab.push(50);
+
+        let mut expected = vec![
+            (indices[0], 0),
+            (indices[2], 20),
+            (indices[4], 40),
+            (reused, 50),
+        ];
+        expected.sort();
+        assert_eq!(
+            slab.iter_indexed()
+                .map(|(idx, &v)| (idx, v))
+                .collect::<Vec<_>>(),
+            expected
+        );
+
+        for (idx, v) in slab.iter_indexed_mut() {
+            *v = idx;
+        }
+        assert!(slab.iter_indexed().all(|(idx, &v)| idx == v));
+    }
+
+    #[test]
+    fn drain_empties_the_slab() {
+        let mut slab = Slab::new();
+        for i in 0..4 {
+            slab.push(i);
+        }
+        slab.remove(1);
+
+        assert_eq!(slab.drain().collect::<Vec<_>>(), [(0, 0), (2, 2), (3, 3)]);
+        assert_eq!(slab.len(), 0);
+        assert_eq!(slab.max_len(), 0);
+        assert_eq!(slab.push(4), 0);
+    }
+}
@@ -1,6 +1,22 @@
-use std::fmt::Display;
+use std::{fmt::Display, str::FromStr};
 
-use crate::{layout::Position, screen::Context};
+use crate::{config::Config, layout::Position, screen::Context};
+
+/// the space between tiled windows and around them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Gaps {
+    pub inner: u16,
+    pub outer: u16,
+}
+
+impl From<&Config> for Gaps {
+    fn from(config: &Config) -> Self {
+        Self {
+            inner: config.inner_gap,
+            outer: config.outer_gap,
+        }
+    }
+}
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Layout {
@@ -9,6 +25,9 @@ pub enum Layout {
     MasterRight,
     MasterLeftGrid,
     MasterRightGrid,
+    /// the master in a column in the middle, with the other windows split between a stack on
+    /// either side
+    CenterMaster,
     Monocle,
 }
 
@@ -20,180 +39,497 @@ impl Display for Layout {
             Self::MasterRight => "=[]",
             Self::MasterLeftGrid => "[]H",
             Self::MasterRightGrid => "H[]",
+            Self::CenterMaster => "|M|",
             Self::Monocle => "[M]",
         })
     }
 }
 
+/// returned when a string doesn't name a layout
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownLayout(pub String);
+
+impl Display for UnknownLayout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "unknown layout {:?}, expected one of ", self.0)?;
+        for (i, layout) in Layout::all().iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            f.write_str(layout.name())?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for UnknownLayout {}
+
+/// parses the name of a layout, ignoring case and dashes or underscores, so "master-left",
+/// "MasterLeft" and "master_left" are all the same layout
+impl FromStr for Layout {
+    type Err = UnknownLayout;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let normalize = |name: &str| {
+            name.chars()
+                .filter(|c| !matches!(c, '-' | '_'))
+                .map(|c| c.to_ascii_lowercase())
+                .collect::<String>()
+        };
+        let wanted = normalize(s);
+        Self::all()
+            .iter()
+            .copied()
+            .find(|layout| normalize(layout.name()) == wanted)
+            .ok_or_else(|| UnknownLayout(s.to_string()))
+    }
+}
+
 impl Layout {
-    /// ASSUMPTIONS: windows.len() >= 1
-    fn retile_grid(windows: &[usize], gap: u16, screen_position: Position, conn: &mut Context) {
-        let half_gap = gap / 2;
+    /// every layout, in the order they're cycled through
+    pub const fn all() -> &'static [Layout] {
+        &[
+            Self::Grid,
+            Self::MasterLeft,
+            Self::MasterRight,
+            Self::MasterLeftGrid,
+            Self::MasterRightGrid,
+            Self::CenterMaster,
+            Self::Monocle,
+        ]
+    }
 
-        let num_wins_horz = (windows.len() as f64).sqrt().ceil() as u16;
-        let num_wins_vert = windows.len().div_ceil(num_wins_horz as usize) as u16;
+    /// the name used for this layout in the config and over ipc
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Grid => "grid",
+            Self::MasterLeft => "master-left",
+            Self::MasterRight => "master-right",
+            Self::MasterLeftGrid => "master-left-grid",
+            Self::MasterRightGrid => "master-right-grid",
+            Self::CenterMaster => "center-master",
+            Self::Monocle => "monocle",
+        }
+    }
+
+    /// whether the last window is laid out differently from the rest
+    pub fn has_master(self) -> bool {
+        !matches!(self, Self::Grid | Self::Monocle)
+    }
+
+    /// whether the master column is on the left or on the right. None if the layout doesn't have
+    /// a single boundary between the master and the stack
+    pub fn master_is_left(self) -> Option<bool> {
+        match self {
+            Self::MasterLeft | Self::MasterLeftGrid => Some(true),
+            Self::MasterRight | Self::MasterRightGrid => Some(false),
+            Self::Grid | Self::CenterMaster | Self::Monocle => None,
+        }
+    }
+
+    /// ASSUMPTIONS: count >= 1
+    fn grid_tiles(count: usize, gap: u16, screen_position: Position) -> Vec<Position> {
+        let num_wins_horz = ((count as f64).sqrt().ceil() as u16).max(1);
+        let num_wins_vert = count.div_ceil(num_wins_horz as usize) as u16;
 
         let win_width = screen_position.width / num_wins_horz;
         let win_height = screen_position.height / num_wins_vert;
 
-        let offset_x = half_gap + screen_position.x;
-        let offset_y = half_gap + screen_position.y;
-
-        let len = windows.len();
-        for i in 0..windows.len() {
-            let x = (i as u16 % num_wins_horz) * win_width + offset_x;
-            let y = (i as u16 / num_wins_horz) * win_height + offset_y;
-
-            let i = len - 1 - i;
-            conn.windows[windows[i]].update(
-                win_width - gap,
-                win_height - gap,
-                x,
-                y,
-                &conn.connection,
-            );
-        }
+        // the first tile is the one in the top left, which belongs to the last window
+        let mut tiles = (0..count as u16)
+            .map(|i| {
+                tile(
+                    (i % num_wins_horz) * win_width + screen_position.x,
+                    (i / num_wins_horz) * win_height + screen_position.y,
+                    win_width,
+                    win_height,
+                    gap,
+                )
+            })
+            .collect::<Vec<_>>();
+        tiles.reverse();
+        tiles
     }
 
-    /// ASSUMPTIONS: windows.len() >= 1
-    fn retile_with_master(
-        windows: &[usize],
+    /// the windows on top of each other in `column`, the last window at the top
+    ///
+    /// ASSUMPTIONS: count >= 1
+    fn stack_tiles(count: usize, gap: u16, column: Position) -> Vec<Position> {
+        let height_gapless = column.height / count as u16;
+
+        let mut tiles = (0..count as u16)
+            .map(|i| {
+                tile(
+                    column.x,
+                    i * height_gapless + column.y,
+                    column.width,
+                    height_gapless,
+                    gap,
+                )
+            })
+            .collect::<Vec<_>>();
+        tiles.reverse();
+        tiles
+    }
+
+    /// ASSUMPTIONS: count >= 2
+    fn master_tiles(
+        count: usize,
         gap: u16,
         screen_position: Position,
+        master_width: u16,
         master_is_left: bool,
-        conn: &mut Context,
-    ) {
-        let half_gap = gap / 2;
-        let half_width = screen_position.width / 2;
-
-        // we do -1 because that later excludes the last element and is the last element
-        let len = windows.len() - 1;
-        conn.windows[windows[len]].update(
-            half_width - gap,
-            screen_position.height - gap,
-            if master_is_left {
-                half_gap
-            } else {
-                half_width + half_gap
-            } + screen_position.x,
-            half_gap + screen_position.y,
-            &conn.connection,
+        stack_is_grid: bool,
+    ) -> Vec<Position> {
+        let stack_width = screen_position.width - master_width;
+        let (master_x, stack_x) = if master_is_left {
+            (0, master_width)
+        } else {
+            (stack_width, 0)
+        };
+        let column = Position::new(
+            stack_x + screen_position.x,
+            screen_position.y,
+            stack_width,
+            screen_position.height,
         );
 
-        let width = half_width - gap;
-        let height_gapless = screen_position.height / len as u16;
-        let height = height_gapless - gap;
-        let x = if master_is_left {
-            half_width + half_gap
+        // the last window is the master
+        let mut tiles = if stack_is_grid {
+            Self::grid_tiles(count - 1, gap, column)
         } else {
-            half_gap
-        } + screen_position.x;
-
-        for i in 0..len {
-            conn.windows[windows[len - 1 - i]].update(
-                width,
-                height,
-                x,
-                i as u16 * height_gapless + half_gap + screen_position.y,
-                &conn.connection,
-            );
-        }
+            Self::stack_tiles(count - 1, gap, column)
+        };
+        tiles.push(tile(
+            master_x + screen_position.x,
+            screen_position.y,
+            master_width,
+            screen_position.height,
+            gap,
+        ));
+        tiles
     }
 
-    /// ASSUMPTIONS: windows.len() >= 1
-    fn retile_with_master_grid(
-        windows: &[usize],
+    /// ASSUMPTIONS: count >= 2
+    fn center_master_tiles(
+        count: usize,
         gap: u16,
         screen_position: Position,
-        master_is_left: bool,
-        conn: &mut Context,
-    ) {
-        let half_gap = gap / 2;
-        let half_width = screen_position.width / 2;
-
-        // we do -1 because that later excludes the last element and is the last element
-        let len = windows.len() - 1;
-        conn.windows[windows[len]].update(
-            half_width - gap,
-            screen_position.height - gap,
-            if master_is_left {
-                half_gap
-            } else {
-                half_width + half_gap
-            } + screen_position.x,
-            half_gap + screen_position.y,
-            &conn.connection,
+        master_width: u16,
+    ) -> Vec<Position> {
+        let len = count - 1;
+        // a single stack window can't be split, so it gets the entire other side
+        if len == 1 {
+            return Self::master_tiles(count, gap, screen_position, master_width, true, false);
+        }
+
+        let left_width = (screen_position.width - master_width) / 2;
+        let right_width = screen_position.width - master_width - left_width;
+
+        // the top half of the stack goes to the left, the left column gets the extra window
+        let right_len = len / 2;
+        let mut tiles = Self::stack_tiles(
+            right_len,
+            gap,
+            Position::new(
+                screen_position.x + left_width + master_width,
+                screen_position.y,
+                right_width,
+                screen_position.height,
+            ),
         );
+        tiles.extend(Self::stack_tiles(
+            len - right_len,
+            gap,
+            Position::new(
+                screen_position.x,
+                screen_position.y,
+                left_width,
+                screen_position.height,
+            ),
+        ));
+        tiles.push(tile(
+            screen_position.x + left_width,
+            screen_position.y,
+            master_width,
+            screen_position.height,
+            gap,
+        ));
+        tiles
+    }
 
-        if master_is_left {
-            Self::retile_grid(
-                &windows[0..len],
-                gap,
-                Position::new(
-                    half_width + screen_position.x,
-                    screen_position.y,
-                    half_width,
-                    screen_position.height,
-                ),
-                conn,
-            );
-        } else {
-            Self::retile_grid(
-                &windows[0..len],
-                gap,
-                Position::new(
-                    screen_position.x,
-                    screen_position.y,
-                    half_width,
-                    screen_position.height,
-                ),
-                conn,
-            );
+    /// ASSUMPTIONS: count >= 1
+    /// every window covers the workspace. the one that is raised hides the others
+    fn monocle_tiles(count: usize, gap: u16, screen_position: Position) -> Vec<Position> {
+        let tile = tile(
+            screen_position.x,
+            screen_position.y,
+            screen_position.width,
+            screen_position.height,
+            gap,
+        );
+        vec![tile; count]
+    }
+
+    /// where each of `count` windows goes, in the order of the windows. `master_width` has to fit
+    /// into `pos`
+    fn tiles(self, count: usize, gap: u16, pos: Position, master_width: u16) -> Vec<Position> {
+        match count {
+            0 => vec![],
+            // the window is always gonna be the entire window
+            1 => vec![tile(pos.x, pos.y, pos.width, pos.height, gap)],
+            _ => match self {
+                Self::Grid => Self::grid_tiles(count, gap, pos),
+                Self::MasterLeft => Self::master_tiles(count, gap, pos, master_width, true, false),
+                Self::MasterRight => {
+                    Self::master_tiles(count, gap, pos, master_width, false, false)
+                }
+                Self::MasterLeftGrid => {
+                    Self::master_tiles(count, gap, pos, master_width, true, true)
+                }
+                Self::MasterRightGrid => {
+                    Self::master_tiles(count, gap, pos, master_width, false, true)
+                }
+                Self::CenterMaster => Self::center_master_tiles(count, gap, pos, master_width),
+                Self::Monocle => Self::monocle_tiles(count, gap, pos),
+            },
         }
     }
 
-    fn retile_monocle(windows: &[usize], gap: u16, screen_position: Position, conn: &mut Context) {
-        let len = windows.len() - 1;
+    /// where each of the windows goes, without moving them. `master_width` is the width of the
+    /// master column of the master layouts. it has to fit into `pos`. with `smart_gaps`, a single
+    /// window fills `pos` without a gap
+    pub fn arrange(
+        self,
+        windows: &[usize],
+        gaps: Gaps,
+        pos: Position,
+        master_width: u16,
+        smart_gaps: bool,
+    ) -> Vec<(usize, Position)> {
+        if windows.is_empty() {
+            return vec![];
+        } else if is_borderless(windows.len(), smart_gaps) {
+            return vec![(windows[0], pos)];
+        }
+
+        let (pos, inset) = tile_area(pos, gaps);
+        let master_width = (master_width as i32 - inset).clamp(0, pos.width as i32) as u16;
 
-        let x = screen_position.x + gap / 2;
-        let y = screen_position.y + gap / 2;
+        let tiles = self.tiles(windows.len(), gaps.inner, pos, master_width);
+        windows.iter().copied().zip(tiles).collect()
+    }
 
-        for window in windows[..len].iter().copied() {
-            conn.windows[window].update(30, 30, x, y, &conn.connection);
+    /// moves the windows where `arrange` puts them. with `smart_gaps`, a single window doesn't
+    /// have a border either
+    pub fn retile(
+        self,
+        windows: &[usize],
+        gaps: Gaps,
+        pos: Position,
+        master_width: u16,
+        smart_gaps: bool,
+        ctx: &mut Context,
+    ) {
+        let borderless = is_borderless(windows.len(), smart_gaps);
+        for (window, tile) in self.arrange(windows, gaps, pos, master_width, smart_gaps) {
+            let client = &mut ctx.windows[window];
+            client.borderless = borderless;
+            client.update(tile.width, tile.height, tile.x, tile.y, &ctx.connection);
         }
+    }
+}
 
-        conn.windows[windows[len]].update(
-            screen_position.width - gap,
-            screen_position.height - gap,
-            x,
-            y,
-            &conn.connection,
-        );
+/// whether smart gaps let the windows fill the area without gaps and borders
+fn is_borderless(count: usize, smart_gaps: bool) -> bool {
+    count == 1 && smart_gaps
+}
+
+/// the area of `pos` the tiles are laid out in and how far it is inset. every tile keeps half the
+/// inner gap to each side, which already counts towards the outer gap
+fn tile_area(pos: Position, gaps: Gaps) -> (Position, i32) {
+    let inset = gaps.outer as i32 - (gaps.inner / 2) as i32;
+    // the area can't start left of or above the screen, so it has to shrink there instead
+    let (left, top) = ((pos.x as i32 + inset).max(0), (pos.y as i32 + inset).max(0));
+    let right = pos.x as i32 + pos.width as i32 - inset;
+    let bottom = pos.y as i32 + pos.height as i32 - inset;
+    let area = Position::new(
+        left as u16,
+        top as u16,
+        (right - left).clamp(0, u16::MAX as i32) as u16,
+        (bottom - top).clamp(0, u16::MAX as i32) as u16,
+    );
+    (area, inset)
+}
+
+/// a tile in the cell at `x` and `y`, keeping half the gap to each side. it is at least one pixel
+/// big, even if the gap is bigger than the cell
+fn tile(x: u16, y: u16, width: u16, height: u16, gap: u16) -> Position {
+    Position::new(
+        x + gap / 2,
+        y + gap / 2,
+        width.saturating_sub(gap).max(1),
+        height.saturating_sub(gap).max(1),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_window_gets_a_tile_on_a_tiny_screen() {
+        let pos = Position::new(0, 0, 100, 100);
+        for &layout in Layout::all() {
+            for count in 1..=6 {
+                for gap in [0, 60, 200] {
+                    let tiles = layout.tiles(count, gap, pos, 50);
+                    assert_eq!(tiles.len(), count, "{layout:?} with {count} windows");
+                    for tile in tiles {
+                        assert!(tile.width > 0 && tile.height > 0, "{layout:?}: {tile:?}");
+                    }
+                }
+            }
+        }
     }
 
-    pub fn retile(self, windows: &[usize], gap: u16, pos: Position, ctx: &mut Context) {
-        if windows.len() < 1 {
-            return;
-        } else if windows.len() == 1 {
-            // the window is always gonna be the entire window
-            ctx.windows[windows[0]].update(
-                pos.width - gap,
-                pos.height - gap,
-                gap / 2 + pos.x,
-                gap / 2 + pos.y,
-                &ctx.connection,
-            );
+    #[test]
+    fn more_windows_than_pixels() {
+        let pos = Position::new(0, 0, 2, 2);
+        for &layout in Layout::all() {
+            let tiles = layout.tiles(6, 4, pos, 1);
+            assert!(tiles.iter().all(|tile| tile.width > 0 && tile.height > 0));
+        }
+    }
+
+    #[test]
+    fn master_is_the_last_window() {
+        let tiles = Layout::MasterLeft.tiles(3, 0, Position::new(0, 0, 200, 100), 120);
+        assert_eq!(tiles[2], Position::new(0, 0, 120, 100));
+        // the stack starts at the top with the window before the master
+        assert_eq!(tiles[1], Position::new(120, 0, 80, 50));
+        assert_eq!(tiles[0], Position::new(120, 50, 80, 50));
+    }
 
-            return;
+    #[test]
+    fn monocle_stays_below_a_top_strut() {
+        // the area of an 800x600 screen that is left by a 24 pixel bar at the top
+        let pos = Position::new(0, 24, 800, 576);
+        for gaps in [
+            Gaps { inner: 0, outer: 0 },
+            Gaps {
+                inner: 10,
+                outer: 0,
+            },
+            Gaps {
+                inner: 0,
+                outer: 10,
+            },
+            Gaps {
+                inner: 10,
+                outer: 4,
+            },
+        ] {
+            let (area, _) = tile_area(pos, gaps);
+            for count in 1..=4 {
+                for tile in Layout::Monocle.tiles(count, gaps.inner, area, area.width / 2) {
+                    assert!(tile.y >= 24, "{gaps:?}: {tile:?}");
+                    assert!(tile.y + tile.height <= 600, "{gaps:?}: {tile:?}");
+                    assert!(tile.x + tile.width <= 800, "{gaps:?}: {tile:?}");
+                }
+            }
         }
+    }
 
-        match self {
-            Self::Grid => Self::retile_grid(&windows, gap, pos, ctx),
-            Self::MasterLeft => Self::retile_with_master(&windows, gap, pos, true, ctx),
-            Self::MasterRight => Self::retile_with_master(&windows, gap, pos, false, ctx),
-            Self::MasterLeftGrid => Self::retile_with_master_grid(&windows, gap, pos, true, ctx),
-            Self::MasterRightGrid => Self::retile_with_master_grid(&windows, gap, pos, false, ctx),
-            Self::Monocle => Self::retile_monocle(&windows, gap, pos, ctx),
+    #[test]
+    fn layouts_parse_back_from_their_names() {
+        for &layout in Layout::all() {
+            assert_eq!(layout.name().parse(), Ok(layout));
+        }
+    }
+
+    #[test]
+    fn layout_names_ignore_case_and_separators() {
+        assert_eq!("MasterLeft".parse(), Ok(Layout::MasterLeft));
+        assert_eq!("masterleft".parse(), Ok(Layout::MasterLeft));
+        assert_eq!("CENTER_MASTER".parse(), Ok(Layout::CenterMaster));
+        assert_eq!("Grid".parse(), Ok(Layout::Grid));
+    }
+
+    #[test]
+    fn unknown_layouts_are_rejected() {
+        let err = "spiral".parse::<Layout>().unwrap_err();
+        assert_eq!(err, UnknownLayout("spiral".to_string()));
+        assert!(err.to_string().contains("\"spiral\""));
+        assert!(err.to_string().contains("master-left"));
+    }
+
+    const NO_GAPS: Gaps = Gaps { inner: 0, outer: 0 };
+
+    #[test]
+    fn grid_fills_the_rows_from_the_top_left_with_the_last_window() {
+        let pos = Position::new(0, 0, 200, 100);
+        assert_eq!(
+            Layout::Grid.arrange(&[1, 2, 3, 4], NO_GAPS, pos, 100, false),
+            [
+                (1, Position::new(100, 50, 100, 50)),
+                (2, Position::new(0, 50, 100, 50)),
+                (3, Position::new(100, 0, 100, 50)),
+                (4, Position::new(0, 0, 100, 50)),
+            ]
+        );
+    }
+
+    #[test]
+    fn master_and_stack_keep_the_gaps_between_and_around_them() {
+        let gaps = Gaps {
+            inner: 10,
+            outer: 10,
+        };
+        let pos = Position::new(0, 0, 200, 100);
+        assert_eq!(
+            Layout::MasterLeft.arrange(&[7, 8], gaps, pos, 100, false),
+            [
+                (7, Position::new(105, 10, 85, 80)),
+                (8, Position::new(10, 10, 85, 80)),
+            ]
+        );
+        assert_eq!(
+            Layout::MasterRight.arrange(&[7, 8], gaps, pos, 100, false),
+            [
+                (7, Position::new(10, 10, 85, 80)),
+                (8, Position::new(105, 10, 85, 80)),
+            ]
+        );
+    }
+
+    #[test]
+    fn center_master_splits_the_stack_to_both_sides() {
+        let pos = Position::new(0, 0, 300, 100);
+        assert_eq!(
+            Layout::CenterMaster.arrange(&[1, 2, 3], NO_GAPS, pos, 100, false),
+            [
+                (1, Position::new(200, 0, 100, 100)),
+                (2, Position::new(0, 0, 100, 100)),
+                (3, Position::new(100, 0, 100, 100)),
+            ]
+        );
+    }
+
+    #[test]
+    fn smart_gaps_give_a_lone_window_the_whole_area() {
+        let gaps = Gaps {
+            inner: 10,
+            outer: 10,
+        };
+        let pos = Position::new(0, 24, 800, 576);
+        for &layout in Layout::all() {
+            assert_eq!(layout.arrange(&[5], gaps, pos, 400, true), [(5, pos)]);
+            assert_eq!(
+                layout.arrange(&[5], gaps, pos, 400, false),
+                [(5, Position::new(10, 34, 780, 556))]
+            );
         }
+        assert_eq!(Layout::Grid.arrange(&[], gaps, pos, 400, true), []);
     }
 }
@@ -1,55 +1,131 @@
 use std::{
-    process::{Command, Stdio},
+    os::unix::process::CommandExt,
+    process::{Child, Command, Stdio},
     sync::{mpsc::RecvTimeoutError, Arc},
     time::{Duration, Instant},
 };
 
 use anyhow::{Context, Result};
-use tracing::error;
+use tracing::{debug, error, info, trace};
 use xcb::{
+    randr,
     x::{
-        ChangeWindowAttributes, CreateGlyphCursor, Cw, DestroyWindow, Drawable, Event as XEvent,
-        EventMask, GetGeometry, OpenFont, Window,
+        Allow, AllowEvents, ButtonIndex, ChangeWindowAttributes, ClientMessageData,
+        ConfigWindowMask, CreateGlyphCursor, CreateWindow, Cursor, Cw, DeleteProperty,
+        DestroyWindow, Drawable, Error as XError, Event as XEvent, EventMask, GetGeometry,
+        GetWindowAttributes, GrabButton, GrabKeyboard, GrabMode, GrabStatus, KeyButMask, MapState,
+        ModMask as XModMask, OpenFont, QueryTree, UngrabButton, UngrabKeyboard, Window,
+        WindowClass, COPY_FROM_PARENT, CURRENT_TIME,
     },
-    Connection, Event as XcbEvent, Xid,
+    Connection, Event as XcbEvent, ProtocolError, Xid,
 };
 
 use crate::{
-    actions::{Action, ActionType},
+    actions::{self, Action, ActionType, MouseAction},
     atoms::Atoms,
-    events::{Event, MouseButton},
-    keyboard::Keyboard,
+    config::{self, Config},
+    events::{ConfigureRequest, Event, MouseButton},
+    ewmh, ipc,
+    keyboard::{resolve_mod, Keyboard, ModifierMap},
+    layout::Position,
     screen::Screen,
+    session::Session,
+    signals,
 };
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DragKind {
+    Move,
+    Resize,
+}
+
+/// a floating window that is currently being dragged with the mouse
+struct DragState {
+    window_idx: usize,
+    kind: DragKind,
+    /// the pointer position when the drag started
+    pointer_x: i16,
+    pointer_y: i16,
+    /// the window geometry when the drag started
+    start: Position,
+}
+
+/// a mouse button that is grabbed on the root for an action
+#[derive(Debug)]
+struct BoundMouseAction {
+    button: MouseButton,
+    modifiers: XModMask,
+    action_index: usize,
+}
+
+/// a key sequence that was started but isn't finished yet
+struct ChordState {
+    /// the indices of the bound actions whose sequence matches the keys pressed so far
+    candidates: Vec<usize>,
+    /// how many keys of the sequences were pressed, not counting the first one
+    progress: usize,
+    last_key: Instant,
+}
+
+/// everything that can wake up the main loop
+enum LoopEvent {
+    X(XcbEvent),
+    /// an event that didn't come from the X server
+    Internal(Event),
+    /// the connection to the X server is gone
+    ConnectionLost(xcb::ConnError),
+}
+
 pub struct Wm {
     conn: Arc<Connection>,
     screen: Screen,
     atoms: Atoms,
     keyboard: Keyboard,
     root: Window,
+    drag: Option<DragState>,
+    /// the boundary between the master and the stack is being dragged
+    dragging_master_edge: bool,
+    /// the window the pointer entered last
+    hovered_window: Window,
+    /// the main loop was left to restart the window manager
+    restart: bool,
+    /// the main loop was left through the quit action, rather than by losing the event threads
+    quit: bool,
+    /// the window _NET_SUPPORTING_WM_CHECK points at
+    check_window: Window,
 }
 
 impl Wm {
-    pub fn new() -> Result<Self> {
-        let (conn, _) = xcb::Connection::connect(None)
-            .context("Failed to connect to the X Server. Is $DISPLAY correct?")?;
+    pub fn new(config: Config) -> Result<Self> {
+        // the events of an extension can only be told apart if it is listed here
+        let (conn, screen_num) = xcb::Connection::connect_with_extensions(
+            None,
+            &[xcb::Extension::Xkb],
+            &[xcb::Extension::RandR],
+        )
+        .context("Failed to connect to the X Server. Is $DISPLAY correct?")?;
         let conn = Arc::new(conn);
+        // $DISPLAY picks the screen unless $WM_SCREEN says otherwise
+        let screen_num = match config::get_screen_index()? {
+            Some(index) => index,
+            None => screen_num.max(0) as usize,
+        };
 
-        let (root, root_depth) = Self::setup(&conn)?;
         let atoms = Atoms::get(&conn);
+        let (root, root_depth, check_window) = Self::setup(&conn, &atoms, screen_num)?;
 
         let root_dimensions = request_sync!(conn => GetGeometry { drawable: Drawable::Window(root) }; "failed to get the initial window size");
 
-        println!(
-            "Root Window: {}x{}",
-            root_dimensions.width(),
-            root_dimensions.height()
-        );
-        println!(
-            "Root Border Width: {} | Depth: {}",
-            root_dimensions.border_width(),
-            root_dimensions.depth()
+        info!(
+            display = std::env::var("DISPLAY").unwrap_or_default(),
+            screen = screen_num,
+            root = root.resource_id(),
+            width = root_dimensions.width(),
+            height = root_dimensions.height(),
+            border_width = root_dimensions.border_width(),
+            depth = root_dimensions.depth(),
+            atoms = atoms.list().len(),
+            "connected to the X server"
         );
         assert_eq!(root_dimensions.x(), 0, "x of rootwindow != 0");
         assert_eq!(root_dimensions.y(), 0, "y of rootwindow != 0");
@@ -59,7 +135,7 @@ impl Wm {
         let screen = Screen::new(
             root_dimensions.width(),
             root_dimensions.height(),
-            0,
+            config,
             atoms,
             root,
             conn.clone(),
@@ -67,19 +143,53 @@ impl Wm {
         )
         .context("Failed to initialise the screen")?;
 
-        Ok(Self {
+        let mut wm = Self {
             conn,
             screen,
             atoms,
             keyboard,
             root,
-        })
+            drag: None,
+            dragging_master_edge: false,
+            hovered_window: root,
+            restart: false,
+            quit: false,
+            check_window,
+        };
+        wm.adopt_windows()
+            .context("Failed to adopt the existing windows")?;
+        Ok(wm)
+    }
+
+    /// manages the windows that are already mapped, e.g. the ones a previous instance left behind
+    /// when it restarted
+    fn adopt_windows(&mut self) -> Result<()> {
+        match Session::load() {
+            Ok(session) => self.screen.start_restore(session),
+            Err(e) => error!("failed to load the session: {e:?}"),
+        }
+        let tree = request_sync!(self.conn => QueryTree { window: self.root });
+        for &window in tree.children() {
+            let attributes = request_sync!(self.conn => GetWindowAttributes { window });
+            // override-redirect windows like the bar are skipped by the screen
+            if attributes.map_state() != MapState::Viewable {
+                continue;
+            }
+            trace_result!(self.screen.adopt_window(window); "failed to adopt a window");
+        }
+        self.screen.finish_restore();
+        Ok(())
     }
 
-    // returns the root window and the depth
-    fn setup(conn: &Connection) -> Result<(Window, u8)> {
+    // returns the root window, the depth and the supporting wm check window of the screen
+    fn setup(conn: &Connection, atoms: &Atoms, screen_num: usize) -> Result<(Window, u8, Window)> {
         let setup = conn.get_setup();
-        let screen = setup.roots().next().context("Failed to get a screen")?;
+        let Some(screen) = setup.roots().nth(screen_num) else {
+            anyhow::bail!(
+                "there is no screen {screen_num}, the display has {} screen(s)",
+                setup.roots().count()
+            );
+        };
         let window = screen.root();
 
         let font = conn.generate_id();
@@ -105,7 +215,7 @@ impl Wm {
         })
         .context("Failed to a new create cursor")?;
 
-        conn.send_and_check_request(&ChangeWindowAttributes {
+        let acquired = conn.send_and_check_request(&ChangeWindowAttributes {
             window,
             value_list: &[
                 Cw::EventMask(
@@ -121,81 +231,506 @@ impl Wm {
                 ),
                 Cw::Cursor(cursor),
             ],
-        })
-        .context("Failed to acquire root window")?;
+        });
+        // only one client can select SUBSTRUCTURE_REDIRECT on the root
+        if let Err(ProtocolError::X(XError::Access(_), _)) = acquired {
+            anyhow::bail!("another window manager is already running");
+        }
+        acquired.context("Failed to acquire root window")?;
+
+        if conn
+            .active_extensions()
+            .any(|ext| ext == xcb::Extension::RandR)
+        {
+            trace_result!(conn.send_and_check_request(&randr::SelectInput {
+                window,
+                enable: randr::NotifyMask::SCREEN_CHANGE,
+            }); "failed to listen for screen changes");
+        }
+
+        let check_window = Self::create_check_window(conn, window, atoms)
+            .context("Failed to create the supporting wm check window")?;
+
+        Ok((window, screen.root_depth(), check_window))
+    }
+
+    /// creates the child window that tells other clients which window manager is running
+    fn create_check_window(conn: &Connection, root: Window, atoms: &Atoms) -> Result<Window> {
+        let check_window = conn.generate_id();
+        conn.send_and_check_request(&CreateWindow {
+            depth: 0,
+            wid: check_window,
+            parent: root,
+            x: -1,
+            y: -1,
+            width: 1,
+            height: 1,
+            border_width: 0,
+            class: WindowClass::InputOnly,
+            visual: COPY_FROM_PARENT,
+            value_list: &[Cw::OverrideRedirect(true)],
+        })?;
+        ewmh::set_supporting_wm_check(check_window, check_window, atoms, conn)?;
+        ewmh::set_wm_name(check_window, "bespokewm", atoms, conn)?;
+        ewmh::set_supporting_wm_check(root, check_window, atoms, conn)?;
+        Ok(check_window)
+    }
 
-        Ok((window, screen.root_depth()))
+    fn destroy_check_window(&self) {
+        trace_result!(self.conn.send_and_check_request(&DeleteProperty {
+            window: self.root,
+            property: self.atoms.net_supporting_wm_check,
+        }); "failed to remove _NET_SUPPORTING_WM_CHECK");
+        trace_result!(self.conn.send_and_check_request(&DestroyWindow {
+            window: self.check_window,
+        }); "failed to destroy the supporting wm check window");
+    }
+
+    fn grab_buttons(&self) {
+        let cookies = [ButtonIndex::N1, ButtonIndex::N3].map(|button| {
+            self.conn.send_request_checked(&GrabButton {
+                owner_events: false,
+                grab_window: self.root,
+                event_mask: EventMask::BUTTON_PRESS
+                    | EventMask::BUTTON_RELEASE
+                    | EventMask::BUTTON_MOTION,
+                pointer_mode: GrabMode::Async,
+                keyboard_mode: GrabMode::Async,
+                confine_to: Window::none(),
+                cursor: Cursor::none(),
+                button,
+                modifiers: self.keyboard.modifiers().to_x_mods(config::DRAG_MODS),
+            })
+        });
+        for cookie in cookies {
+            trace_result!(self.conn.check_request(cookie); "failed to grab the mouse buttons");
+        }
+    }
+
+    fn ungrab_buttons(&self) {
+        let cookies = [ButtonIndex::N1, ButtonIndex::N3].map(|button| {
+            self.conn.send_request_checked(&UngrabButton {
+                button,
+                grab_window: self.root,
+                modifiers: self.keyboard.modifiers().to_x_mods(config::DRAG_MODS),
+            })
+        });
+        for cookie in cookies {
+            trace_result!(self.conn.check_request(cookie); "failed to ungrab the mouse buttons");
+        }
+    }
+
+    fn bind_mouse_actions(&self, actions: &[MouseAction]) -> Vec<BoundMouseAction> {
+        let mut bound = vec![];
+        for (i, action) in actions.iter().enumerate() {
+            let modifiers = self
+                .keyboard
+                .modifiers()
+                .to_x_mods(resolve_mod(action.mods, config::MOD_KEY));
+            let grabbed = self
+                .keyboard
+                .modifiers()
+                .lock_combinations(modifiers)
+                .into_iter()
+                .all(|modifiers| {
+                    set_button_grab(&self.conn, self.root, action.button as u8, modifiers, true)
+                });
+            if !grabbed {
+                error!("Failed to bind mouse action #{i} ({action:?})");
+                continue;
+            }
+            bound.push(BoundMouseAction {
+                button: action.button,
+                modifiers,
+                action_index: i,
+            });
+        }
+        bound
+    }
+
+    fn unbind_mouse_actions(&self, bound: &[BoundMouseAction]) {
+        for action in bound {
+            for modifiers in self
+                .keyboard
+                .modifiers()
+                .lock_combinations(action.modifiers)
+            {
+                set_button_grab(&self.conn, self.root, action.button as u8, modifiers, false);
+            }
+        }
+    }
+
+    fn start_drag(
+        &mut self,
+        kind: DragKind,
+        window: Option<Window>,
+        absolute_x: i16,
+        absolute_y: i16,
+    ) {
+        // tiled windows are torn out of the layout when they're dragged around
+        if let (DragKind::Move, Some(window)) = (&kind, window) {
+            self.screen.float_client(window);
+        }
+        let Some(window_idx) = window.and_then(|window| self.screen.floating_client(window)) else {
+            return;
+        };
+
+        self.drag = Some(DragState {
+            window_idx,
+            kind,
+            pointer_x: absolute_x,
+            pointer_y: absolute_y,
+            start: self.screen.client_position(window_idx),
+        });
+    }
+
+    fn update_drag(&mut self, absolute_x: i16, absolute_y: i16) {
+        if self.dragging_master_edge {
+            self.screen.set_master_edge(absolute_x);
+            return;
+        }
+        let Some(drag) = &self.drag else {
+            return;
+        };
+        let delta_x = (absolute_x - drag.pointer_x) as i32;
+        let delta_y = (absolute_y - drag.pointer_y) as i32;
+
+        match drag.kind {
+            DragKind::Move => self.screen.move_client(
+                drag.window_idx,
+                (drag.start.x as i32 + delta_x).max(0) as u16,
+                (drag.start.y as i32 + delta_y).max(0) as u16,
+            ),
+            DragKind::Resize => {
+                // the smallest size at which the window still has content after the borders
+                let border_size = self.screen.config().border_size;
+                let min_size = config::MIN_WINDOW_SIZE as i32 + border_size as i32 * 2;
+                self.screen.resize_client(
+                    drag.window_idx,
+                    (drag.start.width as i32 + delta_x).clamp(min_size, u16::MAX as i32) as u16,
+                    (drag.start.height as i32 + delta_y).clamp(min_size, u16::MAX as i32) as u16,
+                )
+            }
+        }
+    }
+
+    /// runs the action of a keybinding. returns true if the window manager should quit
+    fn run_action(&mut self, action: &ActionType, procs: &mut Vec<Child>) -> bool {
+        match action {
+            ActionType::Quit => {
+                self.quit = true;
+                return true;
+            }
+            ActionType::Restart => {
+                self.restart = true;
+                return true;
+            }
+            ActionType::CycleLayout => self.screen.cycle_layout(),
+            ActionType::CloseFocusedWindow => self.screen.close_focused_window(),
+            ActionType::KillFocused => self.screen.kill_focused_window(),
+            ActionType::SwitchToLayout(new_layout) => self.screen.set_layout(*new_layout),
+            ActionType::ToggleFloating => self.screen.toggle_floating(),
+            ActionType::FloatAll => self.screen.float_all(),
+            ActionType::TileAll => self.screen.tile_all(),
+            ActionType::ToggleSticky => self.screen.toggle_sticky(),
+            ActionType::ToggleAbove => self.screen.toggle_above(),
+            ActionType::ToggleSmartGaps => self.screen.toggle_smart_gaps(),
+            ActionType::ToggleGaps => self.screen.toggle_gaps(),
+            ActionType::ToggleStruts => self.screen.toggle_struts(),
+            ActionType::SwapFocusedWithMasterOrStack => self.screen.swap_master(),
+            ActionType::RenameWorkspace(name) => {
+                trace_result!(self.screen.rename_workspace(name); "failed to rename the workspace")
+            }
+            ActionType::SwapWorkspaces(a, b) => {
+                trace_result!(self.screen.swap_workspaces(*a, *b); "failed to swap the workspaces")
+            }
+            ActionType::ToggleMonocleForFocused => self.screen.toggle_expanded(),
+            ActionType::NextWorkspace => {
+                trace_result!(self.screen.cycle_workspace(1); "failed to switch the workspace")
+            }
+            ActionType::PrevWorkspace => {
+                trace_result!(self.screen.cycle_workspace(-1); "failed to switch the workspace")
+            }
+            ActionType::LastWorkspace => {
+                trace_result!(self.screen.switch_to_last_workspace(); "failed to switch the workspace")
+            }
+            ActionType::MoveToWorkspaceRelative { delta, follow } => {
+                trace_result!(self.screen.move_focused_relative(*delta, *follow); "failed to move the window to another workspace")
+            }
+            ActionType::FocusUrgent => {
+                trace_result!(self.screen.focus_urgent(); "failed to focus the urgent window")
+            }
+            ActionType::CycleWindowMru => {
+                let started = !self.screen.cycling_mru();
+                self.screen.cycle_mru();
+                // the cycle ends when the modifier is released, which we only see with the
+                // keyboard grabbed
+                if started && !self.grab_keyboard() {
+                    self.screen.end_mru_cycle();
+                }
+            }
+            ActionType::ResizeMaster(delta) => self.screen.resize_master(*delta),
+            ActionType::BalanceLayout => self.screen.balance_layout(),
+            ActionType::InnerGap(delta) => self.screen.adjust_gaps(*delta, 0),
+            ActionType::OuterGap(delta) => self.screen.adjust_gaps(0, *delta),
+            ActionType::Minimize => self.screen.minimize(),
+            ActionType::RestoreLast => self.screen.restore_last(),
+            ActionType::FocusDir(direction) => self.screen.focus_direction(*direction),
+            ActionType::MoveDir(direction) => self.screen.move_direction(*direction),
+            ActionType::RotateStack(direction) => self.screen.rotate_stack(*direction),
+            ActionType::ToggleScratchpad => {
+                if !self.screen.toggle_scratchpad() {
+                    if let Some((program, args)) = config::SCRATCHPAD_COMMAND.split_first() {
+                        let mut command = Command::new(program);
+                        command.args(args);
+                        spawn(command, procs);
+                    }
+                }
+            }
+            ActionType::ShowScratchpad => {
+                self.screen.show_existing_scratchpad();
+            }
+            ActionType::MoveToScratchpad => {
+                trace_result!(self.screen.move_to_scratchpad(); "failed to move the window to the scratchpad")
+            }
+            ActionType::Launch(cmd) => {
+                let mut parts = cmd.split_whitespace();
+                if let Some(program) = parts.next() {
+                    let mut command = Command::new(program);
+                    command.args(parts);
+                    spawn(command, procs);
+                }
+            }
+        }
+        false
+    }
+
+    /// redirects all key presses to the window manager, to read the rest of a key sequence
+    fn grab_keyboard(&self) -> bool {
+        let reply = self
+            .conn
+            .wait_for_reply(self.conn.send_request(&GrabKeyboard {
+                owner_events: false,
+                grab_window: self.root,
+                time: CURRENT_TIME,
+                pointer_mode: GrabMode::Async,
+                keyboard_mode: GrabMode::Async,
+            }));
+        match reply {
+            Ok(reply) if reply.status() == GrabStatus::Success => true,
+            Ok(reply) => {
+                error!("failed to grab the keyboard: {:?}", reply.status());
+                false
+            }
+            Err(e) => {
+                error!("failed to grab the keyboard: {e:?}");
+                false
+            }
+        }
+    }
+
+    fn ungrab_keyboard(&self) {
+        trace_result!(
+            self.conn.send_and_check_request(&UngrabKeyboard { time: CURRENT_TIME });
+            "failed to ungrab the keyboard"
+        );
+    }
+
+    /// executes a command from the control socket, returning the reply
+    fn run_command(&mut self, command: ipc::Command) -> String {
+        match command {
+            ipc::Command::SwitchWorkspace(id) => match self.screen.switch_to_workspace_id(id) {
+                Ok(true) => {}
+                Ok(false) => return format!("error: there is no workspace {id}"),
+                Err(e) => return format!("error: {e:?}"),
+            },
+            ipc::Command::Close => self.screen.close_focused_window(),
+            ipc::Command::CycleLayout => self.screen.cycle_layout(),
+            ipc::Command::SetLayout(layout) => self.screen.set_layout(layout),
+            ipc::Command::SetBorderSize(size) => self.screen.set_border_size(size),
+            ipc::Command::GetState => return self.screen.state_json(),
+        }
+        "ok".to_string()
     }
 
-    pub fn run(&mut self, actions: &[Action]) -> anyhow::Result<()> {
-        let bound_actions = self.keyboard.bind_actions(actions, &self.conn, self.root);
-        println!("{bound_actions:?}");
+    /// runs the window manager until it quits. returns true if it should be restarted
+    pub fn run(&mut self, mut actions: Vec<Action>) -> anyhow::Result<bool> {
+        let mut bound_actions = self.keyboard.bind_actions(&actions, &self.conn, self.root);
+        let mouse_actions = actions::MOUSE_ACTIONS;
+        let bound_mouse_actions = self.bind_mouse_actions(mouse_actions);
+        self.grab_buttons();
+        debug!("bound actions: {bound_actions:?}");
         let mut procs = vec![];
+        // the programs from the first start are still running after a restart
+        if std::env::var_os(RESTARTED_VAR).is_none() {
+            autostart(&mut procs);
+        }
+        let mut chord: Option<ChordState> = None;
         let (event_transmitter, event_receiver) = std::sync::mpsc::channel();
-        println!("{:?}", self.atoms);
+        trace!("atoms: {:?}", self.atoms);
+
+        self.screen.draw_bar();
+
+        {
+            let event_transmitter = event_transmitter.clone();
+            trace_result!(signals::listen(&[libc::SIGHUP, libc::SIGCHLD], move |signal| {
+                let event = match signal {
+                    libc::SIGCHLD => Event::ChildExited,
+                    _ => Event::ReloadConfig,
+                };
+                _ = event_transmitter.send(LoopEvent::Internal(event));
+            }); "failed to install the signal handlers");
+        }
+        // children that exited before the handler was installed
+        reap_children(&mut procs);
 
-        // self.screen.draw_bar();
+        match config::get_socket_path() {
+            Ok(path) => {
+                let event_transmitter = event_transmitter.clone();
+                trace_result!(ipc::listen(&path, move |command| {
+                    let (reply, reply_receiver) = std::sync::mpsc::channel();
+                    _ = event_transmitter.send(LoopEvent::Internal(Event::Command { command, reply }));
+                    reply_receiver
+                        .recv_timeout(Duration::from_secs(1))
+                        .unwrap_or_else(|_| "error: the window manager didn't respond".to_string())
+                }); "failed to open the control socket");
+            }
+            Err(e) => error!("failed to get the control socket path: {e:?}"),
+        }
 
         {
             let conn = self.conn.clone();
             std::thread::spawn(move || loop {
-                match conn.wait_for_event() {
-                    Ok(ev) => {
-                        if let Err(_) = event_transmitter.send(ev) {
-                            drop(event_transmitter);
-                            std::process::abort();
-                        }
+                let event = match conn.wait_for_event() {
+                    Ok(ev) => LoopEvent::X(ev),
+                    // the error of a request nobody checked, the connection is still fine
+                    Err(xcb::Error::Protocol(e)) => {
+                        error!("unhandled error from the X server: {e:?}");
+                        continue;
                     }
-                    Err(e) => {
-                        println!("{e:?}");
-                        drop(event_transmitter);
-                        std::process::abort();
+                    Err(xcb::Error::Connection(e)) => {
+                        _ = event_transmitter.send(LoopEvent::ConnectionLost(e));
+                        return;
                     }
+                };
+                // the main loop is gone
+                if event_transmitter.send(event).is_err() {
+                    return;
                 }
             });
         };
 
+        let mut connection_lost = None;
         'mainloop: loop {
-            // wait half a second for each thread before updating the status bar
+            // wait half a second for each thread before updating the clock
             let ev = match event_receiver.recv_timeout(Duration::from_millis(500)) {
+                Ok(LoopEvent::ConnectionLost(e)) => {
+                    connection_lost = Some(e);
+                    break 'mainloop;
+                }
                 Ok(v) => Some(v),
                 Err(RecvTimeoutError::Timeout) => None,
                 Err(RecvTimeoutError::Disconnected) => break 'mainloop,
             };
+            let timed_out = ev.is_none();
+
+            if chord
+                .as_ref()
+                .is_some_and(|chord| chord.last_key.elapsed() > config::CHORD_TIMEOUT)
+            {
+                chord = None;
+                self.ungrab_keyboard();
+            }
 
-            if let Some(ev) = self.translate_event(ev) {
+            let ev = self.translate_event(ev);
+            // moving the mouse or a program exiting doesn't change anything that is displayed in the
+            // bar
+            let redraw_bar = !matches!(ev, Some(Event::MouseMove { .. } | Event::ChildExited));
+
+            if let Some(ev) = ev {
                 match ev {
                     Event::KeyPress(ev) => {
-                        for action in bound_actions.iter() {
-                            if action.key == ev.keycode && action.modifiers == ev.mods {
-                                match actions[action.action_index].action {
-                                    ActionType::Quit => break 'mainloop,
-                                    ActionType::CycleLayout => self.screen.cycle_layout(),
-                                    ActionType::CloseFocusedWindow => {
-                                        self.screen.close_focused_window()
-                                    }
-                                    ActionType::SwitchToLayout(new_layout) => {
-                                        self.screen.set_layout(new_layout)
-                                    }
-                                    ActionType::Launch(cmd) => {
-                                        let mut command = Command::new(cmd);
-                                        command
-                                            .stdin(Stdio::null())
-                                            .stdout(Stdio::null())
-                                            .stderr(Stdio::null());
-                                        if let Some(display) = std::env::var_os("DISPLAY")
-                                            .and_then(|str| str.into_string().ok())
-                                        {
-                                            command.env("DISPLAY", display);
-                                        }
-                                        match command.spawn() {
-                                            Err(e) => {
-                                                error!("Failed to run Action: Failed to run Command: {e:?}")
-                                            }
-                                            Ok(child) => procs.push(child),
-                                        }
-                                    }
+                        let action_index = match chord.take() {
+                            // modifiers are pressed on the way to the next key of the sequence
+                            Some(state) if ev.key.is_modifier_key() => {
+                                chord = Some(state);
+                                None
+                            }
+                            Some(mut state) => {
+                                let progress = state.progress;
+                                state.candidates.retain(|&i| {
+                                    bound_actions[i].sequence.get(progress)
+                                        == Some(&(
+                                            ev.keycode,
+                                            self.keyboard.modifiers().without_locks(ev.mods),
+                                        ))
+                                });
+                                state.progress += 1;
+                                state.last_key = Instant::now();
+
+                                let finished =
+                                    state.candidates.iter().copied().find(|&i| {
+                                        bound_actions[i].sequence.len() == state.progress
+                                    });
+                                if finished.is_none() && !state.candidates.is_empty() {
+                                    chord = Some(state);
+                                } else {
+                                    self.ungrab_keyboard();
                                 }
+                                finished.map(|i| bound_actions[i].action_index)
+                            }
+                            None => {
+                                let candidates = (0..bound_actions.len())
+                                    .filter(|&i| {
+                                        bound_actions[i].key == ev.keycode
+                                            && bound_actions[i].modifiers
+                                                == self.keyboard.modifiers().without_locks(ev.mods)
+                                    })
+                                    .collect::<Vec<_>>();
+                                let single = candidates
+                                    .iter()
+                                    .copied()
+                                    .find(|&i| bound_actions[i].sequence.is_empty());
+                                // wait for the rest of the sequence with the keyboard grabbed
+                                if single.is_none()
+                                    && !candidates.is_empty()
+                                    && self.grab_keyboard()
+                                {
+                                    chord = Some(ChordState {
+                                        candidates,
+                                        progress: 0,
+                                        last_key: Instant::now(),
+                                    });
+                                }
+                                single.map(|i| bound_actions[i].action_index)
+                            }
+                        };
+
+                        if let Some(action_index) = action_index {
+                            if self.run_action(&actions[action_index].action, &mut procs) {
+                                break 'mainloop;
+                            }
+                        }
+                    }
+                    Event::KeyRelease(ev)
+                        if ev.key.is_modifier_key() && self.screen.cycling_mru() =>
+                    {
+                        self.screen.end_mru_cycle();
+                        self.ungrab_keyboard();
+                    }
+                    Event::KeyRelease(ev) if chord.is_none() => {
+                        let release = bound_actions
+                            .iter()
+                            .filter(|bound| {
+                                bound.key == ev.keycode
+                                    && bound.modifiers
+                                        == self.keyboard.modifiers().without_locks(ev.mods)
+                                    && bound.sequence.is_empty()
+                            })
+                            .find_map(|bound| actions[bound.action_index].release.clone());
+                        if let Some(release) = release {
+                            if self.run_action(&release, &mut procs) {
+                                break 'mainloop;
                             }
                         }
                     }
@@ -206,42 +741,189 @@ impl Wm {
                         }
                     }
                     Event::DestroyNotify(window) => self.screen.remove_window(window),
-                    Event::EnterNotify(window) => self.screen.enter_client(window),
+                    Event::UnmapNotify(window) => self.screen.unmap_window(window),
+                    Event::Expose(window) => self.screen.expose(window),
+                    Event::ConfigureRequest(request) => self.screen.configure_request(request),
+                    Event::PropertyNotify { window, atom } => {
+                        self.screen.property_notify(window, atom)
+                    }
+                    Event::ClientMessage { window, atom, data } => {
+                        trace_result!(self.screen.client_message(window, atom, data); "failed to handle a client message")
+                    }
+                    Event::ChildExited => reap_children(&mut procs),
+                    Event::ReloadConfig => {
+                        // ungrab everything first, the new config might not bind some keys anymore
+                        self.keyboard
+                            .unbind_actions(&bound_actions, &self.conn, self.root);
+                        if chord.take().is_some() {
+                            self.ungrab_keyboard();
+                        }
+                        actions = config::load_actions();
+                        config::load_settings(self.screen.config_mut());
+                        bound_actions = self.keyboard.bind_actions(&actions, &self.conn, self.root);
+                    }
+                    Event::KeymapChanged => {
+                        // the grabs use the keycodes of the old keymap
+                        self.keyboard
+                            .unbind_actions(&bound_actions, &self.conn, self.root);
+                        if chord.take().is_some() {
+                            self.ungrab_keyboard();
+                        }
+                        self.keyboard.reload_keymap(&self.conn);
+                        bound_actions = self.keyboard.bind_actions(&actions, &self.conn, self.root);
+                    }
+                    Event::EnterNotify { window, sequence } => {
+                        self.hovered_window = window;
+                        self.screen.enter_client(window, sequence)
+                    }
+                    Event::MouseScroll(direction) if self.hovered_window == self.root => {
+                        trace_result!(self.screen.cycle_workspace(direction); "failed to switch the workspace")
+                    }
+                    Event::ButtonPress { button, mods, .. }
+                        if bound_mouse_actions.iter().any(|bound| {
+                            bound.button == button
+                                && bound.modifiers == mouse_mods(mods, self.keyboard.modifiers())
+                        }) =>
+                    {
+                        let bound = bound_mouse_actions
+                            .iter()
+                            .find(|bound| {
+                                bound.button == button
+                                    && bound.modifiers
+                                        == mouse_mods(mods, self.keyboard.modifiers())
+                            })
+                            .expect("the guard found the action");
+                        if self.run_action(&mouse_actions[bound.action_index].action, &mut procs) {
+                            break 'mainloop;
+                        }
+                    }
+                    Event::ButtonPress {
+                        button,
+                        window,
+                        absolute_x,
+                        absolute_y,
+                        mods,
+                    } if mods.contains(KeyButMask::from_bits_truncate(
+                        self.keyboard
+                            .modifiers()
+                            .to_x_mods(config::DRAG_MODS)
+                            .bits(),
+                    )) =>
+                    {
+                        match button {
+                            MouseButton::Left => {
+                                self.start_drag(DragKind::Move, window, absolute_x, absolute_y)
+                            }
+                            MouseButton::Right => {
+                                self.start_drag(DragKind::Resize, window, absolute_x, absolute_y)
+                            }
+                            MouseButton::Middle | MouseButton::Back | MouseButton::Forward => {}
+                        }
+                    }
+                    // clicks in the gaps and on the borders end up on the root
+                    Event::ButtonPress {
+                        button: MouseButton::Left,
+                        absolute_x,
+                        absolute_y,
+                        ..
+                    } if self.screen.near_master_edge(absolute_x, absolute_y) => {
+                        self.dragging_master_edge = true
+                    }
+                    Event::ScreenChange { width, height } => self.screen.update_size(width, height),
+                    Event::BarClick { button, x } => {
+                        trace_result!(self.screen.click_bar(button, x); "failed to handle a click on the bar")
+                    }
+                    Event::ClientClick(window) => {
+                        self.screen.click_client(window);
+                        // the pointer is frozen until the click is passed on to the client
+                        trace_result!(self.conn.send_and_check_request(&AllowEvents {
+                            mode: Allow::ReplayPointer,
+                            time: CURRENT_TIME,
+                        }); "failed to replay the click");
+                    }
+                    Event::Command { command, reply } => {
+                        _ = reply.send(self.run_command(command));
+                    }
+                    Event::MouseMove {
+                        absolute_x,
+                        absolute_y,
+                        ..
+                    } => self.update_drag(absolute_x, absolute_y),
+                    Event::ButtonRelease(MouseButton::Left | MouseButton::Right) => {
+                        self.drag = None;
+                        self.dragging_master_edge = false;
+                    }
                     _ => {}
                 }
             }
 
-            // clean up child processes
-            let len = procs.len();
-            for i in 0..len {
-                let i = len - 1 - i;
-                // the process exited
-                if !matches!(procs[i].try_wait(), Ok(None)) {
-                    procs.remove(i);
-                }
+            // everything the event changed is published at once
+            self.screen.flush_atoms();
+            if timed_out {
+                self.screen.update_clock();
+            } else if redraw_bar {
+                self.screen.draw_bar();
+                self.screen.save_session();
             }
-
-            // self.screen.draw_bar();
         }
 
         self.keyboard
             .unbind_actions(&bound_actions, &self.conn, self.root);
+        self.unbind_mouse_actions(&bound_mouse_actions);
+        self.ungrab_buttons();
+        self.destroy_check_window();
+        if let Some(e) = connection_lost {
+            // the windows are gone with the connection, only the programs are left to clean up
+            for proc in procs.iter_mut() {
+                _ = proc.kill();
+            }
+            return Err(anyhow::Error::new(e).context("lost the connection to the X server"));
+        }
+        if self.restart || self.quit {
+            // the windows and programs outlive us, the new instance takes them over if there is one
+            self.screen.release_clients();
+            procs.clear();
+            return Ok(self.restart);
+        }
         self.screen.kill_children();
         for proc in procs.iter_mut() {
             _ = proc.kill();
         }
         procs.clear();
-        Ok(())
+        Ok(false)
+    }
+
+    /// whether the window was created by us: a frame, the bar or the check window
+    fn is_own_window(&self, window: Window) -> bool {
+        window == self.check_window || self.screen.is_bar(window) || self.screen.is_frame(window)
     }
 
-    fn translate_event(&self, event: Option<xcb::Event>) -> Option<Event> {
+    fn translate_event(&self, event: Option<LoopEvent>) -> Option<Event> {
         match event? {
+            LoopEvent::X(event) => self.translate_x_event(event),
+            LoopEvent::Internal(event) => Some(event),
+            LoopEvent::ConnectionLost(_) => None,
+        }
+    }
+
+    fn translate_x_event(&self, event: XcbEvent) -> Option<Event> {
+        match event {
             XcbEvent::X(XEvent::KeyPress(event)) => {
                 Some(self.keyboard.translate_event(event, true))
             }
             XcbEvent::X(XEvent::KeyRelease(event)) => {
                 Some(self.keyboard.translate_event(event, false))
             }
+            XcbEvent::X(XEvent::ButtonPress(btn)) if self.screen.is_bar(btn.event()) => {
+                Some(Event::BarClick {
+                    button: btn.detail(),
+                    x: btn.event_x(),
+                })
+            }
+            // only frames have their clicks grabbed besides the root
+            XcbEvent::X(XEvent::ButtonPress(btn)) if btn.event() != self.root => {
+                Some(Event::ClientClick(btn.event()))
+            }
             XcbEvent::X(XEvent::ButtonPress(btn)) if btn.detail() == 4 => {
                 Some(Event::MouseScroll(-1))
             }
@@ -251,9 +933,17 @@ impl Wm {
             XcbEvent::X(XEvent::ButtonRelease(btn)) if btn.detail() == 4 || btn.detail() == 5 => {
                 None
             }
-            XcbEvent::X(XEvent::ButtonPress(btn)) => MouseButton::try_from(btn.detail())
-                .ok()
-                .map(Event::ButtonPress),
+            XcbEvent::X(XEvent::ButtonPress(btn)) => {
+                MouseButton::try_from(btn.detail())
+                    .ok()
+                    .map(|button| Event::ButtonPress {
+                        button,
+                        window: (!btn.child().is_none()).then(|| btn.child()),
+                        absolute_x: btn.root_x(),
+                        absolute_y: btn.root_y(),
+                        mods: btn.state(),
+                    })
+            }
             XcbEvent::X(XEvent::ButtonRelease(btn)) => MouseButton::try_from(btn.detail())
                 .ok()
                 .map(Event::ButtonRelease),
@@ -265,18 +955,52 @@ impl Wm {
                 window_y: ev.event_y(),
             }),
 
-            XcbEvent::X(XEvent::EnterNotify(ev)) => Some(Event::EnterNotify(ev.event())),
+            XcbEvent::X(XEvent::EnterNotify(ev)) => Some(Event::EnterNotify {
+                window: ev.event(),
+                sequence: ev.sequence(),
+            }),
+            // our own windows are mapped, unmapped and destroyed by us, that isn't news
+            XcbEvent::X(XEvent::MapRequest(ev)) if self.is_own_window(ev.window()) => None,
+            XcbEvent::X(XEvent::DestroyNotify(ev)) if self.is_own_window(ev.window()) => None,
+            XcbEvent::X(XEvent::UnmapNotify(ev)) if self.is_own_window(ev.window()) => None,
             XcbEvent::X(XEvent::MapRequest(ev)) => Some(Event::MapRequest(ev.window())),
             XcbEvent::X(XEvent::DestroyNotify(ev)) => Some(Event::DestroyNotify(ev.window())),
-            XcbEvent::X(XEvent::ReparentNotify(_)) => None,
-            XcbEvent::X(XEvent::PropertyNotify(ev)) => {
-                println!(
-                    "Property changed for window {:?}: {:?}",
-                    ev.window(),
-                    ev.atom()
-                );
-                None
+            XcbEvent::X(XEvent::UnmapNotify(ev)) => Some(Event::UnmapNotify(ev.window())),
+            XcbEvent::X(XEvent::ConfigureRequest(ev)) => {
+                let mask = ev.value_mask();
+                Some(Event::ConfigureRequest(ConfigureRequest {
+                    window: ev.window(),
+                    x: mask.contains(ConfigWindowMask::X).then(|| ev.x()),
+                    y: mask.contains(ConfigWindowMask::Y).then(|| ev.y()),
+                    width: mask.contains(ConfigWindowMask::WIDTH).then(|| ev.width()),
+                    height: mask.contains(ConfigWindowMask::HEIGHT).then(|| ev.height()),
+                    border_width: mask
+                        .contains(ConfigWindowMask::BORDER_WIDTH)
+                        .then(|| ev.border_width()),
+                    sibling: mask
+                        .contains(ConfigWindowMask::SIBLING)
+                        .then(|| ev.sibling()),
+                    stack_mode: mask
+                        .contains(ConfigWindowMask::STACK_MODE)
+                        .then(|| ev.stack_mode()),
+                }))
             }
+            XcbEvent::X(XEvent::ClientMessage(ev)) => match ev.data() {
+                ClientMessageData::Data32(data) => Some(Event::ClientMessage {
+                    window: ev.window(),
+                    atom: ev.r#type(),
+                    data,
+                }),
+                // none of the messages we understand use 8 or 16 bit data
+                _ => None,
+            },
+            // only redraw once the last expose event of a series arrived
+            XcbEvent::X(XEvent::Expose(ev)) if ev.count() == 0 => Some(Event::Expose(ev.window())),
+            XcbEvent::X(XEvent::ReparentNotify(_)) => None,
+            XcbEvent::X(XEvent::PropertyNotify(ev)) => Some(Event::PropertyNotify {
+                window: ev.window(),
+                atom: ev.atom(),
+            }),
 
             XcbEvent::Xkb(xcb::xkb::Event::StateNotify(xkb_ev))
                 if xkb_ev.device_id() as i32 == self.keyboard.device_id() =>
@@ -284,6 +1008,25 @@ impl Wm {
                 self.keyboard.update_state(xkb_ev);
                 None
             }
+            XcbEvent::RandR(randr::Event::ScreenChangeNotify(ev)) => {
+                // the size is reported without the rotation applied
+                let rotated = ev
+                    .rotation()
+                    .intersects(randr::Rotation::ROTATE_90 | randr::Rotation::ROTATE_270);
+                let (width, height) = if rotated {
+                    (ev.height(), ev.width())
+                } else {
+                    (ev.width(), ev.height())
+                };
+                Some(Event::ScreenChange { width, height })
+            }
+            // the core keyboard was replaced, so its device id might have changed as well
+            XcbEvent::Xkb(xcb::xkb::Event::NewKeyboardNotify(_)) => Some(Event::KeymapChanged),
+            XcbEvent::Xkb(xcb::xkb::Event::MapNotify(xkb_ev))
+                if xkb_ev.device_id() as i32 == self.keyboard.device_id() =>
+            {
+                Some(Event::KeymapChanged)
+            }
             e => {
                 //println!("{e:?}");
                 None
@@ -291,3 +1034,129 @@ impl Wm {
         }
     }
 }
+
+/// the modifiers of a button event without the lock modifiers and the pressed buttons
+fn mouse_mods(mods: KeyButMask, modifiers: ModifierMap) -> XModMask {
+    modifiers.without_locks(XModMask::from_bits_truncate(mods.bits())) & XModMask::ANY.complement()
+}
+
+/// a void cookie of libxcb
+#[repr(C)]
+struct VoidCookie {
+    sequence: libc::c_uint,
+}
+
+// xcb's `ButtonIndex` ends at the scroll wheel, so the grabs for the other buttons go through
+// libxcb directly
+extern "C" {
+    fn xcb_grab_button_checked(
+        c: *mut xcb::ffi::xcb_connection_t,
+        owner_events: u8,
+        grab_window: u32,
+        event_mask: u16,
+        pointer_mode: u8,
+        keyboard_mode: u8,
+        confine_to: u32,
+        cursor: u32,
+        button: u8,
+        modifiers: u16,
+    ) -> VoidCookie;
+    fn xcb_ungrab_button_checked(
+        c: *mut xcb::ffi::xcb_connection_t,
+        button: u8,
+        grab_window: u32,
+        modifiers: u16,
+    ) -> VoidCookie;
+    fn xcb_request_check(
+        c: *mut xcb::ffi::xcb_connection_t,
+        cookie: VoidCookie,
+    ) -> *mut libc::c_void;
+}
+
+/// grabs or ungrabs `button` on `window`. returns false if the server refused
+fn set_button_grab(
+    conn: &Connection,
+    window: Window,
+    button: u8,
+    modifiers: XModMask,
+    grab: bool,
+) -> bool {
+    let c = conn.get_raw_conn();
+    unsafe {
+        let cookie = if grab {
+            xcb_grab_button_checked(
+                c,
+                0,
+                window.resource_id(),
+                (EventMask::BUTTON_PRESS | EventMask::BUTTON_RELEASE).bits() as u16,
+                GrabMode::Async as u8,
+                GrabMode::Async as u8,
+                Window::none().resource_id(),
+                Cursor::none().resource_id(),
+                button,
+                modifiers.bits() as u16,
+            )
+        } else {
+            xcb_ungrab_button_checked(c, button, window.resource_id(), modifiers.bits() as u16)
+        };
+        let error = xcb_request_check(c, cookie);
+        if error.is_null() {
+            return true;
+        }
+        libc::free(error);
+        false
+    }
+}
+
+/// waits for every child that exited, including the ones a previous instance started before
+/// restarting, so that none of them stay around as zombies
+fn reap_children(procs: &mut Vec<Child>) {
+    loop {
+        let pid = unsafe { libc::waitpid(-1, std::ptr::null_mut(), libc::WNOHANG) };
+        if pid <= 0 {
+            break;
+        }
+        procs.retain(|proc| proc.id() != pid as u32);
+    }
+}
+
+/// set for the new instance when restarting
+static RESTARTED_VAR: &str = "WM_RESTARTED";
+
+/// starts the programs of `config::AUTOSTART`
+fn autostart(procs: &mut Vec<Child>) {
+    for command in config::AUTOSTART {
+        let Some((program, args)) = command.split_first() else {
+            continue;
+        };
+        let mut command = Command::new(program);
+        command.args(args);
+        spawn(command, procs);
+    }
+}
+
+fn spawn(mut command: Command, procs: &mut Vec<Child>) {
+    command
+        .env_remove(RESTARTED_VAR)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    if let Some(display) = std::env::var_os("DISPLAY").and_then(|str| str.into_string().ok()) {
+        command.env("DISPLAY", display);
+    }
+    match command.spawn() {
+        Err(e) => {
+            error!("Failed to run Action: Failed to run Command: {e:?}")
+        }
+        Ok(child) => procs.push(child),
+    }
+}
+
+/// replaces the process with a new instance of the window manager. only returns on failure
+pub fn restart() -> anyhow::Error {
+    let error = Command::new("/proc/self/exe")
+        .args(std::env::args_os().skip(1))
+        .env(RESTARTED_VAR, "1")
+        .exec();
+    anyhow::Error::new(error).context("Failed to restart the window manager")
+}